@@ -0,0 +1,152 @@
+#import bevy_pbr::{
+    forward_io::{FragmentOutput, VertexOutput},
+    mesh_functions,
+    mesh_view_bindings::globals,
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    view_transformations::position_world_to_clip,
+}
+
+@group(2) @binding(100) var array_texture: texture_2d_array<f32>;
+@group(2) @binding(101) var array_sampler: sampler;
+@group(2) @binding(102) var<uniform> ao_strength: f32;
+
+// Matches `material::face_shades`: faces along each axis, then against it.
+@group(2) @binding(103) var<uniform> face_shades: array<vec4<f32>, 2>;
+
+// Tiles per second that liquid surfaces drift along u.
+const LIQUID_SCROLL_SPEED: f32 = 0.05;
+
+// Matches `light::MIN_BRIGHTNESS`.
+const MIN_BRIGHTNESS: f32 = 0.1;
+
+// Indexed by `Face::index`.
+const FACE_NORMALS = array<vec3<f32>, 6>(
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, -1.0, 0.0),
+    vec3(1.0, 0.0, 0.0),
+    vec3(-1.0, 0.0, 0.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 0.0, -1.0),
+);
+
+// Matches `Face::tangent`.
+const FACE_TANGENTS = array<vec4<f32>, 6>(
+    vec4(0.0, 0.0, 1.0, 1.0),
+    vec4(0.0, 0.0, 1.0, -1.0),
+    vec4(0.0, 0.0, -1.0, 1.0),
+    vec4(0.0, 0.0, -1.0, -1.0),
+    vec4(1.0, 0.0, 0.0, 1.0),
+    vec4(1.0, 0.0, 0.0, -1.0),
+);
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+#ifdef PACKED_VERTICES
+    @location(1) packed: u32,
+#ifdef VERTEX_LIGHT
+    @location(6) light: u32,
+#endif
+#else
+    @location(1) normal: vec3<f32>,
+#ifdef VERTEX_UVS_A
+    @location(2) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_UVS_B
+    @location(3) uv_b: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(4) tangent: vec4<f32>,
+#endif
+#ifdef VERTEX_COLORS
+    @location(5) color: vec4<f32>,
+#endif
+#endif
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let world_from_local = mesh_functions::get_world_from_local(vertex.instance_index);
+    out.world_position =
+        mesh_functions::mesh_position_local_to_world(world_from_local, vec4(vertex.position, 1.0));
+    out.position = position_world_to_clip(out.world_position.xyz);
+
+#ifdef PACKED_VERTICES
+    // Matches `PackedVertex::pack`.
+    let normal = FACE_NORMALS[vertex.packed & 7u];
+    let tangent = FACE_TANGENTS[vertex.packed & 7u];
+    let ao = f32((vertex.packed >> 3u) & 3u);
+    var shade = 1.0 - ao_strength * (3.0 - ao) / 3.0;
+    let face_shade = select(face_shades[1].xyz, face_shades[0].xyz, normal > vec3(0.0));
+    shade *= dot(abs(normal), face_shade);
+#ifdef VERTEX_LIGHT
+    // Matches `light::brightness`.
+    let level = f32(max((vertex.light >> 4u) & 15u, vertex.light & 15u)) / 15.0;
+    shade *= MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS) * level;
+#endif
+    out.uv = vec2(f32((vertex.packed >> 5u) & 31u), f32((vertex.packed >> 10u) & 31u));
+    if (vertex.packed >> 31u) != 0u {
+        out.uv.x += globals.time * LIQUID_SCROLL_SPEED;
+    }
+    out.uv_b = vec2(f32((vertex.packed >> 15u) & 65535u), 0.0);
+    out.color = vec4(shade, shade, shade, 1.0);
+#else
+    let normal = vertex.normal;
+#ifdef VERTEX_UVS_A
+    out.uv = vertex.uv;
+#endif
+#ifdef VERTEX_UVS_B
+    out.uv_b = vertex.uv_b;
+#endif
+#ifdef VERTEX_TANGENTS
+    let tangent = vertex.tangent;
+#endif
+#ifdef VERTEX_COLORS
+    out.color = vertex.color;
+#endif
+#endif
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(normal, vertex.instance_index);
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = mesh_functions::mesh_tangent_local_to_world(
+        world_from_local,
+        tangent,
+        vertex.instance_index,
+    );
+#endif
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    // UVs count tiles across the face, so wrap them inside the layer. Gradients come from the
+    // unwrapped UVs to keep mip selection continuous across the wrap.
+    var wrapped = in;
+    wrapped.uv = fract(in.uv);
+    var pbr_input = pbr_input_from_standard_material(wrapped, is_front);
+
+    let layer = i32(in.uv_b.x + 0.5);
+    let color = textureSampleGrad(
+        array_texture,
+        array_sampler,
+        fract(in.uv),
+        layer,
+        dpdx(in.uv),
+        dpdy(in.uv),
+    );
+    pbr_input.material.base_color *= color;
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+
+    return out;
+}
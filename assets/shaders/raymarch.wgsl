@@ -0,0 +1,103 @@
+#import bevy_pbr::{
+    forward_io::VertexOutput,
+    mesh_view_bindings::view,
+    view_transformations::position_world_to_clip,
+}
+
+@group(2) @binding(0) var voxels: texture_3d<u32>;
+// World position of the volume's minimum corner, and the voxel size in `w`.
+@group(2) @binding(1) var<uniform> min_corner: vec4<f32>;
+
+// Enough steps to cross the volume's diagonal.
+const MAX_STEPS: i32 = 128;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+// A stable color per voxel id, in place of textures.
+fn id_color(id: u32) -> vec3<f32> {
+    let hash = id * 2654435761u;
+    let rgb = vec3(hash & 255u, (hash >> 8u) & 255u, (hash >> 16u) & 255u);
+    return vec3<f32>(rgb) / 255.0 * 0.7 + 0.3;
+}
+
+// Fixed shading in place of lighting, brightest on top and darkest underneath.
+fn face_shade(normal: vec3<f32>) -> f32 {
+    if normal.y > 0.5 {
+        return 1.0;
+    } else if normal.y < -0.5 {
+        return 0.5;
+    } else if abs(normal.z) > 0.5 {
+        return 0.8;
+    }
+    return 0.6;
+}
+
+// Walks the voxel grid from the camera through the fragment with a DDA, one cell per step, and
+// shades the first voxel that isn't air. The proxy box is drawn from behind, so fragments exist
+// even with the camera inside the volume.
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    let dims = vec3<i32>(textureDimensions(voxels));
+    let size = min_corner.w;
+    let origin = (view.world_position - min_corner.xyz) / size;
+    var dir = normalize(in.world_position.xyz - view.world_position);
+    dir = select(dir, vec3(1e-6), abs(dir) < vec3(1e-6));
+    let inv = 1.0 / dir;
+
+    let t0 = -origin * inv;
+    let t1 = (vec3<f32>(dims) - origin) * inv;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    let enter = max(max(max(near.x, near.y), near.z), 0.0);
+    let exit = min(min(far.x, far.y), far.z);
+    if enter > exit {
+        discard;
+    }
+
+    let start = origin + dir * (enter + 1e-4);
+    var cell = clamp(vec3<i32>(floor(start)), vec3(0), dims - 1);
+    let step = vec3<i32>(sign(dir));
+    let delta = abs(inv);
+    var side = (vec3<f32>(cell) + select(vec3(0.0), vec3(1.0), dir > vec3(0.0)) - origin) * inv;
+    var t = enter;
+    var normal = -vec3<f32>(step) * vec3<f32>(near == vec3(enter));
+
+    for (var i = 0; i < MAX_STEPS; i++) {
+        if any(cell < vec3(0)) || any(cell >= dims) {
+            break;
+        }
+
+        let id = textureLoad(voxels, cell, 0).r;
+        if id != 0u {
+            let hit = min_corner.xyz + (origin + dir * t) * size;
+            let clip = position_world_to_clip(hit);
+
+            var out: FragmentOutput;
+            out.color = vec4(id_color(id) * face_shade(normal), 1.0);
+            out.depth = clip.z / clip.w;
+            return out;
+        }
+
+        if side.x < side.y && side.x < side.z {
+            t = side.x;
+            cell.x += step.x;
+            side.x += delta.x;
+            normal = vec3(-f32(step.x), 0.0, 0.0);
+        } else if side.y < side.z {
+            t = side.y;
+            cell.y += step.y;
+            side.y += delta.y;
+            normal = vec3(0.0, -f32(step.y), 0.0);
+        } else {
+            t = side.z;
+            cell.z += step.z;
+            side.z += delta.z;
+            normal = vec3(0.0, 0.0, -f32(step.z));
+        }
+    }
+
+    discard;
+}
@@ -0,0 +1,43 @@
+#import bevy_pbr::view_transformations::position_world_to_clip
+
+@group(2) @binding(0) var array_texture: texture_2d_array<f32>;
+@group(2) @binding(1) var array_sampler: sampler;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    // Matches `VoxelInstance`.
+    @location(8) i_position: vec3<f32>,
+    @location(9) i_layer: u32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) layer: u32,
+    @location(2) shade: f32,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+    // Instances are in world space, so the mesh transform is skipped entirely.
+    out.clip_position = position_world_to_clip(vertex.position + vertex.i_position);
+    out.uv = vertex.uv;
+    out.layer = vertex.i_layer;
+    // Fixed shading in place of lighting, brightest on top and darkest underneath.
+    out.shade = 0.8 + 0.2 * vertex.normal.y;
+
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(array_texture, array_sampler, in.uv, in.layer);
+    if color.a < 0.5 {
+        discard;
+    }
+
+    return vec4(color.rgb * in.shade, 1.0);
+}
@@ -0,0 +1,169 @@
+use bevy::math::IVec3;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::time::Duration;
+use voxel::{
+    atlas::AtlasLayout,
+    chunk::{Chunk, ChunkData, ChunkStorage},
+    generation,
+    mesh::{
+        self, ChunkMeshers, ChunkNeighborhood, ChunkSnapshot, FaceShading, MeshBufferPool,
+        MeshContext, UvLayout, WindingOrder,
+    },
+    registry::VoxelRegistry,
+    stats::ChunkMeshStats,
+    surface_nets::SurfaceNets,
+    voxel::Voxel,
+};
+
+/// A cubic chunk of `size` with stone wherever `filled` says.
+fn filled_chunk(
+    registry: &VoxelRegistry,
+    size: u32,
+    filled: fn(usize, usize, usize) -> bool,
+) -> ChunkData {
+    let stone = Voxel::new(registry.id("stone").expect("default registry has stone"));
+    let mut data = ChunkData::cubic(size);
+    for z in 0..size as usize {
+        for y in 0..size as usize {
+            for x in 0..size as usize {
+                if filled(x, y, z) {
+                    data.set(x, y, z, stone);
+                }
+            }
+        }
+    }
+    data
+}
+
+/// Every voxel is exposed on all six sides, the most quads any chunk can produce.
+fn checkerboard(x: usize, y: usize, z: usize) -> bool {
+    (x + y + z).is_multiple_of(2)
+}
+
+/// The chunks every mesher is measured on, from cheapest to most expensive to mesh.
+fn bench_chunks(registry: &VoxelRegistry) -> Vec<(&'static str, ChunkStorage)> {
+    let size = Chunk::SIZE as u32;
+
+    // The demo terrain is generated from fixed seeds, so the surface is the same every run.
+    let mut terrain = ChunkData::cubic(size);
+    generation::generate_chunk(&mut terrain, IVec3::ZERO, registry);
+
+    [
+        ("empty", ChunkData::cubic(size)),
+        ("full", filled_chunk(registry, size, |_, _, _| true)),
+        ("terrain", terrain),
+        ("checkerboard", filled_chunk(registry, size, checkerboard)),
+    ]
+    .map(|(name, data)| (name, data.into()))
+    .to_vec()
+}
+
+/// `center` surrounded by copies of itself on every side, as if the world repeated it.
+fn neighborhood(center: &ChunkStorage) -> ChunkNeighborhood<'_> {
+    ChunkNeighborhood {
+        center,
+        neighbors: [Some(center); 6],
+        diagonals: [Some(center); 20],
+    }
+}
+
+fn context<'a>(registry: &'a VoxelRegistry, uvs: UvLayout<'a>) -> MeshContext<'a> {
+    MeshContext {
+        registry,
+        uvs,
+        ao_strength: 1.0,
+        face_shading: FaceShading::default(),
+        skirt_depth: None,
+        lod: 1,
+        neighbor_lods: [1; 6],
+        winding: WindingOrder::default(),
+        light: None,
+    }
+}
+
+fn meshers() -> ChunkMeshers {
+    let mut meshers = ChunkMeshers::default();
+    meshers.register(SurfaceNets);
+    meshers
+}
+
+/// Meshes every bench chunk with every mesher in both vertex formats, reporting throughput in
+/// output quads so meshers producing fewer, larger quads compare fairly.
+fn bench_meshers(c: &mut Criterion) {
+    let registry = VoxelRegistry::default();
+    let atlas = AtlasLayout::default();
+    let meshers = meshers();
+    let chunks = bench_chunks(&registry);
+
+    for (uvs, layout) in [
+        ("atlas", UvLayout::Atlas(&atlas)),
+        ("packed", UvLayout::Array),
+    ] {
+        let context = context(&registry, layout);
+
+        for mesher in meshers.iter() {
+            let mut group = c.benchmark_group(format!("mesh/{}/{uvs}", mesher.name()));
+            for (name, chunk) in &chunks {
+                let chunks = neighborhood(chunk);
+                let mut pool = MeshBufferPool::default();
+                let meshes = mesh::mesh_chunk(&**mesher, chunks, &context, &mut pool);
+                let stats = ChunkMeshStats::new(&meshes, Duration::ZERO);
+                let (quads, bytes) = (stats.quads, stats.vertex_bytes);
+                println!(
+                    "{} {uvs} {name}: {quads} quads, {bytes} vertex bytes",
+                    mesher.name()
+                );
+
+                group.throughput(Throughput::Elements(quads.max(1) as u64));
+                group.bench_function(BenchmarkId::from_parameter(name), |b| {
+                    b.iter(|| mesh::mesh_chunk(&**mesher, black_box(chunks), &context, &mut pool))
+                });
+            }
+            group.finish();
+        }
+    }
+}
+
+/// Copies a chunk and the borders of its 26 neighbors out for meshing, as every meshing task
+/// does before it starts.
+fn bench_snapshot(c: &mut Criterion) {
+    let registry = VoxelRegistry::default();
+    let mut group = c.benchmark_group("snapshot");
+    for (name, chunk) in &bench_chunks(&registry) {
+        let chunks = neighborhood(chunk);
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| ChunkSnapshot::new(black_box(chunks)))
+        });
+    }
+    group.finish();
+}
+
+/// Meshes a dense checkerboard chunk whose power-of-two dims index voxels by shifting against
+/// chunks a voxel smaller and larger, which index by multiplying. Throughput is in voxels, so
+/// the sizes compare fairly.
+fn bench_indexing(c: &mut Criterion) {
+    let registry = VoxelRegistry::default();
+    let mesher = ChunkMeshers::default().active().clone();
+    let context = context(&registry, UvLayout::Array);
+    let size = Chunk::SIZE as u32;
+
+    let mut group = c.benchmark_group("indexing");
+    for (name, size) in [
+        ("shift", size),
+        ("multiply", size - 1),
+        ("multiply", size + 1),
+    ] {
+        let chunk: ChunkStorage = filled_chunk(&registry, size, checkerboard).into();
+        let chunks = neighborhood(&chunk);
+        let mut pool = MeshBufferPool::default();
+
+        group.throughput(Throughput::Elements(size.pow(3) as u64));
+        group.bench_function(BenchmarkId::new(name, size), |b| {
+            b.iter(|| mesh::mesh_chunk(&*mesher, black_box(chunks), &context, &mut pool))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_meshers, bench_snapshot, bench_indexing);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+use bevy::math::UVec3;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use voxel::{
+    chunk::{Chunk, ChunkData, ChunkStorage, SparseChunk, VoxelStorage},
+    voxel::Voxel,
+};
+
+/// The system allocator, keeping count of the bytes currently allocated through it.
+struct Counting;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LIVE.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Chunks along each axis of the measured world.
+const WORLD_CHUNKS: usize = 8;
+
+/// A chunk with every hundredth voxel filled, spread over the whole chunk.
+fn one_percent(sparse: bool) -> ChunkStorage {
+    let size = Chunk::SIZE;
+    let mut chunk: ChunkStorage = if sparse {
+        SparseChunk::new(UVec3::splat(size as u32)).into()
+    } else {
+        ChunkData::cubic(size as u32).into()
+    };
+    for i in (0..size.pow(3)).step_by(100) {
+        chunk.set(i % size, i / size % size, i / (size * size), Voxel::new(1));
+    }
+    chunk
+}
+
+/// Heap bytes held by whatever `build` returns.
+fn heap_bytes<T>(build: impl FnOnce() -> T) -> usize {
+    let before = LIVE.load(Ordering::Relaxed);
+    let value = build();
+    let bytes = LIVE.load(Ordering::Relaxed) - before;
+    drop(value);
+    bytes
+}
+
+/// Reports the memory of a world of [`WORLD_CHUNKS`]³ chunks 1% full in either backing, then
+/// how long reading every voxel of one takes, since sparse storage trades speed for memory.
+fn bench_storage(c: &mut Criterion) {
+    let chunks = WORLD_CHUNKS.pow(3);
+    for (name, sparse) in [("dense", false), ("sparse", true)] {
+        let chunk = heap_bytes(|| one_percent(sparse));
+        let world = heap_bytes(|| (0..chunks).map(|_| one_percent(sparse)).collect::<Vec<_>>());
+        println!("{name}: {chunk} bytes per 1% full chunk, {world} bytes for {chunks} chunks");
+    }
+
+    let mut group = c.benchmark_group("storage/read");
+    for (name, sparse) in [("dense", false), ("sparse", true)] {
+        let chunk = one_percent(sparse);
+        let size = Chunk::SIZE;
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| {
+                let chunk = black_box(&chunk);
+                (0..size.pow(3))
+                    .filter(|i| {
+                        let voxel = chunk.get(i % size, i / size % size, i / (size * size));
+                        voxel.is_some_and(|voxel| voxel.id != 0)
+                    })
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_storage);
+criterion_main!(benches);
@@ -0,0 +1,96 @@
+use bevy::{
+    ecs::system::Resource,
+    math::{Rect, UVec2, Vec2},
+};
+
+/// How tiles are laid out in the block texture atlas.
+///
+/// Tiles are numbered row by row from the top left. Each tile may be surrounded by `padding`
+/// pixels of bleed border, and UV rects are inset by half a pixel so filtering never samples the
+/// neighboring tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct AtlasLayout {
+    pub columns: u32,
+    pub rows: u32,
+    /// Size of one tile in pixels, not counting padding.
+    pub tile_size: UVec2,
+    /// Pixels of padding on each side of every tile.
+    pub padding: u32,
+}
+
+impl Default for AtlasLayout {
+    /// The layout of `array_texture.png`: sixteen 16×16 tiles stacked vertically.
+    fn default() -> Self {
+        Self {
+            columns: 1,
+            rows: 16,
+            tile_size: UVec2::splat(16),
+            padding: 0,
+        }
+    }
+}
+
+impl AtlasLayout {
+    /// Size of the whole atlas in pixels.
+    #[inline]
+    pub fn size(&self) -> UVec2 {
+        (self.tile_size + UVec2::splat(self.padding * 2)) * UVec2::new(self.columns, self.rows)
+    }
+
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// UV rect of tile `index`, inset by half a pixel.
+    pub fn tile_rect(&self, index: u32) -> Rect {
+        let stride = self.tile_size + UVec2::splat(self.padding * 2);
+        let cell = UVec2::new(index % self.columns, index / self.columns);
+        let min = (cell * stride + UVec2::splat(self.padding)).as_vec2() + Vec2::splat(0.5);
+        let max = min + self.tile_size.as_vec2() - Vec2::ONE;
+
+        let size = self.size().as_vec2();
+        Rect::from_corners(min / size, max / size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_rects_are_inset_by_half_a_pixel() {
+        let atlas = AtlasLayout::default();
+        let first = atlas.tile_rect(0);
+        assert_eq!(first.min, Vec2::new(0.5 / 16.0, 0.5 / 256.0));
+        assert_eq!(first.max, Vec2::new(15.5 / 16.0, 15.5 / 256.0));
+
+        let last = atlas.tile_rect(atlas.len() - 1);
+        assert_eq!(last.min, Vec2::new(0.5 / 16.0, 240.5 / 256.0));
+        assert_eq!(last.max, Vec2::new(15.5 / 16.0, 255.5 / 256.0));
+    }
+
+    #[test]
+    fn non_square_padded_atlas() {
+        let atlas = AtlasLayout {
+            columns: 4,
+            rows: 2,
+            tile_size: UVec2::new(16, 8),
+            padding: 2,
+        };
+        assert_eq!(atlas.size(), UVec2::new(80, 24));
+
+        // Second row, second column: past one padded tile on each axis, plus its own padding.
+        let rect = atlas.tile_rect(5);
+        assert_eq!(rect.min, Vec2::new(22.5 / 80.0, 14.5 / 24.0));
+        assert_eq!(rect.max, Vec2::new(37.5 / 80.0, 21.5 / 24.0));
+
+        let last = atlas.tile_rect(atlas.len() - 1);
+        assert_eq!(last.max, Vec2::new(77.5 / 80.0, 21.5 / 24.0));
+    }
+}
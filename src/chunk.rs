@@ -1,37 +1,1120 @@
-use crate::voxel::Voxel;
-use bevy::{ecs::component::Component, math::Vec3};
+use crate::{
+    mesh::MeshPass,
+    voxel::{Voxel, VoxelId},
+    world::ChunkPos,
+};
+use bevy::{
+    asset::Handle,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        event::{Event, EventWriter},
+        removal_detection::RemovedComponents,
+        system::{Commands, Query},
+    },
+    math::{IVec3, UVec3, Vec3},
+    render::mesh::Mesh,
+};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
 
+/// Read and write access to a grid of voxels, whatever its backing.
+pub trait VoxelStorage {
+    /// Number of voxels along each axis.
+    fn dims(&self) -> UVec3;
+
+    /// The voxel at a local coordinate, or `None` if it is out of bounds.
+    fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel>;
+
+    /// Writes a voxel. Out of bounds writes are ignored.
+    fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel);
+
+    /// Overwrites every voxel with `value`.
+    fn fill(&mut self, value: Voxel);
+
+    /// Every voxel that isn't air with its local coordinate, in increasing x, then y, then z
+    /// order, so meshes built by iterating don't depend on the backing.
+    fn iter(&self) -> impl Iterator<Item = (UVec3, Voxel)> + '_;
+}
+
+/// Voxel storage of a chunk, independent of the ECS so it can be handed to background tasks,
+/// save files and tests.
+///
+/// Indexing shifts rather than multiplies when the width and height are powers of two, as they
+/// are for [`Chunk::SIZE`]. Other dims work too, through the general path, which meshes no
+/// measurably slower; see the `indexing` bench.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkData {
+    voxels: Vec<Voxel>,
+    dims: UVec3,
+    /// `log2` of the width and of the width times the height, if both are powers of two.
+    shifts: Option<(u32, u32)>,
+}
+
+impl ChunkData {
+    /// Creates chunk data of `dims` filled with air.
+    #[inline]
+    pub fn new(dims: UVec3) -> Self {
+        let shifts = (dims.x.is_power_of_two() && dims.y.is_power_of_two())
+            .then(|| (dims.x.ilog2(), dims.x.ilog2() + dims.y.ilog2()));
+
+        Self {
+            voxels: vec![Voxel::AIR; (dims.x * dims.y * dims.z) as usize],
+            dims,
+            shifts,
+        }
+    }
+
+    /// Like [`new`](Self::new), but fails unless every axis of `dims` is a power of two, for
+    /// callers relying on the fast indexing path.
+    pub fn new_pow2(dims: UVec3) -> Result<Self, NotPowerOfTwo> {
+        if dims.to_array().iter().all(|axis| axis.is_power_of_two()) {
+            Ok(Self::new(dims))
+        } else {
+            Err(NotPowerOfTwo { dims })
+        }
+    }
+
+    #[inline]
+    pub fn cubic(size: u32) -> Self {
+        Self::new(UVec3::splat(size))
+    }
+
+    /// Number of voxels along each axis.
+    #[inline]
+    pub const fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    /// All voxels in linear `x`, then `y`, then `z` order.
+    #[inline]
+    pub fn voxels(&self) -> &[Voxel] {
+        &self.voxels
+    }
+
+    #[inline]
+    pub fn voxels_mut(&mut self) -> &mut [Voxel] {
+        &mut self.voxels
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
+        if self.in_bounds(x, y, z) {
+            self.voxels.get(self.linearize(x, y, z))
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
+        if self.in_bounds(x, y, z) {
+            let i = self.linearize(x, y, z);
+            self.voxels[i] = value;
+        }
+    }
+
+    #[inline]
+    const fn in_bounds(&self, x: usize, y: usize, z: usize) -> bool {
+        in_bounds(self.dims, x, y, z)
+    }
+
+    /// Index of an in-bounds coordinate in [`voxels`](Self::voxels).
+    #[inline]
+    const fn linearize(&self, x: usize, y: usize, z: usize) -> usize {
+        match self.shifts {
+            Some((y_shift, z_shift)) => (z << z_shift) | (y << y_shift) | x,
+            None => linearize(self.dims, x, y, z),
+        }
+    }
+}
+
+impl VoxelStorage for ChunkData {
+    #[inline]
+    fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    #[inline]
+    fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
+        ChunkData::get(self, x, y, z)
+    }
+
+    #[inline]
+    fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
+        ChunkData::set(self, x, y, z, value);
+    }
+
+    #[inline]
+    fn fill(&mut self, value: Voxel) {
+        self.voxels.fill(value);
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (UVec3, Voxel)> + '_ {
+        self.voxels
+            .iter()
+            .enumerate()
+            .filter(|(_, voxel)| voxel.id != 0)
+            .map(|(i, voxel)| (delinearize(self.dims, i), *voxel))
+    }
+}
+
+/// Voxel storage holding only non-air voxels, keyed by the same linear index as [`ChunkData`].
+/// Much smaller than dense storage for chunks that are almost entirely air.
+///
+/// Voxels are kept ordered by index, so iterating visits them in the same order as dense storage
+/// and meshes built from either come out identical.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseChunk {
+    voxels: BTreeMap<u32, Voxel>,
+    dims: UVec3,
+}
+
+impl SparseChunk {
+    /// Creates sparse storage of `dims` filled with air.
+    #[inline]
+    pub fn new(dims: UVec3) -> Self {
+        Self {
+            voxels: BTreeMap::new(),
+            dims,
+        }
+    }
+
+    pub fn from_dense(data: &ChunkData) -> Self {
+        let voxels = data
+            .voxels()
+            .iter()
+            .enumerate()
+            .filter(|(_, voxel)| voxel.id != 0)
+            .map(|(i, voxel)| (i as u32, *voxel))
+            .collect();
+
+        Self {
+            voxels,
+            dims: data.dims(),
+        }
+    }
+
+    pub fn to_dense(&self) -> ChunkData {
+        let mut data = ChunkData::new(self.dims);
+        let voxels = data.voxels_mut();
+        self.voxels
+            .iter()
+            .for_each(|(i, voxel)| voxels[*i as usize] = *voxel);
+
+        data
+    }
+
+    /// Number of non-air voxels.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
+        if !in_bounds(self.dims, x, y, z) {
+            return None;
+        }
+
+        let i = linearize(self.dims, x, y, z) as u32;
+        Some(self.voxels.get(&i).unwrap_or(&Voxel::AIR))
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
+        if !in_bounds(self.dims, x, y, z) {
+            return;
+        }
+
+        let i = linearize(self.dims, x, y, z) as u32;
+        if value.id == 0 {
+            self.voxels.remove(&i);
+        } else {
+            self.voxels.insert(i, value);
+        }
+    }
+}
+
+impl VoxelStorage for SparseChunk {
+    #[inline]
+    fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    #[inline]
+    fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
+        SparseChunk::get(self, x, y, z)
+    }
+
+    #[inline]
+    fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
+        SparseChunk::set(self, x, y, z, value);
+    }
+
+    fn fill(&mut self, value: Voxel) {
+        self.voxels.clear();
+        if value.id != 0 {
+            let len = self.dims.x * self.dims.y * self.dims.z;
+            self.voxels.extend((0..len).map(|i| (i, value)));
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (UVec3, Voxel)> + '_ {
+        self.voxels
+            .iter()
+            .map(|(i, voxel)| (delinearize(self.dims, *i as usize), *voxel))
+    }
+}
+
+/// Dense or sparse voxel storage, switching between the two by fill ratio.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkStorage {
+    Dense(ChunkData),
+    Sparse(SparseChunk),
+}
+
+impl ChunkStorage {
+    /// Dense storage whose fill ratio drops below this becomes sparse in
+    /// [`optimize`](Self::optimize).
+    pub const SPARSE_BELOW: f32 = 1.0 / 32.0;
+    /// Sparse storage whose fill ratio rises above this becomes dense as soon as it is written.
+    /// Kept above [`SPARSE_BELOW`](Self::SPARSE_BELOW) so chunks near the threshold don't
+    /// flip back and forth.
+    pub const DENSE_ABOVE: f32 = 1.0 / 16.0;
+
+    /// Fraction of voxels that aren't air.
+    pub fn fill_ratio(&self) -> f32 {
+        let dims = self.dims();
+        let total = (dims.x * dims.y * dims.z).max(1) as f32;
+        let filled = match self {
+            Self::Dense(data) => data.voxels().iter().filter(|voxel| voxel.id != 0).count(),
+            Self::Sparse(sparse) => sparse.len(),
+        };
+
+        filled as f32 / total
+    }
+
+    /// Switches to whichever backing suits the current fill ratio. Scans every voxel of dense
+    /// storage, so it is meant for load time rather than every edit.
+    pub fn optimize(&mut self) {
+        let ratio = self.fill_ratio();
+        match self {
+            Self::Dense(data) if ratio < Self::SPARSE_BELOW => {
+                *self = Self::Sparse(SparseChunk::from_dense(data));
+            }
+            Self::Sparse(sparse) if ratio > Self::DENSE_ABOVE => {
+                *self = Self::Dense(sparse.to_dense());
+            }
+            _ => {}
+        }
+    }
+
+    /// Dense storage, converting sparse storage in place.
+    pub fn make_dense(&mut self) -> &mut ChunkData {
+        if let Self::Sparse(sparse) = self {
+            *self = Self::Dense(sparse.to_dense());
+        }
+
+        match self {
+            Self::Dense(data) => data,
+            Self::Sparse(_) => unreachable!(),
+        }
+    }
+
+    /// A dense copy of the voxels, e.g. for encoding.
+    pub fn to_dense(&self) -> ChunkData {
+        match self {
+            Self::Dense(data) => data.clone(),
+            Self::Sparse(sparse) => sparse.to_dense(),
+        }
+    }
+}
+
+impl From<ChunkData> for ChunkStorage {
+    #[inline]
+    fn from(data: ChunkData) -> Self {
+        Self::Dense(data)
+    }
+}
+
+impl From<SparseChunk> for ChunkStorage {
+    #[inline]
+    fn from(sparse: SparseChunk) -> Self {
+        Self::Sparse(sparse)
+    }
+}
+
+impl VoxelStorage for ChunkStorage {
+    #[inline]
+    fn dims(&self) -> UVec3 {
+        match self {
+            Self::Dense(data) => data.dims(),
+            Self::Sparse(sparse) => sparse.dims,
+        }
+    }
+
+    #[inline]
+    fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
+        match self {
+            Self::Dense(data) => data.get(x, y, z),
+            Self::Sparse(sparse) => sparse.get(x, y, z),
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
+        match self {
+            Self::Dense(data) => data.set(x, y, z, value),
+            Self::Sparse(sparse) => {
+                sparse.set(x, y, z, value);
+                if self.fill_ratio() > Self::DENSE_ABOVE {
+                    self.make_dense();
+                }
+            }
+        }
+    }
+
+    /// Fills with air by switching to empty sparse storage, and with anything else by switching
+    /// to dense storage.
+    fn fill(&mut self, value: Voxel) {
+        let dims = self.dims();
+        if value.id == 0 {
+            *self = Self::Sparse(SparseChunk::new(dims));
+        } else {
+            self.make_dense().fill(value);
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (UVec3, Voxel)> + '_ {
+        let (dense, sparse) = match self {
+            Self::Dense(data) => (Some(data.iter()), None),
+            Self::Sparse(sparse) => (None, Some(sparse.iter())),
+        };
+
+        dense
+            .into_iter()
+            .flatten()
+            .chain(sparse.into_iter().flatten())
+    }
+}
+
+#[inline]
+const fn in_bounds(dims: UVec3, x: usize, y: usize, z: usize) -> bool {
+    x < dims.x as usize && y < dims.y as usize && z < dims.z as usize
+}
+
+#[inline]
+const fn linearize(dims: UVec3, x: usize, y: usize, z: usize) -> usize {
+    let (width, height) = (dims.x as usize, dims.y as usize);
+
+    (z * width * height) + (y * width) + x
+}
+
+#[inline]
+const fn delinearize(dims: UVec3, i: usize) -> UVec3 {
+    let (width, height) = (dims.x as usize, dims.y as usize);
+
+    UVec3::new(
+        (i % width) as u32,
+        (i / width % height) as u32,
+        (i / (width * height)) as u32,
+    )
+}
+
+/// A local coordinate outside a chunk's dimensions, returned by [`Chunk::try_set`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBounds {
+    pub position: [usize; 3],
+    pub dims: UVec3,
+}
+
+impl fmt::Display for OutOfBounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [x, y, z] = self.position;
+        write!(f, "({x}, {y}, {z}) is outside a chunk of {}", self.dims)
+    }
+}
+
+impl Error for OutOfBounds {}
+
+/// Dims with an axis that isn't a power of two, returned by [`ChunkData::new_pow2`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotPowerOfTwo {
+    pub dims: UVec3,
+}
+
+impl fmt::Display for NotPowerOfTwo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "chunk dims {} aren't all powers of two", self.dims)
+    }
+}
+
+impl Error for NotPowerOfTwo {}
+
+/// Sent by [`send_chunk_modified`] once a frame for every chunk whose voxels changed, e.g. by an
+/// edit, a tick or generation, so systems like remeshing and lighting can react to changes
+/// without the code making them knowing about those systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct ChunkModified {
+    pub chunk: Entity,
+    pub coord: ChunkPos,
+    /// Inclusive min and max local coordinates of the voxels that changed, or `None` if any of
+    /// them may have, e.g. for a newly generated or loaded chunk.
+    pub region: Option<(UVec3, UVec3)>,
+}
+
+/// Voxels of a chunk changed since its last [`ChunkModified`].
+#[derive(Debug, Clone, Copy)]
+enum Modified {
+    Region(UVec3, UVec3),
+    All,
+}
+
+/// A chunk of the world: its voxel storage plus the ECS-side state needed to render it.
 #[derive(Debug, Component)]
 pub struct Chunk {
-    voxels: Vec<Voxel>,
+    data: ChunkStorage,
     pub position: Vec3,
+    /// Set when the voxels change, see [`ChunkModified`], or the way they are meshed does, until
+    /// the chunk is remeshed.
+    dirty: bool,
+    /// Set alongside `dirty` for edits that should be remeshed ahead of everything else.
+    urgent: bool,
+    /// Voxels changed since the last [`ChunkModified`] was sent.
+    modified: Option<Modified>,
+    /// Factor the chunk is downsampled by for meshing, see
+    /// [`LodSettings`](crate::mesh::LodSettings).
+    lod: u32,
+    /// Counts the times the chunk was marked dirty for anything but a level of detail change.
+    mesh_revision: u64,
+    /// Mesh of the opaque pass, `None` while it is empty.
+    pub mesh: Option<Handle<Mesh>>,
+    /// Mesh of the translucent pass, `None` while it is empty.
+    pub translucent_mesh: Option<Handle<Mesh>>,
 }
 
 impl Chunk {
+    /// Edge length of the default cubic chunk.
     pub const SIZE: usize = 16;
 
+    /// Creates an empty cubic chunk of [`Chunk::SIZE`].
     #[inline]
     pub fn new(position: Vec3) -> Self {
+        Self::cubic(position, Self::SIZE as u32)
+    }
+
+    #[inline]
+    pub fn cubic(position: Vec3, size: u32) -> Self {
+        Self::from_data(position, ChunkData::cubic(size))
+    }
+
+    #[inline]
+    pub fn with_dims(position: Vec3, dims: UVec3) -> Self {
+        Self::from_data(position, ChunkData::new(dims))
+    }
+
+    /// Wraps existing data, e.g. produced by a background task, in whichever storage suits its
+    /// fill ratio. The chunk starts dirty.
+    pub fn from_data(position: Vec3, data: impl Into<ChunkStorage>) -> Self {
+        let mut data = data.into();
+        data.optimize();
+
         Self {
-            voxels: vec![Voxel { id: 0 }; Self::SIZE * Self::SIZE * Self::SIZE],
+            data,
             position,
+            dirty: true,
+            urgent: false,
+            modified: Some(Modified::All),
+            lod: 1,
+            mesh_revision: 0,
+            mesh: None,
+            translucent_mesh: None,
         }
     }
 
+    #[inline]
+    pub fn data(&self) -> &ChunkStorage {
+        &self.data
+    }
+
+    /// Mutable access to the voxel storage. Marks the whole chunk modified.
+    #[inline]
+    pub fn data_mut(&mut self) -> &mut ChunkStorage {
+        self.modified = Some(Modified::All);
+        &mut self.data
+    }
+
+    #[inline]
+    pub fn dims(&self) -> UVec3 {
+        self.data.dims()
+    }
+
+    /// Chunk coordinate of this chunk, the key it is indexed by in
+    /// [`VoxelWorld`](crate::world::VoxelWorld).
+    #[inline]
+    pub fn chunk_pos(&self) -> ChunkPos {
+        ChunkPos(self.position.as_ivec3())
+    }
+
+    /// World voxel coordinate of this chunk's `(0, 0, 0)` voxel.
+    #[inline]
+    pub fn origin(&self) -> IVec3 {
+        self.position.as_ivec3() * self.dims().as_ivec3()
+    }
+
+    /// Converts a world voxel coordinate into local coordinates, if it lies inside this chunk.
+    pub fn local(&self, position: IVec3) -> Option<(usize, usize, usize)> {
+        let local = position - self.origin();
+        let inside = local.cmpge(IVec3::ZERO).all() && local.cmplt(self.dims().as_ivec3()).all();
+
+        inside.then_some((local.x as usize, local.y as usize, local.z as usize))
+    }
+
+    /// Mutable access to the raw voxel data, e.g. for remapping ids of loaded chunks. Converts
+    /// sparse storage to dense and marks the whole chunk modified.
+    #[inline]
+    pub fn voxels_mut(&mut self) -> &mut [Voxel] {
+        self.data_mut().make_dense().voxels_mut()
+    }
+
     #[inline]
     pub fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
-        self.voxels.get(Self::linearize(x, y, z))
+        self.data.get(x, y, z)
+    }
+
+    /// Read-modify-write access to the voxel at a local coordinate, or `None` if it is out of
+    /// bounds. See [`VoxelMut`] for when the change is written.
+    #[inline]
+    pub fn get_mut(&mut self, x: usize, y: usize, z: usize) -> Option<VoxelMut<'_>> {
+        let voxel = *self.get(x, y, z)?;
+        Some(VoxelMut {
+            chunk: self,
+            pos: (x, y, z),
+            original: voxel,
+            voxel,
+        })
+    }
+
+    /// Local y of the topmost non-air voxel of the column at `(x, z)`, or `None` if the column is
+    /// all air or outside the chunk.
+    pub fn surface_height(&self, x: usize, z: usize) -> Option<usize> {
+        (0..self.dims().y as usize)
+            .rev()
+            .find(|&y| self.get(x, y, z).is_some_and(|voxel| voxel.id != 0))
     }
 
+    /// Whether a local coordinate lies inside the chunk.
+    #[inline]
+    pub fn contains(&self, x: usize, y: usize, z: usize) -> bool {
+        in_bounds(self.dims(), x, y, z)
+    }
+
+    /// Writes a voxel, adding it to the chunk's modified region. Out of bounds writes are
+    /// silently dropped, so prefer this only where coordinates are in range by construction, e.g.
+    /// loops over [`dims`](Self::dims).
+    #[inline]
     pub fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
-        if x < Self::SIZE && y < Self::SIZE && z < Self::SIZE {
-            let i = Self::linearize(x, y, z);
-            self.voxels[i] = value;
+        if !self.contains(x, y, z) {
+            return;
         }
+
+        self.data.set(x, y, z, value);
+        let pos = UVec3::new(x as u32, y as u32, z as u32);
+        self.grow_modified(pos, pos);
+    }
+
+    /// Runs `f` with a [`ChunkEditor`] for many writes at once, e.g. a fill tool or generation
+    /// pass. The writes skip per-voxel bookkeeping and are added to the chunk's modified region
+    /// as one box once `f` returns, so however many there are the chunk is remeshed once.
+    pub fn edit_batch<R>(&mut self, f: impl FnOnce(&mut ChunkEditor) -> R) -> R {
+        let mut editor = ChunkEditor {
+            dims: self.dims(),
+            data: &mut self.data,
+            bounds: None,
+        };
+        let result = f(&mut editor);
+        if let Some((min, max)) = editor.bounds {
+            self.grow_modified(min, max);
+        }
+
+        result
+    }
+
+    fn grow_modified(&mut self, min: UVec3, max: UVec3) {
+        self.modified = Some(match self.modified {
+            None => Modified::Region(min, max),
+            Some(Modified::Region(lo, hi)) => Modified::Region(lo.min(min), hi.max(max)),
+            Some(Modified::All) => Modified::All,
+        });
+    }
+
+    /// Like [`set`](Self::set), but reports out of bounds writes instead of dropping them. Prefer
+    /// it where a coordinate comes from outside the chunk, e.g. player edits, and a miss is a
+    /// bug worth surfacing.
+    pub fn try_set(
+        &mut self,
+        x: usize,
+        y: usize,
+        z: usize,
+        value: Voxel,
+    ) -> Result<(), OutOfBounds> {
+        if !self.contains(x, y, z) {
+            return Err(OutOfBounds {
+                position: [x, y, z],
+                dims: self.dims(),
+            });
+        }
+
+        self.set(x, y, z, value);
+        Ok(())
+    }
+
+    /// Like [`set`](Self::set), but clamps each coordinate into the chunk first. Prefer it in
+    /// generation code whose computed coordinates may stray slightly out of range, where writing
+    /// the nearest voxel is the intended behavior.
+    pub fn set_clamped(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
+        let dims = self.dims();
+        let clamp = |value: usize, size: u32| value.min(size.saturating_sub(1) as usize);
+        self.set(clamp(x, dims.x), clamp(y, dims.y), clamp(z, dims.z), value);
+    }
+
+    /// Sets every voxel to air, returning how many weren't already. The chunk is only marked
+    /// modified if any changed.
+    pub fn clear(&mut self) -> usize {
+        let changed = self.data.iter().count();
+        if changed > 0 {
+            self.data_mut().fill(Voxel::AIR);
+        }
+
+        changed
+    }
+
+    /// Replaces every voxel of id `from` with `to`, e.g. to retexture a selection, returning how
+    /// many were replaced. Replaced voxels keep their orientation, unless they become air. The
+    /// chunk is only marked modified if any were.
+    pub fn swap_ids(&mut self, from: VoxelId, to: VoxelId) -> usize {
+        if from == to {
+            return 0;
+        }
+
+        // Storage iterators skip air, so swapping air away has to visit every position.
+        let positions: Vec<(UVec3, Voxel)> = if from == 0 {
+            let dims = self.dims();
+            (0..(dims.x * dims.y * dims.z) as usize)
+                .filter_map(|i| {
+                    let pos = delinearize(dims, i);
+                    let [x, y, z] = pos.to_array().map(|c| c as usize);
+                    let voxel = self.get(x, y, z).filter(|voxel| voxel.id == 0)?;
+                    Some((pos, *voxel))
+                })
+                .collect()
+        } else {
+            self.data
+                .iter()
+                .filter(|(_, voxel)| voxel.id == from)
+                .collect()
+        };
+
+        self.edit_batch(|editor| {
+            positions.iter().for_each(|(pos, voxel)| {
+                let [x, y, z] = pos.to_array().map(|c| c as usize);
+                let swapped = match to {
+                    0 => Voxel::AIR,
+                    _ => Voxel::new(to).with_orientation(voxel.orientation()),
+                };
+                editor.set(x, y, z, swapped);
+            });
+        });
+        if to == 0 {
+            self.data.optimize();
+        }
+
+        positions.len()
     }
 
     #[inline]
-    const fn linearize(x: usize, y: usize, z: usize) -> usize {
-        (z * Self::SIZE * Self::SIZE) + (y * Self::SIZE) + x
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    #[inline]
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.mesh_revision += 1;
+    }
+
+    #[inline]
+    pub fn is_urgent(&self) -> bool {
+        self.urgent
+    }
+
+    /// Marks the chunk dirty and has it remeshed before other dirty chunks, regardless of the
+    /// [`MeshingBudget`](crate::mesh::MeshingBudget), e.g. after the player edits it.
+    #[inline]
+    pub fn mark_urgent(&mut self) {
+        self.mark_dirty();
+        self.urgent = true;
+    }
+
+    /// Changes whenever the chunk is marked dirty, except by [`set_lod`](Self::set_lod), so
+    /// meshes built at another level of detail can be told apart from stale ones by it.
+    #[inline]
+    pub fn mesh_revision(&self) -> u64 {
+        self.mesh_revision
+    }
+
+    /// The [`ChunkModified`] of the voxels changed since the last one, if any, for the chunk on
+    /// `entity`.
+    pub fn take_modified(&mut self, entity: Entity) -> Option<ChunkModified> {
+        let region = match self.modified.take()? {
+            Modified::Region(min, max) if min != UVec3::ZERO || max != self.dims() - 1 => {
+                Some((min, max))
+            }
+            _ => None,
+        };
+
+        Some(ChunkModified {
+            chunk: entity,
+            coord: self.chunk_pos(),
+            region,
+        })
+    }
+
+    /// Factor the chunk is meshed at, `1` for full resolution.
+    #[inline]
+    pub fn lod(&self) -> u32 {
+        self.lod
+    }
+
+    /// Changes the factor the chunk is meshed at, marking it dirty if it differs.
+    #[inline]
+    pub fn set_lod(&mut self, lod: u32) {
+        if lod != self.lod {
+            self.lod = lod;
+            self.dirty = true;
+        }
+    }
+
+    /// The mesh handle of `pass`.
+    #[inline]
+    pub fn mesh_mut(&mut self, pass: MeshPass) -> &mut Option<Handle<Mesh>> {
+        match pass {
+            MeshPass::Opaque => &mut self.mesh,
+            MeshPass::Translucent => &mut self.translucent_mesh,
+        }
+    }
+
+    /// Clears the dirty and urgent flags once the chunk's mesh is up to date.
+    #[inline]
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+        self.urgent = false;
+    }
+}
+
+/// Chunks compare by content: the same dims and the same voxel at every coordinate. Nothing else
+/// participates, neither whether the voxels are stored dense or sparse, nor the chunk's position,
+/// nor its ECS-side state (its dirty and urgent flags, modified region, level of detail and mesh
+/// handles), so e.g. every all-air chunk of the same dims is equal.
+impl PartialEq for Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.dims() == other.dims()
+            && self.data.iter().count() == other.data.iter().count()
+            && self.data.iter().all(|(pos, voxel)| {
+                let [x, y, z] = pos.to_array().map(|c| c as usize);
+                other.get(x, y, z) == Some(&voxel)
+            })
+    }
+}
+
+impl Eq for Chunk {}
+
+/// Hashes what [`PartialEq`] compares: the dims, then every voxel that isn't air with its
+/// coordinate, in the backing-independent order of [`VoxelStorage::iter`].
+impl Hash for Chunk {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.dims().hash(state);
+        self.data.iter().for_each(|entry| entry.hash(state));
+    }
+}
+
+/// Tags an entity spawned to render a chunk with that chunk's entity, so everything belonging to
+/// a chunk can be despawned with it by [`despawn_chunk_children`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct ChunkChild(pub Entity);
+
+/// Writes voxels of a chunk during [`Chunk::edit_batch`], only tracking the bounding box of what
+/// it wrote.
+pub struct ChunkEditor<'a> {
+    data: &'a mut ChunkStorage,
+    dims: UVec3,
+    /// Inclusive min and max of the voxels written so far.
+    bounds: Option<(UVec3, UVec3)>,
+}
+
+impl ChunkEditor<'_> {
+    #[inline]
+    pub fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    #[inline]
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<&Voxel> {
+        self.data.get(x, y, z)
+    }
+
+    /// Writes a voxel. Out of bounds writes are silently dropped, like [`Chunk::set`].
+    #[inline]
+    pub fn set(&mut self, x: usize, y: usize, z: usize, value: Voxel) {
+        if !in_bounds(self.dims, x, y, z) {
+            return;
+        }
+
+        self.data.set(x, y, z, value);
+        let pos = UVec3::new(x as u32, y as u32, z as u32);
+        self.bounds = Some(match self.bounds {
+            None => (pos, pos),
+            Some((min, max)) => (min.min(pos), max.max(pos)),
+        });
+    }
+}
+
+/// Sends a [`ChunkModified`] for every chunk whose voxels changed since the last run.
+pub fn send_chunk_modified(
+    mut chunks: Query<(Entity, &mut Chunk)>,
+    mut events: EventWriter<ChunkModified>,
+) {
+    for (entity, mut chunk) in &mut chunks {
+        // Only take a mutable borrow when there is something to take, to keep change detection
+        // quiet for unchanged chunks.
+        if chunk.modified.is_some() {
+            events.send_batch(chunk.take_modified(entity));
+        }
+    }
+}
+
+/// A voxel of a [`Chunk`] borrowed by [`Chunk::get_mut`], dereferencing to the voxel.
+///
+/// Sparse chunks don't store air, so there isn't always a voxel to point into. The guard holds
+/// a copy instead and writes it back with [`Chunk::set`] when dropped, only if it ended up
+/// different from what was read. Changes reach the modified region like any other write, and
+/// reading through the guard, or writing the same value back, never remeshes the chunk.
+#[derive(Debug)]
+pub struct VoxelMut<'a> {
+    chunk: &'a mut Chunk,
+    pos: (usize, usize, usize),
+    original: Voxel,
+    voxel: Voxel,
+}
+
+impl Deref for VoxelMut<'_> {
+    type Target = Voxel;
+
+    #[inline]
+    fn deref(&self) -> &Voxel {
+        &self.voxel
+    }
+}
+
+impl DerefMut for VoxelMut<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Voxel {
+        &mut self.voxel
+    }
+}
+
+impl Drop for VoxelMut<'_> {
+    fn drop(&mut self) {
+        if self.voxel != self.original {
+            let (x, y, z) = self.pos;
+            self.chunk.set(x, y, z, self.voxel);
+        }
+    }
+}
+
+/// Despawns the [`ChunkChild`] entities of chunks that were despawned or lost their [`Chunk`].
+pub fn despawn_chunk_children(
+    mut commands: Commands,
+    mut removed: RemovedComponents<Chunk>,
+    children: Query<(Entity, &ChunkChild)>,
+) {
+    let removed: Vec<_> = removed.read().collect();
+    if removed.is_empty() {
+        return;
+    }
+
+    children
+        .iter()
+        .filter(|(_, ChunkChild(chunk))| removed.contains(chunk))
+        .for_each(|(entity, _)| commands.entity(entity).despawn());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tall and thin, with every coordinate fitting a distinct id.
+    const TALL: UVec3 = UVec3::new(16, 256, 16);
+
+    /// A voxel whose id encodes its coordinate in a [`TALL`] chunk.
+    fn voxel_at(x: usize, y: usize, z: usize) -> Voxel {
+        Voxel::new((x + y * 16 + z * 16 * 256) as VoxelId)
+    }
+
+    fn coords(dims: UVec3) -> impl Iterator<Item = (usize, usize, usize)> {
+        let [w, h, d] = dims.to_array().map(|axis| axis as usize);
+        (0..d).flat_map(move |z| (0..h).flat_map(move |y| (0..w).map(move |x| (x, y, z))))
+    }
+
+    #[test]
+    fn tall_chunk_round_trips_every_coordinate() {
+        let mut data = ChunkData::new(TALL);
+        coords(TALL).for_each(|(x, y, z)| data.set(x, y, z, voxel_at(x, y, z)));
+
+        for (i, (x, y, z)) in coords(TALL).enumerate() {
+            assert_eq!(data.get(x, y, z), Some(&voxel_at(x, y, z)));
+            assert_eq!(data.voxels()[i], voxel_at(x, y, z));
+            assert_eq!(
+                delinearize(TALL, i),
+                UVec3::new(x as u32, y as u32, z as u32)
+            );
+        }
+        assert_eq!(data.get(16, 0, 0), None);
+        assert_eq!(data.get(0, 256, 0), None);
+        assert_eq!(data.get(0, 0, 16), None);
+    }
+
+    #[test]
+    fn shifted_indexing_matches_general_indexing() {
+        let data = ChunkData::new(TALL);
+        assert!(data.shifts.is_some());
+        for (x, y, z) in coords(TALL) {
+            assert_eq!(data.linearize(x, y, z), linearize(TALL, x, y, z));
+        }
+
+        let dims = UVec3::new(5, 7, 3);
+        let mut data = ChunkData::new(dims);
+        assert!(data.shifts.is_none());
+        coords(dims).for_each(|(x, y, z)| data.set(x, y, z, voxel_at(x, y, z)));
+        coords(dims).for_each(|(x, y, z)| assert_eq!(data.get(x, y, z), Some(&voxel_at(x, y, z))));
+    }
+
+    #[test]
+    fn sparse_and_chunk_storage_index_like_dense() {
+        let mut chunk = Chunk::with_dims(Vec3::ZERO, TALL);
+        let mut sparse = SparseChunk::new(TALL);
+        let written = [(1, 0, 0), (15, 0, 0), (3, 200, 9), (15, 255, 15)];
+        for (x, y, z) in written {
+            chunk.set(x, y, z, voxel_at(x, y, z));
+            sparse.set(x, y, z, voxel_at(x, y, z));
+        }
+
+        for (x, y, z) in written {
+            assert_eq!(chunk.get(x, y, z), Some(&voxel_at(x, y, z)));
+            assert_eq!(sparse.get(x, y, z), Some(&voxel_at(x, y, z)));
+        }
+        assert_eq!(chunk.get(2, 0, 0), Some(&Voxel::AIR));
+        assert_eq!(chunk.dims(), TALL);
+        assert_eq!(sparse.to_dense(), chunk.data().to_dense());
+    }
+
+    /// A chunk with `count` voxels of id 1 and one of id 2, already reported as modified.
+    fn edited_chunk(count: usize) -> Chunk {
+        let mut chunk = Chunk::new(Vec3::ZERO);
+        (0..count).for_each(|x| chunk.set(x, 0, 0, Voxel::new(1)));
+        chunk.set(0, 1, 0, Voxel::new(2));
+        chunk.take_modified(Entity::PLACEHOLDER);
+        chunk
+    }
+
+    #[test]
+    fn clear_counts_the_voxels_it_removed() {
+        let mut chunk = edited_chunk(5);
+        assert_eq!(chunk.clear(), 6);
+        assert!(chunk.data().iter().next().is_none());
+        assert!(chunk.take_modified(Entity::PLACEHOLDER).is_some());
+
+        assert_eq!(chunk.clear(), 0);
+        assert!(chunk.take_modified(Entity::PLACEHOLDER).is_none());
+    }
+
+    #[test]
+    fn swap_ids_counts_the_voxels_it_replaced() {
+        use crate::voxel::Orientation;
+
+        let mut chunk = edited_chunk(5);
+        assert_eq!(chunk.swap_ids(1, 3), 5);
+        assert_eq!(chunk.get(4, 0, 0), Some(&Voxel::new(3)));
+        assert_eq!(chunk.get(0, 1, 0), Some(&Voxel::new(2)));
+        assert!(chunk.take_modified(Entity::PLACEHOLDER).is_some());
+
+        // Nothing left to replace, or replacing an id with itself, leaves the chunk unmodified.
+        assert_eq!(chunk.swap_ids(1, 4), 0);
+        assert_eq!(chunk.swap_ids(2, 2), 0);
+        assert!(chunk.take_modified(Entity::PLACEHOLDER).is_none());
+
+        // Logs on their side stay on their side.
+        let log = Voxel::new(3).with_orientation(Orientation::PosX);
+        chunk.set(4, 0, 0, log);
+        assert_eq!(chunk.swap_ids(3, 5), 5);
+        let swapped = Voxel::new(5).with_orientation(Orientation::PosX);
+        assert_eq!(chunk.get(4, 0, 0), Some(&swapped));
+        assert_eq!(chunk.swap_ids(5, 3), 5);
+
+        // Air isn't stored by sparse chunks, but is swapped all the same.
+        let voxels = Chunk::SIZE.pow(3);
+        assert_eq!(chunk.swap_ids(0, 1), voxels - 6);
+        assert_eq!(chunk.swap_ids(1, 0), voxels - 6);
+        assert_eq!(chunk.data().iter().count(), 6);
+    }
+
+    fn hash_of(chunk: &Chunk) -> u64 {
+        let mut hasher = std::hash::DefaultHasher::new();
+        chunk.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn equal_chunks_hash_equal() {
+        use crate::voxel::Orientation;
+
+        let mut dense = Chunk::new(Vec3::ZERO);
+        dense.data_mut().make_dense();
+        dense.set(3, 4, 5, Voxel::new(2));
+        // Air turned another way is still air to `PartialEq`.
+        dense.set(0, 0, 0, Voxel::AIR.with_orientation(Orientation::NegX));
+        let mut sparse = Chunk::from_data(Vec3::X, SparseChunk::new(dense.dims()));
+        sparse.set(3, 4, 5, Voxel::new(2));
+        assert!(matches!(dense.data(), ChunkStorage::Dense(_)));
+        assert!(matches!(sparse.data(), ChunkStorage::Sparse(_)));
+
+        assert_eq!(dense, sparse);
+        assert_eq!(hash_of(&dense), hash_of(&sparse));
+
+        sparse.set(3, 4, 6, Voxel::new(2));
+        assert_ne!(dense, sparse);
+        assert_ne!(hash_of(&dense), hash_of(&sparse));
+    }
+
+    #[test]
+    fn surface_height_finds_the_topmost_solid_voxel() {
+        let mut chunk = Chunk::cubic(Vec3::ZERO, 16);
+        (0..16).for_each(|y| chunk.set(1, y, 0, Voxel::new(1)));
+        (0..4)
+            .chain([7])
+            .for_each(|y| chunk.set(2, y, 0, Voxel::new(1)));
+
+        assert_eq!(chunk.surface_height(0, 0), None);
+        assert_eq!(chunk.surface_height(1, 0), Some(15));
+        assert_eq!(chunk.surface_height(2, 0), Some(7));
+        assert_eq!(chunk.surface_height(16, 0), None);
     }
 }
@@ -0,0 +1,259 @@
+use crate::{
+    chunk::ChunkData,
+    voxel::{Orientation, Voxel},
+};
+use bevy::math::UVec3;
+use std::io;
+
+/// Chunk voxels stored as a palette of the distinct voxels in the chunk plus a packed index into it
+/// per voxel, using the fewest of 1, 2, 4, 8 or 16 bits that fit the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PalettedChunk {
+    dims: UVec3,
+    palette: Vec<Voxel>,
+    bits: u32,
+    /// Indices packed from the least significant bit up; widths divide 64, so no index spans
+    /// two words.
+    words: Vec<u64>,
+}
+
+impl PalettedChunk {
+    pub fn encode(data: &ChunkData) -> Self {
+        let mut palette: Vec<Voxel> = Vec::new();
+        let indices: Vec<u64> = data
+            .voxels()
+            .iter()
+            .map(|voxel| {
+                let index = palette
+                    .iter()
+                    .position(|entry| entry == voxel)
+                    .unwrap_or_else(|| {
+                        palette.push(*voxel);
+                        palette.len() - 1
+                    });
+                index as u64
+            })
+            .collect();
+
+        let bits = bits_for(palette.len());
+        let per_word = (u64::BITS / bits) as usize;
+        let words = indices
+            .chunks(per_word)
+            .map(|indices| {
+                indices
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (i, index)| word | index << (i as u32 * bits))
+            })
+            .collect();
+
+        Self {
+            dims: data.dims(),
+            palette,
+            bits,
+            words,
+        }
+    }
+
+    pub fn decode(&self) -> ChunkData {
+        let mut data = ChunkData::new(self.dims);
+        let per_word = (u64::BITS / self.bits) as usize;
+        let mask = (1 << self.bits) - 1;
+
+        data.voxels_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, voxel)| {
+                let shift = (i % per_word) as u32 * self.bits;
+                let index = (self.words[i / per_word] >> shift) & mask;
+                *voxel = self.palette[index as usize];
+            });
+
+        data
+    }
+
+    #[inline]
+    pub fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    /// Distinct voxels in the chunk, ids and orientations, in order of first appearance.
+    #[inline]
+    pub fn palette(&self) -> &[Voxel] {
+        &self.palette
+    }
+
+    /// Bits used per voxel.
+    #[inline]
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Serializes the chunk, little endian: dims, palette length and entries (id and
+    /// orientation), bits, then the words.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(21 + self.palette.len() * 3 + self.words.len() * 8);
+        self.dims
+            .to_array()
+            .iter()
+            .for_each(|dim| bytes.extend(dim.to_le_bytes()));
+        bytes.extend((self.palette.len() as u32).to_le_bytes());
+        for voxel in &self.palette {
+            bytes.extend(voxel.id.to_le_bytes());
+            bytes.push(voxel.orientation() as u8);
+        }
+        bytes.push(self.bits as u8);
+        bytes.extend((self.words.len() as u32).to_le_bytes());
+        self.words
+            .iter()
+            .for_each(|word| bytes.extend(word.to_le_bytes()));
+
+        bytes
+    }
+
+    /// Reads a chunk written by [`to_bytes`](Self::to_bytes), rejecting data that doesn't decode
+    /// to a whole chunk with [`io::ErrorKind::InvalidData`].
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader(bytes);
+        let dims = UVec3::new(reader.u32()?, reader.u32()?, reader.u32()?);
+        let palette = (0..reader.u32()?)
+            .map(|_| {
+                let id = reader.u16()?;
+                let orientation = Orientation::ALL
+                    .get(reader.u8()? as usize)
+                    .ok_or_else(|| invalid("unknown orientation"))?;
+                Ok(Voxel::new(id).with_orientation(*orientation))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let bits = reader.u8()? as u32;
+        let words = (0..reader.u32()?)
+            .map(|_| reader.u64())
+            .collect::<io::Result<Vec<_>>>()?;
+
+        if ![1, 2, 4, 8, 16].contains(&bits) || palette.is_empty() || palette.len() > 1 << bits {
+            return Err(invalid("bad palette"));
+        }
+        let len = dims.x as usize * dims.y as usize * dims.z as usize;
+        let per_word = (u64::BITS / bits) as usize;
+        if words.len() != len.div_ceil(per_word) {
+            return Err(invalid("wrong voxel count"));
+        }
+        let mask = (1 << bits) - 1;
+        let in_palette = (0..len).all(|i| {
+            let shift = (i % per_word) as u32 * bits;
+            ((words[i / per_word] >> shift) & mask) < palette.len() as u64
+        });
+        if !in_palette {
+            return Err(invalid("index out of palette"));
+        }
+
+        Ok(Self {
+            dims,
+            palette,
+            bits,
+            words,
+        })
+    }
+}
+
+/// Reads little endian values off the front of a slice.
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let (head, rest) = self
+            .0
+            .split_first_chunk::<N>()
+            .ok_or_else(|| invalid("unexpected end of chunk"))?;
+        self.0 = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        self.take::<1>().map(|[byte]| byte)
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        self.take().map(u64::from_le_bytes)
+    }
+}
+
+#[inline]
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Smallest supported index width that can address `len` palette entries.
+fn bits_for(len: usize) -> u32 {
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .find(|bits| len <= 1 << bits)
+        .unwrap_or(16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A chunk cycling through `distinct` different voxels, with every orientation.
+    fn mixed(distinct: usize) -> ChunkData {
+        let mut data = ChunkData::cubic(16);
+        data.voxels_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, voxel)| {
+                let orientation = Orientation::ALL[i % distinct % Orientation::ALL.len()];
+                *voxel = Voxel::new((i % distinct) as u16).with_orientation(orientation);
+            });
+        data
+    }
+
+    #[test]
+    fn every_palette_width_round_trips() {
+        for (distinct, bits) in [
+            (1, 1),
+            (2, 1),
+            (3, 2),
+            (16, 4),
+            (17, 8),
+            (256, 8),
+            (300, 16),
+        ] {
+            let data = mixed(distinct);
+            let encoded = PalettedChunk::encode(&data);
+            assert_eq!(encoded.palette().len(), distinct);
+            assert_eq!(encoded.bits(), bits, "{distinct} voxels");
+            assert_eq!(encoded.decode().voxels(), data.voxels());
+
+            let read = PalettedChunk::from_bytes(&encoded.to_bytes()).unwrap();
+            assert_eq!(read, encoded);
+        }
+    }
+
+    #[test]
+    fn truncated_and_corrupt_bytes_are_rejected() {
+        let bytes = PalettedChunk::encode(&mixed(5)).to_bytes();
+        let error = PalettedChunk::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // The first index of the last word points past the five palette entries.
+        let mut corrupt = bytes.clone();
+        let last_word = corrupt.len() - 8;
+        corrupt[last_word] = 0x0f;
+        let error = PalettedChunk::from_bytes(&corrupt).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
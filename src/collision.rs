@@ -0,0 +1,248 @@
+use crate::{
+    chunk::VoxelStorage, face::Face, mesh::ChunkNeighborhood, registry::VoxelRegistry, voxel::Voxel,
+};
+use bevy::{
+    ecs::{component::Component, system::Resource},
+    math::{primitives::Cuboid, IVec3, Vec3},
+};
+
+/// Whether the box `min..max` overlaps any voxel for which `is_solid` is true.
+///
+/// Uses the same `p..p + 1` voxel grid as [`raycast`](crate::raycast::raycast); boxes that only
+/// touch a voxel's face don't overlap it.
+pub fn overlaps(min: Vec3, max: Vec3, is_solid: impl Fn(IVec3) -> bool) -> bool {
+    let (lo, hi) = (min.floor().as_ivec3(), max.ceil().as_ivec3() - IVec3::ONE);
+
+    (lo.x..=hi.x)
+        .any(|x| (lo.y..=hi.y).any(|y| (lo.z..=hi.z).any(|z| is_solid(IVec3::new(x, y, z)))))
+}
+
+/// Moves a box one axis at a time, cancelling movement along any axis that would push it into a
+/// voxel for which `is_solid` is true. Returns the new center.
+///
+/// A box that already overlaps a solid voxel moves freely so it can escape.
+pub fn move_and_slide(
+    center: Vec3,
+    half_extents: Vec3,
+    delta: Vec3,
+    is_solid: impl Fn(IVec3) -> bool,
+) -> Vec3 {
+    if overlaps(center - half_extents, center + half_extents, &is_solid) {
+        return center + delta;
+    }
+
+    let mut center = center;
+    for axis in 0..3 {
+        let mut moved = center;
+        moved[axis] += delta[axis];
+
+        if !overlaps(moved - half_extents, moved + half_extents, &is_solid) {
+            center = moved;
+        }
+    }
+
+    center
+}
+
+/// Whether meshing also builds a [`ChunkCollider`] for every chunk. Off by default, since nothing
+/// in the engine reads colliders; turn it on for a physics integration.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct ColliderSettings {
+    pub enabled: bool,
+}
+
+/// One rectangle of a [`ChunkCollider`]: `face` of the box spanning the voxels `min..=max`, in
+/// the chunk's local voxel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionQuad {
+    pub face: Face,
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl CollisionQuad {
+    /// Corners of the quad in local voxel units, where voxels are centered on integer
+    /// coordinates like in chunk meshes, in the order [`Face::indices`] triangulates.
+    pub fn corners(&self) -> [Vec3; 4] {
+        let (min, max) = (self.min.as_vec3(), self.max.as_vec3());
+        self.face
+            .corners(Vec3::ZERO, 1.0)
+            .map(|corner| Vec3::select(corner.cmplt(Vec3::ZERO), min, max) + corner)
+    }
+}
+
+/// Surfaces of a chunk's [collidable](crate::registry::VoxelType::collidable) voxels, for physics
+/// and precise raycasts.
+///
+/// Unlike the render mesh, it ignores every voxel that doesn't collide, such as liquids, and
+/// merges faces regardless of texture or shading, so its quads are as large as they get. It is
+/// rebuilt alongside the render mesh while [`ColliderSettings`] is enabled.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+pub struct ChunkCollider {
+    pub quads: Vec<CollisionQuad>,
+}
+
+impl ChunkCollider {
+    /// Finds the collidable surfaces of `chunks.center`. Faces against a collidable voxel of a
+    /// loaded neighbor are left out, and faces at the border of an unloaded one are kept.
+    pub fn build<S: VoxelStorage>(chunks: ChunkNeighborhood<S>, registry: &VoxelRegistry) -> Self {
+        let collides = |pos: IVec3| {
+            chunks
+                .get(pos)
+                .is_some_and(|voxel: &Voxel| registry.is_collidable(voxel.id))
+        };
+
+        let dims = chunks.center.dims().as_ivec3();
+        let mut quads = Vec::new();
+        for face in Face::ALL {
+            let axis = face.axis();
+            let [u, v] = face.uv_axes();
+            let at = |slice: i32, i: i32, j: i32| {
+                let mut pos = IVec3::ZERO;
+                pos[axis] = slice;
+                pos[u] = i;
+                pos[v] = j;
+                pos
+            };
+
+            let mut mask = vec![false; (dims[u] * dims[v]) as usize];
+            for slice in 0..dims[axis] {
+                mask.iter_mut().enumerate().for_each(|(index, exposed)| {
+                    let pos = at(slice, index as i32 % dims[u], index as i32 / dims[u]);
+                    *exposed = collides(pos) && !collides(pos + face.offset());
+                });
+
+                for j in 0..dims[v] {
+                    let mut i = 0;
+                    while i < dims[u] {
+                        if !mask[(j * dims[u] + i) as usize] {
+                            i += 1;
+                            continue;
+                        }
+
+                        let open = |i: i32, j: i32| mask[(j * dims[u] + i) as usize];
+                        let width = (i..dims[u]).take_while(|&i| open(i, j)).count() as i32;
+                        let height = (j..dims[v])
+                            .take_while(|&j| (i..i + width).all(|i| open(i, j)))
+                            .count() as i32;
+                        for j in j..j + height {
+                            for i in i..i + width {
+                                mask[(j * dims[u] + i) as usize] = false;
+                            }
+                        }
+
+                        quads.push(CollisionQuad {
+                            face,
+                            min: at(slice, i, j),
+                            max: at(slice, i + width - 1, j + height - 1),
+                        });
+                        i += width;
+                    }
+                }
+            }
+        }
+
+        Self { quads }
+    }
+
+    /// The quads as a triangle list in local voxel units, for physics engines that take a
+    /// trimesh. Add the chunk's [`origin`](crate::chunk::Chunk::origin) and scale by
+    /// [`Voxel::SIZE`] for world space.
+    pub fn triangles(&self) -> (Vec<Vec3>, Vec<[u32; 3]>) {
+        let mut positions = Vec::with_capacity(self.quads.len() * 4);
+        let mut triangles = Vec::with_capacity(self.quads.len() * 2);
+        for quad in &self.quads {
+            let base = positions.len() as u32;
+            positions.extend(quad.corners());
+            let [a, b, c, d, e, f] = quad.face.indices().map(|i| base + i);
+            triangles.extend([[a, b, c], [d, e, f]]);
+        }
+
+        (positions, triangles)
+    }
+
+    /// Every quad as a slab `thickness` voxels deep behind it, with its center in local voxel
+    /// units, for physics engines that prefer convex primitives.
+    pub fn cuboids(&self, thickness: f32) -> impl Iterator<Item = (Vec3, Cuboid)> + '_ {
+        self.quads.iter().map(move |quad| {
+            let [a, _, c, _] = quad.corners();
+            let normal = quad.face.normal();
+            let (min, max) = (a.min(c), a.max(c));
+            let center = (min + max) / 2.0 - normal * thickness / 2.0;
+            let size = (max - min) + normal.abs() * thickness;
+            (center, Cuboid::from_size(size))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        raycast,
+        registry::{FaceTextures, Transparency, VoxelType},
+        voxel::VoxelId,
+    };
+
+    /// The default registry plus tall grass: visible and targetable, but not collidable.
+    fn registry() -> VoxelRegistry {
+        let mut registry = VoxelRegistry::default();
+        registry.register(
+            VoxelType::new("tall_grass", FaceTextures::all(10))
+                .with_transparency(Transparency::Cutout)
+                .with_collidable(false),
+        );
+        registry
+    }
+
+    /// Whether a unit box walking two voxels along x stops short of voxel `(2, 0, 0)` of type
+    /// `id`, and whether the block target stops at it.
+    fn blocks_and_targets(registry: &VoxelRegistry, id: VoxelId) -> (bool, bool) {
+        let at_wall = |pos: IVec3| pos == IVec3::new(2, 0, 0);
+        let center = move_and_slide(Vec3::splat(0.5), Vec3::splat(0.5), Vec3::X * 2.0, |pos| {
+            at_wall(pos) && registry.is_collidable(id)
+        });
+        let hit = raycast::raycast(Vec3::splat(0.5), Vec3::X, 8.0, |pos| {
+            at_wall(pos) && registry.is_targetable(id)
+        });
+
+        (center.x < 2.5, hit.is_some())
+    }
+
+    #[test]
+    fn movement_and_targeting_filter_voxels_separately() {
+        let registry = registry();
+        for (name, blocks, targeted) in [
+            ("stone", true, true),
+            ("tall_grass", false, true),
+            ("water", false, false),
+            ("barrier", true, false),
+            (VoxelRegistry::AIR, false, false),
+        ] {
+            let id = registry.id(name).unwrap();
+            assert_eq!(
+                blocks_and_targets(&registry, id),
+                (blocks, targeted),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn colliders_skip_non_collidable_voxels() {
+        let registry = registry();
+        let mut data = crate::chunk::ChunkData::cubic(4);
+        data.set(0, 0, 0, Voxel::new(registry.id("stone").unwrap()));
+        data.set(2, 0, 0, Voxel::new(registry.id("tall_grass").unwrap()));
+        data.set(0, 2, 0, Voxel::new(registry.id("water").unwrap()));
+        let chunks = ChunkNeighborhood {
+            center: &data,
+            neighbors: [None; 6],
+            diagonals: [None; 20],
+        };
+
+        let collider = ChunkCollider::build(chunks, &registry);
+        assert_eq!(collider.quads.len(), 6);
+        assert!(collider.quads.iter().all(|quad| quad.min == IVec3::ZERO));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    chunk::Chunk,
+    material::BackfaceCulling,
+    mesh::{AoSettings, ChunkMeshers, FaceShading, LodSettings, SkirtSettings, WindingOrder},
+    scene::{FogConfig, SceneConfig, TimeOfDay},
+    schedule::{self, VoxelSet},
+    stats::{FrameTimings, MeshStats},
+};
+use bevy::{
+    app::{App, Plugin, PreUpdate, Update},
+    ecs::{
+        schedule::IntoSystemConfigs,
+        system::{Query, Res, ResMut},
+    },
+    input::{keyboard::KeyCode, mouse::MouseButton, ButtonInput},
+    math::Vec3,
+};
+use bevy_egui::{egui, EguiContexts, EguiPlugin, EguiSet};
+use std::f32::consts::PI;
+
+/// Runtime panel for tuning lighting and meshing. Only built with the `debug_ui` feature.
+#[derive(Debug, Default)]
+pub struct DebugUiPlugin;
+
+impl Plugin for DebugUiPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.add_plugins(EguiPlugin)
+            .add_systems(PreUpdate, block_captured_input.after(EguiSet::ProcessInput))
+            .add_systems(Update, debug_panel.in_set(VoxelSet::Input));
+    }
+}
+
+/// Hides mouse and keyboard input from the game while egui is using it, so clicking the panel
+/// doesn't break blocks and typing into it doesn't move the camera.
+fn block_captured_input(
+    mut contexts: EguiContexts,
+    mut buttons: ResMut<ButtonInput<MouseButton>>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+) {
+    let ctx = contexts.ctx_mut();
+    if ctx.wants_pointer_input() || ctx.is_pointer_over_area() {
+        buttons.reset_all();
+    }
+    if ctx.wants_keyboard_input() {
+        keys.reset_all();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn debug_panel(
+    mut contexts: EguiContexts,
+    mut scene: ResMut<SceneConfig>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut fog: ResMut<FogConfig>,
+    mut ao: ResMut<AoSettings>,
+    mut face_shading: ResMut<FaceShading>,
+    mut skirts: ResMut<SkirtSettings>,
+    mut lod: ResMut<LodSettings>,
+    mut winding: ResMut<WindingOrder>,
+    mut culling: ResMut<BackfaceCulling>,
+    mut meshers: ResMut<ChunkMeshers>,
+    stats: Res<MeshStats>,
+    timings: Res<FrameTimings>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    let ao_before = ao.clone();
+    let face_shading_before = *face_shading;
+    let skirts_before = skirts.clone();
+    let winding_before = *winding;
+
+    egui::Window::new("World").show(contexts.ctx_mut(), |ui| {
+        ui.heading("Lighting");
+        let mut heading = scene.sun_direction.x.atan2(scene.sun_direction.z);
+        if ui
+            .add(egui::Slider::new(&mut heading, -PI..=PI).text("sun heading"))
+            .changed()
+        {
+            scene.sun_direction = Vec3::new(heading.sin(), -1.0, heading.cos()).normalize();
+        }
+        ui.add(
+            egui::Slider::new(&mut scene.sun_illuminance, 0.0..=120_000.0)
+                .logarithmic(true)
+                .text("sun illuminance"),
+        );
+        ui.add(egui::Slider::new(&mut time_of_day.time, 0.0..=1.0).text("time of day"));
+        ui.add(egui::Slider::new(&mut time_of_day.day_length, 10.0..=3600.0).text("day length"));
+        ui.checkbox(&mut time_of_day.paused, "pause cycle");
+        ui.checkbox(&mut fog.enabled, "fog");
+        ui.add(egui::Slider::new(&mut fog.start, 0.0..=256.0).text("fog start"));
+        ui.add(egui::Slider::new(&mut fog.end, 0.0..=512.0).text("fog end"));
+
+        ui.heading("Meshing");
+        ui.checkbox(&mut ao.enabled, "ambient occlusion");
+        ui.add(egui::Slider::new(&mut ao.strength, 0.0..=1.0).text("AO strength"));
+        ui.checkbox(&mut face_shading.enabled, "face shading");
+        ui.checkbox(&mut skirts.enabled, "chunk skirts");
+        ui.add(egui::Slider::new(&mut skirts.depth, 0.5..=8.0).text("skirt depth"));
+        ui.checkbox(&mut lod.enabled, "level of detail");
+        ui.add(egui::Slider::new(&mut lod.distances[0], 16.0..=512.0).text("LOD 2x distance"));
+        ui.add(egui::Slider::new(&mut lod.distances[1], 16.0..=1024.0).text("LOD 4x distance"));
+        ui.horizontal(|ui| {
+            let (active, mut selected) = (meshers.active_index(), meshers.active_index());
+            for (index, mesher) in meshers.iter().enumerate() {
+                if ui.radio(index == active, mesher.name()).clicked() {
+                    selected = index;
+                }
+            }
+            meshers.set_active(selected);
+        });
+        ui.horizontal(|ui| {
+            ui.label("winding");
+            ui.radio_value(&mut *winding, WindingOrder::Ccw, "CCW");
+            ui.radio_value(&mut *winding, WindingOrder::Cw, "CW");
+        });
+        ui.checkbox(&mut culling.enabled, "backface culling");
+        ui.label(format!("remesh backlog: {} chunks", stats.remesh_backlog));
+        ui.label(format!("world triangles: {}", stats.world_triangles));
+        ui.label(format!(
+            "world vertex data: {:.1} KiB",
+            stats.world_vertex_bytes as f64 / 1024.0
+        ));
+        ui.label(format!(
+            "average build: {:.3} ms",
+            stats.average_build_time().as_secs_f64() * 1000.0
+        ));
+
+        ui.heading("Last frame");
+        for (stage, elapsed) in timings.last_frame() {
+            ui.label(format!("{stage}: {:.3} ms", elapsed.as_secs_f64() * 1000.0));
+        }
+    });
+
+    // Mesher and level of detail changes are picked up by the remesh systems in `main`.
+    let ao_changed = ao.enabled != ao_before.enabled || ao.strength != ao_before.strength;
+    let shading_changed = *face_shading != face_shading_before;
+    if ao_changed || shading_changed || *skirts != skirts_before || *winding != winding_before {
+        chunks.iter_mut().for_each(|mut chunk| chunk.mark_dirty());
+    }
+}
@@ -0,0 +1,420 @@
+use crate::{
+    atlas::AtlasLayout,
+    chunk::Chunk,
+    face::Face,
+    mesh::{self, OVERLAY_INFLATE},
+    raycast::{self, RaycastHit},
+    registry::{FaceTextures, VoxelRegistry},
+    tick::{self, VoxelTickQueue},
+    voxel::Voxel,
+    world::VoxelWorld,
+};
+use bevy::{
+    asset::{AssetServer, Assets, Handle},
+    color::Color,
+    core_pipeline::core_3d::Camera3d,
+    ecs::{
+        component::Component,
+        query::With,
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::{FromWorld, World},
+    },
+    input::{mouse::MouseButton, ButtonInput},
+    math::{IVec3, Vec3},
+    pbr::{NotShadowCaster, PbrBundle, StandardMaterial},
+    render::{alpha::AlphaMode, mesh::Mesh, view::Visibility},
+    time::Time,
+    transform::components::{GlobalTransform, Transform},
+};
+use std::collections::VecDeque;
+
+/// How far away blocks can be targeted, in voxels.
+pub const REACH: f32 = 8.0;
+
+/// Seconds the break button must be held to break a block.
+pub const BREAK_SECONDS: f32 = 1.0;
+
+/// Atlas tile of the first crack stage; the remaining stages follow it.
+pub const CRACK_TILE: u32 = 12;
+pub const CRACK_STAGES: u32 = 4;
+
+/// The voxel under the crosshair, updated every frame.
+#[derive(Debug, Default, Resource)]
+pub struct BlockTarget(pub Option<RaycastHit>);
+
+#[derive(Debug, Default, Resource)]
+pub struct BreakProgress {
+    pub target: Option<IVec3>,
+    /// Fraction of [`BREAK_SECONDS`] the target has been held for.
+    pub progress: f32,
+}
+
+/// What the bucket tool fills the targeted block's connected region with on the middle mouse
+/// button, see [`flood_fill`].
+#[derive(Debug, Clone, Copy, Resource)]
+pub struct BucketFill {
+    pub replacement: Voxel,
+    /// Most voxels one click fills, so filling the open sky doesn't run away.
+    pub limit: usize,
+}
+
+impl FromWorld for BucketFill {
+    fn from_world(world: &mut World) -> Self {
+        let registry = world.resource::<VoxelRegistry>();
+        let replacement = Voxel::new(registry.id("stone").unwrap_or_default());
+
+        Self {
+            replacement,
+            limit: 4096,
+        }
+    }
+}
+
+impl BreakProgress {
+    /// Crack overlay stage to draw on the target, if it is being broken.
+    pub fn stage(&self) -> Option<u32> {
+        self.target?;
+        (self.progress > 0.0)
+            .then(|| ((self.progress * CRACK_STAGES as f32) as u32).min(CRACK_STAGES - 1))
+    }
+}
+
+/// Marks the overlay entity drawing crack stages over the block being broken.
+///
+/// It is spawned hidden once and only moved, reshaped and shown by [`update_crack_overlay`], which
+/// runs right after [`break_blocks`], so a re-targeted or just broken block never shows a stale
+/// stage for a frame.
+#[derive(Debug, Component)]
+pub struct CrackOverlay;
+
+#[derive(Debug, Resource)]
+pub struct CrackAssets {
+    meshes: Vec<Handle<Mesh>>,
+}
+
+pub fn setup_crack_overlay(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    atlas: Res<AtlasLayout>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let crack_meshes: Vec<_> = (0..CRACK_STAGES)
+        .map(|stage| {
+            let textures = FaceTextures::all(CRACK_TILE + stage);
+            meshes.add(mesh::generate_cube(textures, &atlas))
+        })
+        .collect();
+    let material = materials.add(StandardMaterial {
+        base_color_texture: Some(asset_server.load("array_texture.png")),
+        alpha_mode: AlphaMode::Mask(0.5),
+        ..Default::default()
+    });
+
+    commands.spawn((
+        PbrBundle {
+            mesh: crack_meshes[0].clone(),
+            material,
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        CrackOverlay,
+        // The cracks are a decal; as a caster they'd stamp their pattern into the block's shadow.
+        NotShadowCaster,
+    ));
+    commands.insert_resource(CrackAssets {
+        meshes: crack_meshes,
+    });
+}
+
+/// Marks the entity outlining the [`BlockTarget`].
+#[derive(Debug, Component)]
+pub struct BlockHighlight;
+
+/// Spawns the hidden [`BlockHighlight`], drawn unlit so the outline stays visible in shadow.
+pub fn setup_block_highlight(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(mesh::selection_box_mesh()),
+            material: materials.add(StandardMaterial {
+                base_color: Color::BLACK,
+                unlit: true,
+                ..Default::default()
+            }),
+            visibility: Visibility::Hidden,
+            ..Default::default()
+        },
+        BlockHighlight,
+        NotShadowCaster,
+    ));
+}
+
+pub fn update_target(
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    chunks: Query<&Chunk>,
+    world: Res<VoxelWorld>,
+    registry: Res<VoxelRegistry>,
+    mut target: ResMut<BlockTarget>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+
+    // Voxels are centered on their coordinate, so shift into the raycast's `p..p + 1` grid.
+    let origin = camera.translation() / Voxel::SIZE + Vec3::splat(0.5);
+    target.0 = raycast::raycast(origin, *camera.forward(), REACH, |position| {
+        world
+            .voxel(&chunks, position)
+            .is_some_and(|voxel| registry.is_targetable(voxel.id))
+    });
+}
+
+/// Accumulates break progress while the left mouse button is held on a block, replacing it with
+/// air once complete.
+#[allow(clippy::too_many_arguments)]
+pub fn break_blocks(
+    time: Res<Time>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    target: Res<BlockTarget>,
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    mut progress: ResMut<BreakProgress>,
+    mut tick_queue: ResMut<VoxelTickQueue>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    let target = target.0.map(|hit| hit.position);
+    if !buttons.pressed(MouseButton::Left) || target.is_none() || target != progress.target {
+        *progress = BreakProgress {
+            target,
+            progress: 0.0,
+        };
+        return;
+    }
+
+    progress.progress += time.delta_seconds() / BREAK_SECONDS;
+    if progress.progress >= 1.0 {
+        progress.progress = 0.0;
+        if let Some(position) = target {
+            world.set_voxel(&mut chunks, position, Voxel::AIR);
+            world.mark_urgent(&mut chunks, position);
+
+            let chunks = chunks.to_readonly();
+            tick::notify_neighbors(&mut tick_queue, &registry, position, |neighbor| {
+                world.voxel(&chunks, neighbor)
+            });
+        }
+    }
+}
+
+/// Replaces the voxels connected to `start` through their faces that have its id with
+/// `replacement`, at most `limit` of them, nearest first. The fill crosses into every loaded chunk
+/// it reaches and stops at unloaded ones. Each chunk written is reported by its next
+/// [`ChunkModified`](crate::chunk::ChunkModified) and remeshed. Returns how many voxels changed.
+pub fn flood_fill(
+    world: &VoxelWorld,
+    chunks: &mut Query<&mut Chunk>,
+    start: IVec3,
+    replacement: Voxel,
+    limit: usize,
+) -> usize {
+    let Some(target) = world
+        .voxel(&chunks.to_readonly(), start)
+        .map(|voxel| voxel.id)
+    else {
+        return 0;
+    };
+    // Filled voxels no longer match, so nothing is visited twice without a visited set.
+    if target == replacement.id {
+        return 0;
+    }
+
+    let mut queue = VecDeque::from([start]);
+    let mut filled = 0;
+    while filled < limit {
+        let Some(position) = queue.pop_front() else {
+            break;
+        };
+        let matches = world
+            .voxel(&chunks.to_readonly(), position)
+            .is_some_and(|voxel| voxel.id == target);
+        if !matches {
+            continue;
+        }
+
+        world.set_voxel(chunks, position, replacement);
+        filled += 1;
+        queue.extend(Face::ALL.map(|face| position + face.offset()));
+    }
+
+    filled
+}
+
+/// Flood fills the targeted block's region with the [`BucketFill`] on the middle mouse button.
+pub fn bucket_fill(
+    buttons: Res<ButtonInput<MouseButton>>,
+    target: Res<BlockTarget>,
+    fill: Res<BucketFill>,
+    world: Res<VoxelWorld>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    let Some(hit) = target
+        .0
+        .filter(|_| buttons.just_pressed(MouseButton::Middle))
+    else {
+        return;
+    };
+
+    flood_fill(
+        &world,
+        &mut chunks,
+        hit.position,
+        fill.replacement,
+        fill.limit,
+    );
+}
+
+/// Moves the [`BlockHighlight`] to the targeted block, hiding it while nothing is targeted.
+pub fn update_block_highlight(
+    target: Res<BlockTarget>,
+    mut highlight: Query<(&mut Transform, &mut Visibility), With<BlockHighlight>>,
+) {
+    let Ok((mut transform, mut visibility)) = highlight.get_single_mut() else {
+        return;
+    };
+
+    match target.0 {
+        Some(hit) => {
+            *transform = Transform::from_translation(hit.position.as_vec3() * Voxel::SIZE);
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+pub fn update_crack_overlay(
+    progress: Res<BreakProgress>,
+    crack_assets: Res<CrackAssets>,
+    mut overlay: Query<(&mut Transform, &mut Handle<Mesh>, &mut Visibility), With<CrackOverlay>>,
+) {
+    let Ok((mut transform, mut mesh, mut visibility)) = overlay.get_single_mut() else {
+        return;
+    };
+
+    match (progress.target, progress.stage()) {
+        (Some(position), Some(stage)) => {
+            *transform = Transform::from_translation(position.as_vec3() * Voxel::SIZE)
+                .with_scale(Vec3::splat(Voxel::SIZE * OVERLAY_INFLATE));
+            *mesh = crack_assets.meshes[stage as usize].clone();
+            *visibility = Visibility::Inherited;
+        }
+        _ => *visibility = Visibility::Hidden,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chunk::VoxelStorage, world::ChunkPos};
+    use bevy::ecs::{entity::Entity, system::SystemState};
+
+    /// Two chunks of stone side by side along x, hollowed out by an air cavity across their
+    /// border, with the state of a query writing them.
+    fn hollow_world() -> (
+        World,
+        VoxelWorld,
+        SystemState<Query<'static, 'static, &'static mut Chunk>>,
+    ) {
+        let mut world = World::new();
+        let mut voxels = VoxelWorld::default();
+        let size = Chunk::SIZE as i32;
+        let cavity = |pos: IVec3| {
+            (12..20).contains(&pos.x) && (4..8).contains(&pos.y) && (4..8).contains(&pos.z)
+        };
+        for chunk_x in 0..2 {
+            let mut chunk = Chunk::new(Vec3::new(chunk_x as f32, 0.0, 0.0));
+            chunk.edit_batch(|editor| {
+                for z in 0..size {
+                    for y in 0..size {
+                        for x in 0..size {
+                            if !cavity(IVec3::new(chunk_x * size + x, y, z)) {
+                                editor.set(x as usize, y as usize, z as usize, Voxel::new(1));
+                            }
+                        }
+                    }
+                }
+            });
+            chunk.take_modified(Entity::PLACEHOLDER);
+            voxels.insert_chunk(ChunkPos::new(chunk_x, 0, 0), world.spawn(chunk).id());
+        }
+        let state = SystemState::new(&mut world);
+        (world, voxels, state)
+    }
+
+    /// World positions of every voxel of `id`.
+    fn positions_of(world: &mut World, id: u16) -> Vec<IVec3> {
+        let mut chunks = world.query::<&Chunk>();
+        chunks
+            .iter(world)
+            .flat_map(|chunk| {
+                let origin = chunk.origin();
+                let voxels = chunk.data().iter().filter(move |(_, voxel)| voxel.id == id);
+                voxels.map(move |(pos, _)| origin + pos.as_ivec3())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flood_fill_fills_a_cavity_across_chunks() {
+        let (mut world, voxels, mut state) = hollow_world();
+        let dirt = Voxel::new(2);
+        let filled = flood_fill(
+            &voxels,
+            &mut state.get_mut(&mut world),
+            IVec3::new(15, 5, 5),
+            dirt,
+            1000,
+        );
+
+        assert_eq!(filled, 8 * 4 * 4);
+        let positions = positions_of(&mut world, dirt.id);
+        assert_eq!(positions.len(), filled);
+        assert!(positions.iter().all(|pos| (12..20).contains(&pos.x)));
+        assert!(positions.iter().any(|pos| pos.x < Chunk::SIZE as i32));
+        assert!(positions.iter().any(|pos| pos.x >= Chunk::SIZE as i32));
+        let mut chunks = world.query::<&mut Chunk>();
+        for mut chunk in chunks.iter_mut(&mut world) {
+            assert!(chunk.take_modified(Entity::PLACEHOLDER).is_some());
+        }
+    }
+
+    #[test]
+    fn flood_fill_stops_at_its_limit_nearest_first() {
+        let (mut world, voxels, mut state) = hollow_world();
+        let start = IVec3::new(15, 5, 5);
+        let dirt = Voxel::new(2);
+        assert_eq!(
+            flood_fill(&voxels, &mut state.get_mut(&mut world), start, dirt, 10),
+            10
+        );
+
+        let positions = positions_of(&mut world, dirt.id);
+        assert_eq!(positions.len(), 10);
+        // One voxel, then its six neighbors, then the nearest of the next layer.
+        let distance = |pos: &IVec3| (*pos - start).abs().element_sum();
+        assert!(positions.iter().all(|pos| distance(pos) <= 2));
+
+        let mut chunks = state.get_mut(&mut world);
+        assert_eq!(
+            flood_fill(&voxels, &mut chunks, IVec3::ZERO, Voxel::new(1), 10),
+            0
+        );
+        assert_eq!(
+            flood_fill(&voxels, &mut chunks, IVec3::splat(-1), dirt, 10),
+            0
+        );
+    }
+}
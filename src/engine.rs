@@ -0,0 +1,1963 @@
+use crate::{
+    atlas::AtlasLayout,
+    chunk::{self, Chunk, ChunkChild, ChunkModified},
+    collision::{self, ChunkCollider, ColliderSettings},
+    editing::{self, BlockTarget, BreakProgress, BucketFill},
+    generation,
+    instancing::{InstancedVoxels, VoxelInstanceTexture, VoxelInstancingPlugin},
+    light::{self, ChunkLight, VertexLight},
+    material::{
+        self, BackfaceCulling, ChunkArrayTexture, ChunkMaterial, ChunkMaterialHandle,
+        ChunkMaterials, ChunkTextureMode, ChunkWireframes, PendingArrayTexture,
+    },
+    mesh::{
+        self, AoSettings, ChunkMesh, ChunkMeshers, ChunkMeshes, ChunkNeighborhood, ChunkSnapshot,
+        FaceShading, LodSettings, MeshBufferPool, MeshContext, MeshPass, MeshingBudget,
+        SkirtSettings, StaticWorld, UvLayout, WindingOrder,
+    },
+    raymarch::RaymarchPlugin,
+    registry::VoxelRegistry,
+    render::RenderMode,
+    save::{AutosaveConfig, AutosavePlugin, SavedWorld},
+    scene::{self, FogConfig, SceneConfig, TimeOfDay},
+    schedule::{self, VoxelSet},
+    schematic,
+    stats::{ChunkMeshStats, FrameTimings, MeshStats, MeshStatsPlugin},
+    surface_nets::SurfaceNets,
+    tick::{self, VoxelTickQueue},
+    voxel::Voxel,
+    world::{self, VoxelWorld},
+};
+use bevy::{
+    app::{AppExit, FixedUpdate, Plugin, PluginGroup, PluginGroupBuilder, PostUpdate, Update},
+    asset::{AssetServer, Assets, Handle},
+    core::Name,
+    core_pipeline::{
+        bloom::BloomSettings,
+        core_3d::{Camera3d, Camera3dBundle},
+        tonemapping::Tonemapping,
+    },
+    ecs::{
+        bundle::Bundle,
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        event::{EventReader, EventWriter},
+        query::{Added, With},
+        removal_detection::RemovedComponents,
+        schedule::{
+            common_conditions::{not, resource_equals, resource_exists},
+            IntoSystemConfigs,
+        },
+        system::{Commands, Local, Query, Res, ResMut, Resource},
+    },
+    input::{
+        keyboard::KeyCode,
+        mouse::{MouseScrollUnit, MouseWheel},
+        ButtonInput,
+    },
+    log::{error, info, info_span, warn},
+    math::{vec3, Affine3A, IVec2, IVec3, Quat, UVec2, Vec3},
+    pbr::{
+        wireframe::{Wireframe, WireframePlugin},
+        MaterialMeshBundle, MaterialPlugin, PbrBundle, StandardMaterial, VolumetricFogSettings,
+    },
+    prelude::{App, Startup},
+    render::{
+        alpha::AlphaMode,
+        mesh::Mesh,
+        primitives::Frustum,
+        renderer::RenderDevice,
+        settings::WgpuFeatures,
+        texture::{Image, ImageLoaderSettings},
+        view::GpuCulling,
+    },
+    tasks::{block_on, futures_lite::future, AsyncComputeTaskPool, Task},
+    time::Time,
+    transform::components::{GlobalTransform, Transform},
+    utils::HashMap,
+};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Tangent space normal map applied to every face of the texture array material, bevelling each
+/// voxel's edges.
+const NORMAL_MAP: &str = "normal_map.png";
+
+/// Half size of the box the camera collides with, in voxels.
+const CAMERA_HALF_EXTENTS: Vec3 = Vec3::splat(0.3);
+
+/// The world [`ChunkStreamingPlugin`] generates at startup, and again on `G`.
+#[derive(Debug, Clone, Resource)]
+pub struct WorldConfig {
+    /// Chunks along x and z.
+    pub size: UVec2,
+    /// Schematic stamped onto the highest voxel of a column once the terrain is generated.
+    pub schematic: Option<(PathBuf, IVec2)>,
+}
+
+impl Default for WorldConfig {
+    /// The demo world, three chunks on a side with a tree in a column clear of the pond.
+    fn default() -> Self {
+        Self {
+            size: UVec2::splat(3),
+            schematic: Some(("assets/schematics/tree.ron".into(), IVec2::new(36, 12))),
+        }
+    }
+}
+
+/// Where the [`FlyCameraPlugin`] camera starts: above the surface of [`column`](Self::column)
+/// once its chunk is generated, and at [`fallback`](Self::fallback) until then.
+#[derive(Debug, Clone, Resource)]
+pub struct SpawnConfig {
+    /// World voxel column to spawn over, or the middle of the [`WorldConfig`] world if `None`.
+    pub column: Option<IVec2>,
+    /// Voxels from the center of the column's topmost voxel up to the camera.
+    pub eye_height: f32,
+    /// Camera translation in world units while the column isn't generated.
+    pub fallback: Vec3,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        Self {
+            column: None,
+            eye_height: 2.0,
+            fallback: vec3(0.0, 0.0, -10.0),
+        }
+    }
+}
+
+/// Marks the camera until [`place_camera`] has moved it above the terrain.
+#[derive(Debug, Default, Clone, Copy, Component)]
+struct AwaitingSpawn;
+
+/// How fast the [`FlyCameraPlugin`] camera flies, in world units per second. The scroll wheel
+/// scales [`speed`](Self::speed) within [`speed_range`](Self::speed_range).
+#[derive(Debug, Clone, Component)]
+pub struct CameraController {
+    pub speed: f32,
+    pub speed_range: (f32, f32),
+    /// Factor the speed is scaled by per line scrolled.
+    pub scroll_factor: f32,
+    /// Speed while `Ctrl` is held, for lining up with single voxels.
+    pub slow_speed: f32,
+    pub boost_key: KeyCode,
+    /// Factor the speed is multiplied by while [`boost_key`](Self::boost_key) is held.
+    pub boost: f32,
+}
+
+impl Default for CameraController {
+    fn default() -> Self {
+        Self {
+            speed: 10.0,
+            speed_range: (1.0, 200.0),
+            scroll_factor: 1.1,
+            slow_speed: 2.0,
+            boost_key: KeyCode::AltLeft,
+            boost: 5.0,
+        }
+    }
+}
+
+impl CameraController {
+    /// The speed to fly at with `keys` held.
+    pub fn current_speed(&self, keys: &ButtonInput<KeyCode>) -> f32 {
+        if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+            self.slow_speed
+        } else if keys.pressed(self.boost_key) {
+            self.speed * self.boost
+        } else {
+            self.speed
+        }
+    }
+}
+
+/// Materials shared by every chunk mesh.
+#[derive(Debug, Resource)]
+struct VoxelAssets {
+    material: ChunkMaterialHandle,
+}
+
+/// The whole engine, for an app that already has Bevy's `DefaultPlugins`. Textures are pixel
+/// art, so `ImagePlugin::default_nearest()` suits them best.
+///
+/// Plugins can be left out of the group, e.g. for an app without the debug keys:
+///
+/// ```no_run
+/// use bevy::prelude::*;
+/// use voxel::{DebugPlugin, VoxelEnginePlugins};
+///
+/// let engine = VoxelEnginePlugins::default().build().disable::<DebugPlugin>();
+/// App::new().add_plugins((DefaultPlugins, engine)).run();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct VoxelEnginePlugins {
+    pub world: WorldConfig,
+    pub render_mode: RenderMode,
+}
+
+impl VoxelEnginePlugins {
+    #[inline]
+    pub fn with_world(self, world: WorldConfig) -> Self {
+        Self { world, ..self }
+    }
+
+    #[inline]
+    pub fn with_render_mode(self, render_mode: RenderMode) -> Self {
+        Self {
+            render_mode,
+            ..self
+        }
+    }
+}
+
+impl PluginGroup for VoxelEnginePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(ChunkStreamingPlugin { world: self.world })
+            .add(AutosavePlugin)
+            .add(LightingPlugin)
+            .add(MeshingPlugin {
+                render_mode: self.render_mode,
+            })
+            .add(FlyCameraPlugin)
+            .add(EditingPlugin)
+            .add(DebugPlugin);
+        #[cfg(feature = "physics")]
+        let group = group.add(crate::physics::ChunkPhysicsPlugin);
+
+        group
+    }
+}
+
+/// Generates the [`WorldConfig`] world, keeps [`VoxelWorld`] and voxel ticks running, and
+/// reports every chunk change as a [`ChunkModified`] in [`VoxelSet::Generate`].
+#[derive(Debug, Default, Clone)]
+pub struct ChunkStreamingPlugin {
+    pub world: WorldConfig,
+}
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.insert_resource(self.world.clone())
+            .init_resource::<VoxelRegistry>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<VoxelTickQueue>()
+            .init_resource::<FrameTimings>()
+            .add_event::<ChunkModified>()
+            .observe(world::forget_despawned_chunk)
+            .add_systems(
+                Startup,
+                (spawn_world, stamp_schematic)
+                    .chain()
+                    .in_set(VoxelSet::Generate),
+            )
+            .add_systems(
+                Update,
+                (
+                    regenerate_world,
+                    chunk::send_chunk_modified,
+                    world::remesh_modified_chunks,
+                )
+                    .chain()
+                    .in_set(VoxelSet::Generate),
+            )
+            .add_systems(FixedUpdate, tick::run_ticks)
+            .add_systems(PostUpdate, chunk::despawn_chunk_children);
+    }
+}
+
+/// Propagates voxel light through modified chunks, and runs the scene's day cycle, sun and fog.
+#[derive(Debug, Default)]
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.init_resource::<VoxelRegistry>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<SceneConfig>()
+            .init_resource::<TimeOfDay>()
+            .init_resource::<FogConfig>()
+            .init_resource::<FrameTimings>()
+            .add_systems(Startup, scene::setup_scene.in_set(VoxelSet::Light))
+            .add_systems(
+                Update,
+                (
+                    (light::add_chunk_light, light::propagate_light).chain(),
+                    (
+                        scene::advance_time_of_day,
+                        scene::update_sun,
+                        scene::update_fog,
+                    )
+                        .chain(),
+                )
+                    .in_set(VoxelSet::Light),
+            );
+    }
+}
+
+/// Meshes dirty chunks on background tasks in [`VoxelSet::Mesh`] and swaps the results in during
+/// [`VoxelSet::Render`], or draws chunks with instancing or raymarching instead.
+#[derive(Debug, Default, Clone)]
+pub struct MeshingPlugin {
+    pub render_mode: RenderMode,
+}
+
+impl Plugin for MeshingPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.add_plugins((
+            MaterialPlugin::<ChunkMaterial>::default(),
+            MeshStatsPlugin,
+            VoxelInstancingPlugin,
+            RaymarchPlugin,
+        ))
+        .insert_resource(self.render_mode)
+        .init_resource::<VoxelRegistry>()
+        .init_resource::<VoxelWorld>()
+        .init_resource::<AtlasLayout>()
+        .init_resource::<ChunkTextureMode>()
+        .insert_resource(chunk_meshers())
+        .init_resource::<AoSettings>()
+        .init_resource::<FaceShading>()
+        .init_resource::<SkirtSettings>()
+        .init_resource::<LodSettings>()
+        .init_resource::<WindingOrder>()
+        .init_resource::<BackfaceCulling>()
+        .init_resource::<ColliderSettings>()
+        .init_resource::<MeshingBudget>()
+        .init_resource::<MeshBufferPool>()
+        .add_systems(Startup, setup_chunk_materials.in_set(VoxelSet::Mesh))
+        .add_systems(
+            PostUpdate,
+            remove_despawned_chunk_meshes.before(chunk::despawn_chunk_children),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    sync_ao_strength,
+                    sync_face_shading,
+                    sync_backface_culling,
+                    (log_mesher_comparison, despawn_chunk_meshes)
+                        .chain()
+                        .run_if(chunk_mesher_switched),
+                    (update_chunk_lods, queue_chunk_meshing)
+                        .chain()
+                        .after(despawn_chunk_meshes)
+                        .run_if(not(resource_exists::<InstancedVoxels>))
+                        .run_if(resource_equals(RenderMode::Mesh)),
+                )
+                    .in_set(VoxelSet::Mesh),
+                (
+                    material::build_array_texture,
+                    render_chunks
+                        .run_if(not(resource_exists::<InstancedVoxels>))
+                        .run_if(resource_equals(RenderMode::Mesh)),
+                )
+                    .in_set(VoxelSet::Render),
+            ),
+        );
+    }
+}
+
+/// Spawns the camera over the terrain as set by [`SpawnConfig`] and flies it with `WASD`,
+/// `Space` and `Shift`, colliding with the terrain, at the speed of its [`CameraController`].
+/// `Escape` quits.
+#[derive(Debug, Default)]
+pub struct FlyCameraPlugin;
+
+impl Plugin for FlyCameraPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.init_resource::<VoxelRegistry>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<SpawnConfig>()
+            .add_systems(Startup, spawn_camera.in_set(VoxelSet::Input))
+            .add_systems(
+                Update,
+                (place_camera, adjust_camera_speed, handle_input)
+                    .chain()
+                    .in_set(VoxelSet::Input),
+            );
+    }
+}
+
+/// Breaking voxels under the crosshair, with the block highlight and crack overlay, and the
+/// bucket fill tool.
+#[derive(Debug, Default)]
+pub struct EditingPlugin;
+
+impl Plugin for EditingPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.init_resource::<VoxelRegistry>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<AtlasLayout>()
+            .init_resource::<VoxelTickQueue>()
+            .init_resource::<BlockTarget>()
+            .init_resource::<BreakProgress>()
+            .init_resource::<BucketFill>()
+            .add_systems(
+                Startup,
+                (editing::setup_crack_overlay, editing::setup_block_highlight)
+                    .in_set(VoxelSet::Input),
+            )
+            .add_systems(
+                Update,
+                (
+                    editing::update_target,
+                    editing::update_block_highlight,
+                    editing::break_blocks,
+                    editing::bucket_fill,
+                    editing::update_crack_overlay,
+                )
+                    .chain()
+                    .in_set(VoxelSet::Input),
+            );
+    }
+}
+
+/// Keys toggling and cycling the other plugins' settings and [`ChunkWireframes`], logging of
+/// [`MeshStats`], and the debug panel with the `debug_ui` feature. Expects [`LightingPlugin`]
+/// and [`MeshingPlugin`], whose settings it changes.
+#[derive(Debug, Default)]
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        if !app.is_plugin_added::<WireframePlugin>() {
+            app.add_plugins(WireframePlugin);
+        }
+        app.init_resource::<ChunkWireframes>().add_systems(
+            Update,
+            (
+                toggle_ao,
+                toggle_face_shading,
+                toggle_backface_culling,
+                toggle_skirts,
+                toggle_fog,
+                (toggle_wireframes, sync_chunk_wireframes).chain(),
+                cycle_chunk_mesher,
+                log_mesh_stats,
+            )
+                .in_set(VoxelSet::Input),
+        );
+
+        #[cfg(feature = "debug_ui")]
+        app.add_plugins(crate::debug_ui::DebugUiPlugin);
+    }
+}
+
+/// The built-in meshers, plus [`SurfaceNets`] registered the way a mesher from outside the crate
+/// would be.
+fn chunk_meshers() -> ChunkMeshers {
+    let mut meshers = ChunkMeshers::default();
+    meshers.register(SurfaceNets);
+    meshers
+}
+
+/// Generates the [`WorldConfig`] terrain, registering every chunk with the [`VoxelWorld`]. Chunk
+/// entities are named after their position, like their render entities, for inspectors.
+///
+/// Chunks the [`AutosaveConfig`] save holds are loaded in place of their generated terrain, if
+/// autosaving is enabled. Chunks that fail to load are generated and logged.
+fn spawn_world(
+    mut commands: Commands,
+    config: Res<WorldConfig>,
+    registry: Res<VoxelRegistry>,
+    autosave: Option<Res<AutosaveConfig>>,
+    mut world: ResMut<VoxelWorld>,
+    mut timings: ResMut<FrameTimings>,
+) {
+    let start = Instant::now();
+    let mut saved = autosave
+        .filter(|autosave| autosave.enabled)
+        .and_then(|autosave| {
+            SavedWorld::open(&autosave.path, &registry, autosave.unknown_voxel)
+                .inspect_err(|err| warn!("failed to open save {:?}: {err}", autosave.path))
+                .ok()
+                .flatten()
+        });
+
+    generation::generate_area(config.size.x, config.size.y, &registry)
+        .into_iter()
+        .for_each(|generated| {
+            let pos = generated.chunk_pos();
+            let loaded = saved.as_mut().and_then(|saved| {
+                saved
+                    .read_chunk(pos)
+                    .inspect_err(|err| warn!("failed to load chunk {pos}: {err}"))
+                    .ok()
+                    .flatten()
+            });
+            let chunk = loaded.unwrap_or(generated);
+            let name = Name::new(format!("chunk {pos}"));
+            world.insert_chunk(pos, commands.spawn((name, chunk)).id());
+        });
+    timings.record("generate", start.elapsed());
+}
+
+fn spawn_camera(mut commands: Commands, config: Res<SpawnConfig>) {
+    commands
+        .spawn((
+            Camera3dBundle {
+                transform: Transform::from_translation(config.fallback)
+                    .looking_to(vec3(1.0, 0.0, 2.0), Vec3::Y),
+                ..Default::default()
+            },
+            GpuCulling,
+            CameraController::default(),
+            AwaitingSpawn,
+        ))
+        .insert(Tonemapping::TonyMcMapface)
+        .insert(BloomSettings::default())
+        .insert(VolumetricFogSettings {
+            ambient_intensity: 0.0,
+            ..Default::default()
+        });
+}
+
+/// Moves the camera [`eye_height`](SpawnConfig::eye_height) above the [`SpawnConfig`] column as
+/// soon as the column has a surface, keeping the camera's orientation.
+#[allow(clippy::type_complexity)]
+fn place_camera(
+    mut commands: Commands,
+    config: Res<SpawnConfig>,
+    world_config: Option<Res<WorldConfig>>,
+    world: Res<VoxelWorld>,
+    chunks: Query<&Chunk>,
+    mut camera: Query<(Entity, &mut Transform), (With<Camera3d>, With<AwaitingSpawn>)>,
+) {
+    let Ok((entity, mut transform)) = camera.get_single_mut() else {
+        return;
+    };
+    let column = config.column.unwrap_or_else(|| {
+        let size = world_config.map_or(UVec2::ZERO, |world_config| world_config.size);
+        (size * Chunk::SIZE as u32 / 2).as_ivec2()
+    });
+    let Some(surface) = world.surface_height(&chunks, column) else {
+        return;
+    };
+
+    let eye = vec3(
+        column.x as f32,
+        surface as f32 + config.eye_height,
+        column.y as f32,
+    );
+    transform.translation = eye * Voxel::SIZE;
+    commands.entity(entity).remove::<AwaitingSpawn>();
+}
+
+/// Loads the chunk textures and creates the [`VoxelAssets`] materials for the
+/// [`ChunkTextureMode`].
+#[allow(clippy::too_many_arguments)]
+fn setup_chunk_materials(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    texture_mode: Res<ChunkTextureMode>,
+    ao: Res<AoSettings>,
+    face_shading: Res<FaceShading>,
+    images: Res<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    let texture: Handle<Image> = asset_server.load("array_texture.png");
+    let material = match *texture_mode {
+        ChunkTextureMode::Array => {
+            let array = images.reserve_handle();
+            commands.insert_resource(PendingArrayTexture {
+                atlas: texture,
+                array: array.clone(),
+            });
+            commands.insert_resource(VoxelInstanceTexture(array.clone()));
+            // Normal maps hold directions, not colors.
+            let normal_map: Handle<Image> = asset_server.load_with_settings(
+                NORMAL_MAP,
+                |settings: &mut ImageLoaderSettings| {
+                    settings.is_srgb = false;
+                },
+            );
+
+            let mut material = |alpha_mode| {
+                chunk_materials.add(ChunkMaterial {
+                    base: StandardMaterial {
+                        alpha_mode,
+                        normal_map_texture: Some(normal_map.clone()),
+                        ..Default::default()
+                    },
+                    extension: ChunkArrayTexture {
+                        array_texture: array.clone(),
+                        ao_strength: ao.strength(),
+                        face_shades: material::face_shades(&face_shading),
+                    },
+                })
+            };
+            ChunkMaterialHandle::Array(ChunkMaterials {
+                opaque: material(AlphaMode::Mask(0.5)),
+                translucent: material(AlphaMode::Blend),
+            })
+        }
+        ChunkTextureMode::Atlas => {
+            let mut material = |alpha_mode| {
+                materials.add(StandardMaterial {
+                    base_color_texture: Some(texture.clone()),
+                    alpha_mode,
+                    ..Default::default()
+                })
+            };
+            ChunkMaterialHandle::Standard(ChunkMaterials {
+                opaque: material(AlphaMode::Mask(0.5)),
+                translucent: material(AlphaMode::Blend),
+            })
+        }
+    };
+
+    commands.insert_resource(VoxelAssets { material });
+}
+
+/// Stamps the [`WorldConfig::schematic`] onto the highest voxel of its column.
+fn stamp_schematic(
+    config: Res<WorldConfig>,
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    let Some((path, column)) = &config.schematic else {
+        return;
+    };
+    let schematic = match schematic::load_schematic(path) {
+        Ok(schematic) => schematic,
+        Err(err) => {
+            warn!("{}: {err}", path.display());
+            return;
+        }
+    };
+
+    let column = *column;
+    let ground = world.surface_height(&chunks.to_readonly(), column);
+    if let Some(y) = ground {
+        let origin = IVec3::new(column.x, y, column.y);
+        schematic.stamp(&world, &mut chunks, &registry, origin);
+    }
+}
+
+/// Picks the level of detail of every chunk from its distance to the camera. Chunks whose level
+/// changes are marked dirty, so they are remeshed at the new level.
+fn update_chunk_lods(
+    settings: Res<LodSettings>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    let camera = camera
+        .get_single()
+        .map_or(Vec3::ZERO, |camera| camera.translation() / Voxel::SIZE);
+    chunks.iter_mut().for_each(|mut chunk| {
+        let center = chunk.origin().as_vec3() + chunk.dims().as_vec3() / 2.0;
+        let lod = settings.select(chunk.lod(), center.distance(camera));
+        chunk.set_lod(lod);
+    });
+}
+
+/// A chunk's meshes being built on the [`AsyncComputeTaskPool`]. Replacing the component drops,
+/// and so cancels, the task.
+#[derive(Component)]
+struct MeshingTask(Task<MeshingOutput>);
+
+struct MeshingOutput {
+    /// [`ChunkMesher::name`](mesh::ChunkMesher::name) of the mesher that built the meshes.
+    mesher: String,
+    meshes: ChunkMeshes,
+    /// Built if [`ColliderSettings`] was enabled when the task started.
+    collider: Option<ChunkCollider>,
+    build_time: Duration,
+    /// The buffers the task meshed with, for [`render_chunks`] to return to [`MeshBufferPool`].
+    buffers: MeshBufferPool,
+    key: MeshCacheKey,
+    /// Whether the meshes came from the [`ChunkMeshCache`] rather than a mesher.
+    cached: bool,
+}
+
+/// What a chunk's meshes were built from besides its voxels and settings, which
+/// [`Chunk::mesh_revision`] covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MeshCacheKey {
+    lod: u32,
+    neighbor_lods: [u32; 6],
+    revision: u64,
+}
+
+/// The meshes and collider a chunk was last built with at each level of detail, so a chunk
+/// moving back to a level it left reuses them instead of remeshing. Entries from before the
+/// chunk's current [`Chunk::mesh_revision`] are evicted as soon as it is built again, and there
+/// is at most one per level of [`LodSettings::FACTORS`].
+#[derive(Default, Component)]
+struct ChunkMeshCache(Vec<(MeshCacheKey, ChunkMeshes, Option<ChunkCollider>)>);
+
+impl ChunkMeshCache {
+    fn get(&self, key: MeshCacheKey) -> Option<(&ChunkMeshes, &Option<ChunkCollider>)> {
+        self.0
+            .iter()
+            .find(|(cached, ..)| *cached == key)
+            .map(|(_, meshes, collider)| (meshes, collider))
+    }
+
+    fn insert(&mut self, key: MeshCacheKey, meshes: ChunkMeshes, collider: Option<ChunkCollider>) {
+        self.0
+            .retain(|(cached, ..)| cached.revision == key.revision && cached.lod != key.lod);
+        if self.0.len() >= LodSettings::FACTORS.len() {
+            self.0.remove(0);
+        }
+        self.0.push((key, meshes, collider));
+    }
+}
+
+/// Snapshots dirty chunks along with their neighbors' borders and meshes them on the
+/// [`AsyncComputeTaskPool`], as many per frame as the [`MeshingBudget`] allows. A chunk edited
+/// while its task is in flight gets a fresh task, which cancels the stale one. Chunks that only
+/// changed level of detail reuse their [`ChunkMeshCache`] entry if they have one, outside the
+/// budget.
+#[allow(clippy::too_many_arguments)]
+fn queue_chunk_meshing(
+    mut commands: Commands,
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    meshers: Res<ChunkMeshers>,
+    atlas: Res<AtlasLayout>,
+    // Grouped, as systems take at most 16 parameters.
+    (ao, face_shading, skirts, winding): (
+        Res<AoSettings>,
+        Res<FaceShading>,
+        Res<SkirtSettings>,
+        Res<WindingOrder>,
+    ),
+    colliders: Res<ColliderSettings>,
+    budget: Res<MeshingBudget>,
+    voxel_assets: Res<VoxelAssets>,
+    mut pool: ResMut<MeshBufferPool>,
+    mut stats: ResMut<MeshStats>,
+    mut timings: ResMut<FrameTimings>,
+    camera: Query<(&GlobalTransform, Option<&Frustum>), With<Camera3d>>,
+    lights: Query<&ChunkLight>,
+    caches: Query<&ChunkMeshCache>,
+    mut chunk_query: Query<(Entity, &mut Chunk)>,
+) {
+    let (camera, frustum) = camera
+        .get_single()
+        .map_or((Vec3::ZERO, None), |(camera, frustum)| {
+            (camera.translation() / Voxel::SIZE, frustum)
+        });
+    let mut dirty: Vec<_> = chunk_query
+        .iter()
+        .filter(|(_, chunk)| chunk.is_dirty())
+        .map(|(entity, chunk)| {
+            let center = chunk.origin().as_vec3() + chunk.dims().as_vec3() / 2.0;
+            // Without a frustum yet, every chunk counts as visible.
+            let visible = frustum.is_none_or(|frustum| {
+                let world_from_local = Affine3A::from_scale_rotation_translation(
+                    Vec3::splat(Voxel::SIZE),
+                    Quat::IDENTITY,
+                    chunk.origin().as_vec3() * Voxel::SIZE,
+                );
+                frustum.intersects_obb(
+                    &mesh::chunk_aabb(chunk.dims()),
+                    &world_from_local,
+                    true,
+                    true,
+                )
+            });
+            let priority = (!chunk.is_urgent(), !visible);
+            (priority, center.distance_squared(camera), entity)
+        })
+        .collect();
+    stats.remesh_backlog = dirty.len();
+    if dirty.is_empty() {
+        return;
+    }
+    dirty.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let start = Instant::now();
+    let task_pool = AsyncComputeTaskPool::get();
+    let registry = Arc::new(registry.clone());
+    let (mesher, atlas) = (meshers.active().clone(), *atlas);
+    let (ao_strength, skirt_depth, winding) = (ao.strength(), skirts.depth(), *winding);
+    let face_shading = *face_shading;
+    let build_colliders = colliders.enabled;
+    let array = matches!(voxel_assets.material, ChunkMaterialHandle::Array(_));
+    let (mut started, mut reused) = (0, 0);
+    for ((normal, _), _, entity) in dirty {
+        let Ok((_, chunk)) = chunk_query.get(entity) else {
+            continue;
+        };
+        let pos = chunk.chunk_pos();
+        let lod = chunk.lod();
+        let neighbor_lods = pos.neighbors().map(|pos| {
+            world
+                .chunk_at(pos)
+                .and_then(|neighbor| chunk_query.get(neighbor).ok())
+                .map_or(1, |(_, chunk)| chunk.lod())
+        });
+        let key = MeshCacheKey {
+            lod,
+            neighbor_lods,
+            revision: chunk.mesh_revision(),
+        };
+
+        let cached = caches.get(entity).ok().and_then(|cache| cache.get(key));
+        if let Some((meshes, collider)) = cached {
+            let output = MeshingOutput {
+                mesher: mesher.name().to_owned(),
+                meshes: meshes.clone(),
+                collider: collider.clone(),
+                build_time: Duration::ZERO,
+                buffers: MeshBufferPool::default(),
+                key,
+                cached: true,
+            };
+            commands
+                .entity(entity)
+                .insert(MeshingTask(task_pool.spawn(async move { output })));
+            if let Ok((_, mut chunk)) = chunk_query.get_mut(entity) {
+                chunk.mark_clean();
+            }
+            reused += 1;
+            continue;
+        }
+
+        let exhausted = started >= budget.max_chunks || start.elapsed() >= budget.max_time;
+        if normal && exhausted {
+            continue;
+        }
+
+        let neighbors = pos.neighbors().map(|pos| {
+            let neighbor = world.chunk_at(pos)?;
+            chunk_query
+                .get(neighbor)
+                .ok()
+                .map(|(_, chunk)| chunk.data())
+        });
+        let diagonals = mesh::DIAGONAL_OFFSETS.map(|offset| {
+            let neighbor = world.chunk_at(pos + offset)?;
+            chunk_query
+                .get(neighbor)
+                .ok()
+                .map(|(_, chunk)| chunk.data())
+        });
+        let snapshot = info_span!("chunk_snapshot", chunk = ?pos.0).in_scope(|| {
+            ChunkSnapshot::new(ChunkNeighborhood {
+                center: chunk.data(),
+                neighbors,
+                diagonals,
+            })
+        });
+        let light = lights.get(entity).ok().cloned();
+        let neighbor_light = pos.neighbors().map(|pos| {
+            let neighbor = world.chunk_at(pos)?;
+            lights.get(neighbor).ok().cloned()
+        });
+        if let Ok((_, mut chunk)) = chunk_query.get_mut(entity) {
+            chunk.mark_clean();
+        }
+        started += 1;
+
+        let (registry, mesher) = (registry.clone(), mesher.clone());
+        let mut buffers = pool.split_off(2);
+        let task = task_pool.spawn(async move {
+            let _span = info_span!("mesh_chunk", chunk = ?pos.0, mesher = mesher.name()).entered();
+            let start = Instant::now();
+            let uvs = if array {
+                UvLayout::Array
+            } else {
+                UvLayout::Atlas(&atlas)
+            };
+            let light = info_span!("vertex_light").in_scope(|| {
+                VertexLight::new(
+                    snapshot.neighborhood(),
+                    &registry,
+                    light.as_ref(),
+                    neighbor_light.each_ref().map(Option::as_ref),
+                )
+            });
+            let context = MeshContext {
+                registry: &registry,
+                uvs,
+                ao_strength,
+                face_shading,
+                skirt_depth,
+                lod,
+                neighbor_lods,
+                winding,
+                light: Some(&light),
+            };
+            // Colliders keep full resolution whatever the level of detail.
+            let collider =
+                build_colliders.then(|| ChunkCollider::build(snapshot.neighborhood(), &registry));
+            let snapshot = if lod > 1 {
+                snapshot.downsample(lod)
+            } else {
+                snapshot
+            };
+            let meshes = info_span!("mesher").in_scope(|| {
+                mesh::mesh_chunk(&*mesher, snapshot.neighborhood(), &context, &mut buffers)
+            });
+
+            MeshingOutput {
+                mesher: mesher.name().to_owned(),
+                meshes,
+                collider,
+                build_time: start.elapsed(),
+                buffers,
+                key,
+                cached: false,
+            }
+        });
+        commands.entity(entity).insert(MeshingTask(task));
+    }
+    stats.remesh_backlog -= started + reused;
+    timings.record("queue meshing", start.elapsed());
+}
+
+/// Meshes of a [`StaticWorld`] waiting to be merged, and the merged meshes they went into.
+#[derive(Default)]
+struct StaticWorldMeshes {
+    /// The latest mesh of every chunk with any faces in the pass, by chunk entity.
+    chunks: HashMap<MeshPass, HashMap<Entity, (IVec3, Mesh)>>,
+    /// The merged mesh of each pass, drawn by one render entity spawned on its first merge and
+    /// overwritten by every merge after.
+    merged: HashMap<MeshPass, Handle<Mesh>>,
+    /// Whether any chunk mesh changed since the last merge.
+    changed: bool,
+}
+
+/// Swaps in the meshes of finished [`MeshingTask`]s. A remeshed chunk's new mesh is written over
+/// its existing mesh asset, so its handle and render entity stay the same and only the chunks
+/// that were remeshed are touched. Mesh assets are only allocated on a chunk's first build and
+/// removed once a pass turns empty. Bounds are set to the chunk's volume by
+/// [`mesh::chunk_aabb`], for frustum culling. Never waits on a task that is still running.
+///
+/// While levels of detail are enabled, newly built meshes are also kept in the chunk's
+/// [`ChunkMeshCache`].
+#[allow(clippy::too_many_arguments)]
+fn render_chunks(
+    mut commands: Commands,
+    static_world: Option<Res<StaticWorld>>,
+    voxel_assets: Res<VoxelAssets>,
+    lod_settings: Res<LodSettings>,
+    mut stats: ResMut<MeshStats>,
+    mut timings: ResMut<FrameTimings>,
+    mut pool: ResMut<MeshBufferPool>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut tasks: Query<(
+        Entity,
+        &mut MeshingTask,
+        &mut Chunk,
+        Option<&mut ChunkMeshCache>,
+    )>,
+    mut rendered_query: Query<(Entity, &ChunkChild, &MeshPass, &mut Handle<Mesh>)>,
+    mut static_meshes: Local<StaticWorldMeshes>,
+    mut removed: RemovedComponents<Chunk>,
+) {
+    // Unloaded chunks leave the static world with the next merge.
+    let StaticWorldMeshes {
+        chunks, changed, ..
+    } = &mut *static_meshes;
+    for entity in removed.read() {
+        for chunks in chunks.values_mut() {
+            *changed |= chunks.remove(&entity).is_some();
+        }
+    }
+
+    let finished: Vec<_> = tasks
+        .iter_mut()
+        .filter_map(|(entity, mut task, ..)| {
+            let output = block_on(future::poll_once(&mut task.0))?;
+            commands.entity(entity).remove::<MeshingTask>();
+            Some((entity, output))
+        })
+        .collect();
+    if finished.is_empty() && !static_meshes.changed {
+        return;
+    }
+    let start = Instant::now();
+    let in_flight = tasks.iter().count() - finished.len();
+
+    let mut existing: HashMap<_, _> = rendered_query
+        .iter()
+        .map(|(entity, parent, pass, _)| ((parent.0, *pass), entity))
+        .collect();
+    let mut rendered = Vec::new();
+    for (entity, output) in finished {
+        pool.merge(output.buffers);
+        let chunk_stats = ChunkMeshStats::new(&output.meshes, output.build_time);
+        if !output.cached {
+            stats.record(entity, &output.mesher, &chunk_stats);
+            timings.record("mesh tasks", output.build_time);
+        }
+        commands.entity(entity).insert(chunk_stats);
+        let Ok((_, _, mut chunk, cache)) = tasks.get_mut(entity) else {
+            continue;
+        };
+        // Without levels of detail to switch between, the cache would only duplicate meshes.
+        if !output.cached && lod_settings.enabled && static_world.is_none() {
+            let (meshes, collider) = (output.meshes.clone(), output.collider.clone());
+            match cache {
+                Some(mut cache) => cache.insert(output.key, meshes, collider),
+                None => {
+                    let mut cache = ChunkMeshCache::default();
+                    cache.insert(output.key, meshes, collider);
+                    commands.entity(entity).insert(cache);
+                }
+            }
+        }
+        match output.collider {
+            Some(collider) => commands.entity(entity).insert(collider),
+            None => commands.entity(entity).remove::<ChunkCollider>(),
+        };
+
+        let transform = Transform::from_translation(chunk.origin().as_vec3() * Voxel::SIZE)
+            .with_scale(Vec3::splat(Voxel::SIZE));
+        let aabb = mesh::chunk_aabb(chunk.dims());
+        for (pass, mesh) in output.meshes.into_passes() {
+            // Meshers are trusted in release builds. A rejected mesh leaves the pass as it was.
+            if cfg!(debug_assertions) {
+                if let Err(err) = mesh::validate_mesh(&mesh) {
+                    error!(
+                        "{} built an invalid {pass:?} mesh for chunk {:?}: {err}",
+                        output.mesher,
+                        chunk.chunk_pos().0
+                    );
+                    continue;
+                }
+            }
+            let previous = existing.remove(&(entity, pass));
+            // Empty passes, e.g. every pass of a chunk of air, get no render entity at all.
+            if mesh.count_vertices() == 0 {
+                if let Some(handle) = chunk.mesh_mut(pass).take() {
+                    meshes.remove(&handle);
+                }
+                if let Some(previous) = previous {
+                    commands.entity(previous).despawn();
+                }
+                let static_chunks = static_meshes.chunks.get_mut(&pass);
+                if static_chunks
+                    .and_then(|chunks| chunks.remove(&entity))
+                    .is_some()
+                {
+                    static_meshes.changed = true;
+                }
+                continue;
+            }
+            // Static worlds merge every chunk's mesh below, so chunks keep no handle of their own.
+            if static_world.is_some() {
+                if let Some(handle) = chunk.mesh_mut(pass).take() {
+                    meshes.remove(&handle);
+                }
+                let static_chunks = static_meshes.chunks.entry(pass).or_default();
+                static_chunks.insert(entity, (chunk.origin(), mesh));
+                static_meshes.changed = true;
+                continue;
+            }
+
+            // This runs in `Update`, and the render world only extracts changed assets once the
+            // main world's schedule is done, so it never sees a mesh halfway replaced.
+            let mesh = match chunk.mesh_mut(pass).clone() {
+                Some(handle) if meshes.contains(&handle) => {
+                    meshes.insert(&handle, mesh);
+                    handle
+                }
+                _ => meshes.add(mesh),
+            };
+            *chunk.mesh_mut(pass) = Some(mesh.clone());
+            match previous.and_then(|previous| rendered_query.get_mut(previous).ok()) {
+                Some((previous, _, _, mut handle)) => {
+                    if *handle != mesh {
+                        *handle = mesh;
+                    }
+                    commands.entity(previous).insert(aabb);
+                }
+                None => {
+                    let name = Name::new(format!("chunk {} {pass:?} mesh", chunk.chunk_pos()));
+                    rendered.push((mesh, transform, pass, (name, ChunkChild(entity), aabb)));
+                }
+            }
+        }
+    }
+    spawn_chunk_meshes(&mut commands, &voxel_assets.material, rendered);
+    timings.record("upload meshes", start.elapsed());
+
+    // Static worlds wait for every chunk in flight, so each pass merges once per settled batch,
+    // always from the meshes of every chunk.
+    if static_world.is_none() || in_flight > 0 || !static_meshes.changed {
+        return;
+    }
+    static_meshes.changed = false;
+
+    // Vertices of merged meshes are already offset to their chunk, so only the voxel scale
+    // remains for the transform.
+    let merged_transform = Transform::from_scale(Vec3::splat(Voxel::SIZE));
+    let StaticWorldMeshes { chunks, merged, .. } = &mut *static_meshes;
+    let mut spawned = Vec::new();
+    for (pass, chunk_meshes) in chunks.iter() {
+        let chunk_meshes: Vec<_> = chunk_meshes.values().cloned().collect();
+        let mesh = mesh::merge_chunk_meshes(&chunk_meshes);
+        match merged.get(pass) {
+            // A pass left without faces keeps its render entity, which draws nothing until the
+            // asset is inserted again.
+            Some(handle) if chunk_meshes.is_empty() => {
+                meshes.remove(handle);
+            }
+            Some(handle) => meshes.insert(handle, mesh),
+            None if chunk_meshes.is_empty() => {}
+            None => {
+                let handle = meshes.add(mesh);
+                merged.insert(*pass, handle.clone());
+                let name = Name::new(format!("static world {pass:?} mesh"));
+                spawned.push((handle, merged_transform, *pass, name));
+            }
+        }
+    }
+    spawn_chunk_meshes(&mut commands, &voxel_assets.material, spawned);
+}
+
+/// Removes the mesh assets of chunks that were despawned or lost their [`Chunk`], rather than
+/// waiting for their handles to drop along with the render entities.
+fn remove_despawned_chunk_meshes(
+    mut removed: RemovedComponents<Chunk>,
+    rendered: Query<(&ChunkChild, &Handle<Mesh>), With<MeshPass>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let removed: Vec<_> = removed.read().collect();
+    if removed.is_empty() {
+        return;
+    }
+
+    rendered
+        .iter()
+        .filter(|(ChunkChild(chunk), _)| removed.contains(chunk))
+        .for_each(|(_, mesh)| {
+            meshes.remove(mesh);
+        });
+}
+
+/// Spawns a render entity per mesh with the material of its pass, tagged as a [`ChunkMesh`]
+/// with the pass and `extra`.
+fn spawn_chunk_meshes<B: Bundle>(
+    commands: &mut Commands,
+    material: &ChunkMaterialHandle,
+    rendered: Vec<(Handle<Mesh>, Transform, MeshPass, B)>,
+) {
+    let rendered = rendered.into_iter();
+    match material.clone() {
+        ChunkMaterialHandle::Standard(materials) => {
+            commands.spawn_batch(rendered.map(move |(mesh, transform, pass, extra)| {
+                let bundle = PbrBundle {
+                    mesh,
+                    material: materials.get(pass).clone(),
+                    transform,
+                    ..Default::default()
+                };
+                (bundle, pass, ChunkMesh, extra)
+            }))
+        }
+        ChunkMaterialHandle::Array(materials) => {
+            commands.spawn_batch(rendered.map(move |(mesh, transform, pass, extra)| {
+                let bundle = MaterialMeshBundle::<ChunkMaterial> {
+                    mesh,
+                    material: materials.get(pass).clone(),
+                    transform,
+                    ..Default::default()
+                };
+                (bundle, pass, ChunkMesh, extra)
+            }))
+        }
+    }
+}
+
+fn handle_input(
+    timer: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    chunks: Query<&Chunk>,
+    mut app_exit_writer: EventWriter<AppExit>,
+    mut camera: Query<(&mut Transform, &CameraController), With<Camera3d>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        app_exit_writer.send(AppExit::Success);
+    }
+
+    let mut translate_camera = |translation: Vec3| {
+        let (mut transform, controller) = camera.single_mut();
+        let speed = controller.current_speed(&keys);
+        let delta = translation * speed * timer.delta_seconds() / Voxel::SIZE;
+
+        // Voxels are centered on their coordinate, so shift into the collision grid.
+        let center = transform.translation / Voxel::SIZE + Vec3::splat(0.5);
+        let moved = collision::move_and_slide(center, CAMERA_HALF_EXTENTS, delta, |position| {
+            world
+                .voxel(&chunks, position)
+                .is_some_and(|voxel| registry.is_collidable(voxel.id))
+        });
+        transform.translation = (moved - Vec3::splat(0.5)) * Voxel::SIZE;
+    };
+
+    keys.get_pressed().for_each(|key| match key {
+        KeyCode::KeyW => translate_camera(Vec3::Z),
+        KeyCode::KeyS => translate_camera(-Vec3::Z),
+        KeyCode::KeyA => translate_camera(Vec3::X),
+        KeyCode::KeyD => translate_camera(-Vec3::X),
+        KeyCode::Space => translate_camera(Vec3::Y),
+        KeyCode::ShiftLeft => translate_camera(-Vec3::Y),
+        _ => {}
+    });
+}
+
+/// Scales the camera's [`CameraController::speed`] with the scroll wheel.
+fn adjust_camera_speed(
+    mut wheel: EventReader<MouseWheel>,
+    mut camera: Query<&mut CameraController, With<Camera3d>>,
+) {
+    // Touchpads scroll in pixels, roughly this many to a line.
+    const PIXELS_PER_LINE: f32 = 16.0;
+    let lines: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    if lines == 0.0 {
+        return;
+    }
+
+    for mut controller in &mut camera {
+        let (min, max) = controller.speed_range;
+        controller.speed =
+            (controller.speed * controller.scroll_factor.powf(lines)).clamp(min, max);
+    }
+}
+
+/// Switches to the next of the [`ChunkMeshers`] on `M`.
+fn cycle_chunk_mesher(keys: Res<ButtonInput<KeyCode>>, mut meshers: ResMut<ChunkMeshers>) {
+    if keys.just_pressed(KeyCode::KeyM) {
+        meshers.cycle();
+    }
+}
+
+/// Whether a different one of the [`ChunkMeshers`] is active than on the last run. Compares
+/// indices rather than change ticks, since UI widgets touch the resource every frame.
+fn chunk_mesher_switched(meshers: Res<ChunkMeshers>, mut last: Local<Option<usize>>) -> bool {
+    let active = meshers.active_index();
+    let switched = last.is_some_and(|last| last != active);
+    *last = Some(active);
+
+    switched
+}
+
+/// Logs the newly active mesher along with how every mesher used so far compares.
+fn log_mesher_comparison(meshers: Res<ChunkMeshers>, stats: Res<MeshStats>) {
+    info!(
+        "chunk mesher: {}\n{}",
+        meshers.active().name(),
+        stats.mesher_comparison()
+    );
+}
+
+/// Regenerates the terrain of every chunk on `G`, remeshing the whole world at once.
+fn regenerate_world(
+    keys: Res<ButtonInput<KeyCode>>,
+    config: Res<WorldConfig>,
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    mut timings: ResMut<FrameTimings>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    if !keys.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+
+    let start = Instant::now();
+    for generated in generation::generate_area(config.size.x, config.size.y, &registry) {
+        let entity = world.chunk_at(generated.chunk_pos());
+        if let Some(mut chunk) = entity.and_then(|entity| chunks.get_mut(entity).ok()) {
+            *chunk.data_mut() = generated.data().clone();
+        }
+    }
+    timings.record("generate", start.elapsed());
+}
+
+/// Despawns every chunk mesh entity along with its mesh asset and marks every chunk dirty, so
+/// they are all meshed again.
+fn despawn_chunk_meshes(
+    mut commands: Commands,
+    rendered: Query<(Entity, &Handle<Mesh>), With<MeshPass>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    rendered.iter().for_each(|(entity, mesh)| {
+        meshes.remove(mesh);
+        commands.entity(entity).despawn();
+    });
+    chunks.iter_mut().for_each(|mut chunk| {
+        chunk.mesh = None;
+        chunk.translucent_mesh = None;
+        chunk.mark_dirty();
+    });
+}
+
+/// Toggles baked ambient occlusion, marking every chunk for remeshing.
+fn toggle_ao(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut ao: ResMut<AoSettings>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    if keys.just_pressed(KeyCode::KeyO) {
+        ao.enabled = !ao.enabled;
+        chunks.iter_mut().for_each(|mut chunk| chunk.mark_dirty());
+    }
+}
+
+/// Toggles [`FaceShading`] on `H`, marking every chunk for remeshing.
+fn toggle_face_shading(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut shading: ResMut<FaceShading>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    if keys.just_pressed(KeyCode::KeyH) {
+        shading.enabled = !shading.enabled;
+        chunks.iter_mut().for_each(|mut chunk| chunk.mark_dirty());
+    }
+}
+
+/// Toggles chunk skirts on `K`, marking every chunk for remeshing.
+fn toggle_skirts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut skirts: ResMut<SkirtSettings>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    if keys.just_pressed(KeyCode::KeyK) {
+        skirts.enabled = !skirts.enabled;
+        chunks.iter_mut().for_each(|mut chunk| chunk.mark_dirty());
+    }
+}
+
+/// Toggles distance fog on `F`.
+fn toggle_fog(keys: Res<ButtonInput<KeyCode>>, mut fog: ResMut<FogConfig>) {
+    if keys.just_pressed(KeyCode::KeyF) {
+        fog.enabled = !fog.enabled;
+    }
+}
+
+/// Logs a summary of [`MeshStats`] on `P`.
+fn log_mesh_stats(keys: Res<ButtonInput<KeyCode>>, stats: Res<MeshStats>) {
+    if keys.just_pressed(KeyCode::KeyP) {
+        info!("mesh stats: {}", stats.summary());
+    }
+}
+
+/// Copies [`FaceShading`] into the chunk materials, which shade packed meshes with it.
+fn sync_face_shading(
+    shading: Res<FaceShading>,
+    voxel_assets: Res<VoxelAssets>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    if !shading.is_changed() {
+        return;
+    }
+
+    if let ChunkMaterialHandle::Array(handles) = &voxel_assets.material {
+        for handle in [&handles.opaque, &handles.translucent] {
+            if let Some(material) = materials.get_mut(handle) {
+                material.extension.face_shades = material::face_shades(&shading);
+            }
+        }
+    }
+}
+
+/// Toggles backface culling of the chunk materials on `C`, to look for holes in the terrain.
+fn toggle_backface_culling(keys: Res<ButtonInput<KeyCode>>, mut culling: ResMut<BackfaceCulling>) {
+    if keys.just_pressed(KeyCode::KeyC) {
+        culling.enabled = !culling.enabled;
+    }
+}
+
+/// Toggles [`ChunkWireframes`] on `F4`, to see the triangles a mesher produced.
+fn toggle_wireframes(keys: Res<ButtonInput<KeyCode>>, mut wireframes: ResMut<ChunkWireframes>) {
+    if keys.just_pressed(KeyCode::F4) {
+        wireframes.enabled = !wireframes.enabled;
+    }
+}
+
+/// Adds or removes [`Wireframe`] on every [`ChunkMesh`] as [`ChunkWireframes`] changes, and on
+/// chunk meshes spawned while it is on. Turns it back off if the device can't draw lines.
+fn sync_chunk_wireframes(
+    mut commands: Commands,
+    mut wireframes: ResMut<ChunkWireframes>,
+    device: Option<Res<RenderDevice>>,
+    chunk_meshes: Query<Entity, With<ChunkMesh>>,
+    added: Query<Entity, Added<ChunkMesh>>,
+) {
+    if wireframes.is_changed() {
+        let supported = device
+            .is_some_and(|device| device.features().contains(WgpuFeatures::POLYGON_MODE_LINE));
+        if wireframes.enabled && !supported {
+            warn!("wireframes need POLYGON_MODE_LINE, which the device doesn't support");
+            wireframes.enabled = false;
+        }
+
+        for entity in &chunk_meshes {
+            if wireframes.enabled {
+                commands.entity(entity).insert(Wireframe);
+            } else {
+                commands.entity(entity).remove::<Wireframe>();
+            }
+        }
+    } else if wireframes.enabled {
+        for entity in &added {
+            commands.entity(entity).insert(Wireframe);
+        }
+    }
+}
+
+/// Copies [`BackfaceCulling`] into the chunk materials.
+fn sync_backface_culling(
+    culling: Res<BackfaceCulling>,
+    voxel_assets: Res<VoxelAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut chunk_materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    if !culling.is_changed() {
+        return;
+    }
+
+    match &voxel_assets.material {
+        ChunkMaterialHandle::Array(handles) => {
+            for handle in [&handles.opaque, &handles.translucent] {
+                if let Some(material) = chunk_materials.get_mut(handle) {
+                    material.base.cull_mode = culling.cull_mode();
+                }
+            }
+        }
+        ChunkMaterialHandle::Standard(handles) => {
+            for handle in [&handles.opaque, &handles.translucent] {
+                if let Some(material) = materials.get_mut(handle) {
+                    material.cull_mode = culling.cull_mode();
+                }
+            }
+        }
+    }
+}
+
+/// Copies the AO strength into the chunk materials, which shade packed meshes with it.
+fn sync_ao_strength(
+    ao: Res<AoSettings>,
+    voxel_assets: Res<VoxelAssets>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    if !ao.is_changed() {
+        return;
+    }
+
+    if let ChunkMaterialHandle::Array(handles) = &voxel_assets.material {
+        for handle in [&handles.opaque, &handles.translucent] {
+            if let Some(material) = materials.get_mut(handle) {
+                material.extension.ao_strength = ao.strength();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunk::ChunkData,
+        material::ChunkMaterials,
+        region::{self, RegionFile},
+        save,
+        world::ChunkPos,
+    };
+    use bevy::{
+        asset::{AssetApp, AssetEvent, AssetId, AssetPlugin},
+        ecs::{event::Events, system::SystemState},
+        math::{Mat4, UVec3},
+        render::mesh::VertexAttributeValues,
+        MinimalPlugins,
+    };
+    use std::f32::consts::FRAC_PI_2;
+
+    /// Chunk meshing and rendering as [`MeshingPlugin`] runs it, without a renderer.
+    fn meshing_app() -> App {
+        let material = ChunkMaterialHandle::Standard(ChunkMaterials {
+            opaque: Handle::default(),
+            translucent: Handle::default(),
+        });
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Mesh>()
+            .init_resource::<VoxelRegistry>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<AtlasLayout>()
+            .insert_resource(chunk_meshers())
+            .init_resource::<AoSettings>()
+            .init_resource::<FaceShading>()
+            .init_resource::<SkirtSettings>()
+            .init_resource::<LodSettings>()
+            .init_resource::<WindingOrder>()
+            .init_resource::<ColliderSettings>()
+            .init_resource::<MeshingBudget>()
+            .init_resource::<MeshBufferPool>()
+            .init_resource::<MeshStats>()
+            .init_resource::<FrameTimings>()
+            .insert_resource(VoxelAssets { material })
+            .add_event::<ChunkModified>()
+            .observe(world::forget_despawned_chunk)
+            .add_systems(
+                Update,
+                (
+                    chunk::send_chunk_modified,
+                    world::remesh_modified_chunks,
+                    queue_chunk_meshing,
+                    render_chunks,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                remove_despawned_chunk_meshes.before(chunk::despawn_chunk_children),
+            )
+            .add_systems(PostUpdate, chunk::despawn_chunk_children);
+        app
+    }
+
+    /// Spawns a chunk at `pos` with stone wherever `filled` says.
+    fn spawn_chunk(app: &mut App, pos: IVec3, filled: impl Fn(UVec3) -> bool) -> Entity {
+        let registry = app.world().resource::<VoxelRegistry>();
+        let stone = Voxel::new(registry.id("stone").unwrap());
+        let mut chunk = Chunk::new(pos.as_vec3());
+        let dims = chunk.dims();
+        chunk.edit_batch(|editor| {
+            for z in 0..dims.z {
+                for y in 0..dims.y {
+                    for x in 0..dims.x {
+                        if filled(UVec3::new(x, y, z)) {
+                            editor.set(x as usize, y as usize, z as usize, stone);
+                        }
+                    }
+                }
+            }
+        });
+
+        let entity = app.world_mut().spawn(chunk).id();
+        let mut world = app.world_mut().resource_mut::<VoxelWorld>();
+        world.insert_chunk(ChunkPos(pos), entity);
+        entity
+    }
+
+    /// Whether any chunk is dirty or meshing.
+    fn meshing(app: &mut App) -> bool {
+        let world = app.world_mut();
+        let dirty = world.query::<&Chunk>().iter(world).any(Chunk::is_dirty);
+        dirty || world.query::<&MeshingTask>().iter(world).next().is_some()
+    }
+
+    /// Updates until every chunk is meshed, returning the mesh assets modified meanwhile.
+    fn settle(app: &mut App) -> Vec<AssetId<Mesh>> {
+        let mut reader = app
+            .world()
+            .resource::<Events<AssetEvent<Mesh>>>()
+            .get_reader_current();
+        let mut modified = Vec::new();
+        for _ in 0..1000 {
+            app.update();
+            let events = app.world().resource::<Events<AssetEvent<Mesh>>>();
+            modified.extend(reader.read(events).filter_map(|event| match event {
+                AssetEvent::Modified { id } => Some(*id),
+                _ => None,
+            }));
+            if !meshing(app) {
+                return modified;
+            }
+        }
+        panic!("chunks never finished meshing");
+    }
+
+    /// Every render entity drawing a chunk mesh.
+    fn chunk_meshes(app: &mut App) -> Vec<Entity> {
+        let world = app.world_mut();
+        let mut entities: Vec<_> = world
+            .query_filtered::<Entity, With<ChunkMesh>>()
+            .iter(world)
+            .collect();
+        entities.sort();
+        entities
+    }
+
+    fn dirty_chunks(app: &mut App) -> usize {
+        let world = app.world_mut();
+        world
+            .query::<&Chunk>()
+            .iter(world)
+            .filter(|c| c.is_dirty())
+            .count()
+    }
+
+    #[test]
+    fn meshing_budget_limits_chunks_started_per_frame() {
+        let mut app = meshing_app();
+        app.insert_resource(MeshingBudget {
+            max_chunks: 8,
+            max_time: Duration::from_secs(60),
+        });
+        // Spaced apart, so no chunk dirties another.
+        let chunks: Vec<_> = (0..100)
+            .map(|i| spawn_chunk(&mut app, IVec3::new(i * 2, 0, 0), |pos| pos == UVec3::ONE))
+            .collect();
+
+        app.update();
+        assert_eq!(dirty_chunks(&mut app), 92);
+        app.update();
+        assert_eq!(dirty_chunks(&mut app), 84);
+        assert_eq!(app.world().resource::<MeshStats>().remesh_backlog, 84);
+
+        // Urgent chunks, furthest from the camera, all start past the budget and use it up.
+        for &entity in &chunks[90..] {
+            let mut entity = app.world_mut().entity_mut(entity);
+            entity.get_mut::<Chunk>().unwrap().mark_urgent();
+        }
+        app.update();
+        assert_eq!(dirty_chunks(&mut app), 84 - 10);
+        let world = app.world_mut();
+        let mut query = world.query::<&Chunk>();
+        assert!(chunks[90..]
+            .iter()
+            .all(|e| !query.get(world, *e).unwrap().is_dirty()));
+
+        settle(&mut app);
+        assert_eq!(dirty_chunks(&mut app), 0);
+    }
+
+    #[test]
+    fn edits_remesh_only_the_chunks_they_touch() {
+        let mut app = meshing_app();
+        let chunks = [0, 1, 2].map(|x| spawn_chunk(&mut app, IVec3::X * x, |pos| pos.y == 0));
+        settle(&mut app);
+        let rendered = chunk_meshes(&mut app);
+        assert_eq!(rendered.len(), 3);
+        let mesh = |app: &App, chunk: Entity| {
+            let chunk = app.world().get::<Chunk>(chunk).unwrap();
+            chunk.mesh.as_ref().unwrap().id()
+        };
+        let stone = Voxel::new(1);
+
+        // Inside the first chunk, then on its border with the second.
+        for (x, touched) in [(5, vec![chunks[0]]), (15, vec![chunks[0], chunks[1]])] {
+            let mut chunk = app.world_mut().get_mut::<Chunk>(chunks[0]).unwrap();
+            chunk.set(x, 5, 5, stone);
+            let mut modified = settle(&mut app);
+            modified.sort();
+
+            let mut expected: Vec<_> = touched.iter().map(|chunk| mesh(&app, *chunk)).collect();
+            expected.sort();
+            assert_eq!(modified, expected, "edit at x {x}");
+            // Remeshed chunks keep their render entities, drawing the overwritten asset.
+            assert_eq!(chunk_meshes(&mut app), rendered);
+        }
+    }
+
+    #[test]
+    fn a_batch_of_edits_remeshes_its_chunk_once() {
+        let mut app = meshing_app();
+        let chunk = spawn_chunk(&mut app, IVec3::ZERO, |pos| pos.y == 0);
+        settle(&mut app);
+        let revision = |app: &App| app.world().get::<Chunk>(chunk).unwrap().mesh_revision();
+        let before = revision(&app);
+
+        let stone = Voxel::new(1);
+        let mut edited = app.world_mut().get_mut::<Chunk>(chunk).unwrap();
+        edited.edit_batch(|editor| {
+            (0..1000).for_each(|i| editor.set(i % 10, 1 + i / 100, i / 10 % 10, stone));
+        });
+        let modified = settle(&mut app);
+
+        assert_eq!(revision(&app), before + 1);
+        assert_eq!(modified.len(), 1);
+    }
+
+    #[test]
+    fn saved_chunks_load_in_place_of_generated_ones() {
+        let dir = std::env::temp_dir().join(format!("voxel-spawn-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let registry = VoxelRegistry::default();
+        let mut edited = ChunkData::cubic(Chunk::SIZE as u32);
+        edited.set(3, 4, 5, Voxel::new(registry.id("stone").unwrap()));
+        save::write_name_table(&dir, &registry.name_table()).unwrap();
+        let path = region::region_path(&dir, IVec3::ZERO);
+        let mut region = RegionFile::open(path, IVec3::ZERO).unwrap();
+        region.write_data(ChunkPos::new(0, 0, 0), &edited).unwrap();
+        drop(region);
+
+        let generated = generation::generate_area(2, 1, &registry);
+        for enabled in [true, false] {
+            let mut app = App::new();
+            app.insert_resource(WorldConfig {
+                size: UVec2::new(2, 1),
+                schematic: None,
+            })
+            .insert_resource(AutosaveConfig {
+                path: dir.clone(),
+                enabled,
+                ..Default::default()
+            })
+            .init_resource::<VoxelRegistry>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<FrameTimings>()
+            .add_systems(Startup, spawn_world);
+            app.update();
+
+            let world = app.world();
+            let voxels = |x| {
+                let entity = world
+                    .resource::<VoxelWorld>()
+                    .chunk_at(ChunkPos::new(x, 0, 0));
+                world
+                    .get::<Chunk>(entity.unwrap())
+                    .unwrap()
+                    .data()
+                    .to_dense()
+            };
+            let first = if enabled {
+                edited.clone()
+            } else {
+                generated[0].data().to_dense()
+            };
+            assert_eq!(voxels(0).voxels(), first.voxels(), "enabled {enabled}");
+            assert_eq!(voxels(1).voxels(), generated[1].data().to_dense().voxels());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Slow, so run on request: `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn a_thousand_remeshes_leak_no_assets_or_entities() {
+        let mut app = meshing_app();
+        let chunk = spawn_chunk(&mut app, IVec3::ZERO, |pos| pos.y <= pos.x % 4);
+        settle(&mut app);
+        let mesh = |app: &App| app.world().get::<Chunk>(chunk).unwrap().mesh.clone();
+        let (handle, rendered) = (mesh(&app), chunk_meshes(&mut app));
+        let assets = app.world().resource::<Assets<Mesh>>().len();
+
+        let stone = Voxel::new(1);
+        for i in 0..1000 {
+            let voxel = if i % 2 == 0 { stone } else { Voxel::AIR };
+            let mut edited = app.world_mut().get_mut::<Chunk>(chunk).unwrap();
+            // Placed, then removed again by the next remesh.
+            edited.set(i / 2 % 16, 10, i / 32 % 16, voxel);
+            assert_eq!(settle(&mut app).len(), 1, "remesh {i}");
+        }
+
+        assert_eq!(mesh(&app), handle);
+        assert_eq!(chunk_meshes(&mut app), rendered);
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), assets);
+    }
+
+    #[test]
+    fn despawned_chunks_leave_no_entities_or_meshes() {
+        let mut app = meshing_app();
+        let chunks = [0, 1].map(|x| spawn_chunk(&mut app, IVec3::X * x, |pos| pos.y <= 2));
+        settle(&mut app);
+        let rendered = chunk_meshes(&mut app);
+        assert_eq!(rendered.len(), 2);
+        let name = app
+            .world()
+            .get::<Name>(rendered[0])
+            .unwrap()
+            .as_str()
+            .to_owned();
+        assert!(
+            name.starts_with("chunk (") && name.ends_with("Opaque mesh"),
+            "{name}"
+        );
+
+        // Remeshing keeps the same entities and assets.
+        let mut edited = app.world_mut().get_mut::<Chunk>(chunks[0]).unwrap();
+        edited.set(4, 8, 4, Voxel::new(1));
+        settle(&mut app);
+        assert_eq!(chunk_meshes(&mut app), rendered);
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), 2);
+
+        for chunk in chunks {
+            app.world_mut().despawn(chunk);
+        }
+        app.update();
+
+        assert!(chunk_meshes(&mut app).is_empty());
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), 0);
+        assert_eq!(
+            app.world()
+                .resource::<VoxelWorld>()
+                .chunk_at(ChunkPos::new(0, 0, 0)),
+            None
+        );
+        let mut children = app.world_mut().query::<&ChunkChild>();
+        assert_eq!(children.iter(app.world()).count(), 0);
+    }
+
+    /// Each chunk mesh entity's pass and the mesh it draws, opaque first.
+    fn chunk_passes(app: &mut App) -> Vec<(MeshPass, Entity, AssetId<Mesh>)> {
+        let world = app.world_mut();
+        let mut passes: Vec<_> = world
+            .query_filtered::<(Entity, &MeshPass, &Handle<Mesh>), With<ChunkMesh>>()
+            .iter(world)
+            .map(|(entity, pass, mesh)| (*pass, entity, mesh.id()))
+            .collect();
+        passes.sort_by_key(|(pass, ..)| *pass != MeshPass::Opaque);
+        passes
+    }
+
+    #[test]
+    fn chunk_meshes_follow_a_chunk_from_empty_to_full_and_back() {
+        let mut app = meshing_app();
+        let chunk = spawn_chunk(&mut app, IVec3::ZERO, |_| false);
+        settle(&mut app);
+        assert!(chunk_meshes(&mut app).is_empty());
+
+        let registry = app.world().resource::<VoxelRegistry>();
+        let [stone, water] = ["stone", "water"].map(|name| Voxel::new(registry.id(name).unwrap()));
+        let fill = |app: &mut App, voxel, y: std::ops::Range<usize>| {
+            let mut chunk = app.world_mut().get_mut::<Chunk>(chunk).unwrap();
+            chunk.edit_batch(|editor| {
+                for y in y {
+                    for z in 0..Chunk::SIZE {
+                        (0..Chunk::SIZE).for_each(|x| editor.set(x, y, z, voxel));
+                    }
+                }
+            });
+            settle(app);
+            chunk_passes(app)
+        };
+        let has_mesh = |app: &App, id| app.world().resource::<Assets<Mesh>>().contains(id);
+
+        let full = fill(&mut app, stone, 0..8);
+        let [(MeshPass::Opaque, opaque, opaque_mesh)] = full[..] else {
+            panic!("{full:?}");
+        };
+        assert!(has_mesh(&app, opaque_mesh));
+
+        let flooded = fill(&mut app, water, 8..10);
+        let [(MeshPass::Opaque, ..), (MeshPass::Translucent, translucent, translucent_mesh)] =
+            flooded[..]
+        else {
+            panic!("{flooded:?}");
+        };
+        assert_eq!(flooded[0], full[0]);
+        assert_ne!(translucent, opaque);
+        assert!(has_mesh(&app, translucent_mesh));
+
+        // Draining the water takes its pass's entity and mesh, leaving the opaque pass drawn.
+        assert_eq!(fill(&mut app, Voxel::AIR, 8..10), full);
+        assert!(app.world().get_entity(translucent).is_none());
+        assert!(!has_mesh(&app, translucent_mesh));
+
+        assert!(fill(&mut app, Voxel::AIR, 0..8).is_empty());
+        assert_eq!(app.world().resource::<Assets<Mesh>>().len(), 0);
+    }
+
+    #[test]
+    fn visible_chunks_mesh_before_off_screen_ones() {
+        let mut app = meshing_app();
+        app.insert_resource(MeshingBudget {
+            max_chunks: 3,
+            max_time: Duration::from_secs(60),
+        });
+        // A camera in the middle of chunk 0, looking down +x.
+        let eye = Vec3::splat(Chunk::SIZE as f32 / 2.0) * Voxel::SIZE;
+        let transform = Transform::from_translation(eye).looking_to(Vec3::X, Vec3::Y);
+        let projection = Mat4::perspective_infinite_reverse_rh(FRAC_PI_2, 1.0, 0.1);
+        let clip_from_world = projection * transform.compute_matrix().inverse();
+        app.world_mut().spawn((
+            Camera3d::default(),
+            GlobalTransform::from(transform),
+            Frustum::from_clip_from_world(&clip_from_world),
+        ));
+        // Spaced apart, so no chunk dirties another.
+        let spawn = |app: &mut App, x: i32| spawn_chunk(app, IVec3::X * x, |pos| pos == UVec3::ONE);
+        let in_view = [2, 4, 6, 8, 10].map(|x| spawn(&mut app, x));
+        let behind = [-2, -4].map(|x| spawn(&mut app, x));
+
+        app.update();
+        let world = app.world_mut();
+        let mut query = world.query::<&Chunk>();
+        let mut dirty = |entity: &Entity| query.get(world, *entity).unwrap().is_dirty();
+        // The third chunk in view is further away than both chunks behind.
+        assert_eq!(
+            in_view.iter().map(&mut dirty).collect::<Vec<_>>(),
+            [false, false, false, true, true]
+        );
+        assert!(behind.iter().all(dirty));
+    }
+
+    /// Positions of `chunk`'s opaque mesh vertices whose normal is `normal`.
+    fn vertices_facing(app: &App, chunk: Entity, normal: Vec3) -> Vec<Vec3> {
+        let chunk = app.world().get::<Chunk>(chunk).unwrap();
+        let meshes = app.world().resource::<Assets<Mesh>>();
+        let mesh = meshes.get(chunk.mesh.as_ref().unwrap()).unwrap();
+        let (
+            Some(VertexAttributeValues::Float32x3(positions)),
+            Some(VertexAttributeValues::Float32x3(normals)),
+        ) = (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+        )
+        else {
+            panic!("chunk meshes have positions and normals");
+        };
+        positions
+            .iter()
+            .zip(normals)
+            .filter(|(_, n)| Vec3::from(**n) == normal)
+            .map(|(p, _)| Vec3::from(*p))
+            .collect()
+    }
+
+    #[test]
+    fn removing_a_seam_voxel_reveals_the_neighbor_face_behind_it() {
+        let mut app = meshing_app();
+        let [a, b] = [0, 1].map(|x| spawn_chunk(&mut app, IVec3::X * x, |_| true));
+        settle(&mut app);
+        assert!(vertices_facing(&app, b, Vec3::NEG_X).is_empty());
+
+        let mut state = SystemState::<(Res<VoxelWorld>, Query<&mut Chunk>)>::new(app.world_mut());
+        let (world, mut chunks) = state.get_mut(app.world_mut());
+        let seam = IVec3::new(Chunk::SIZE as i32 - 1, 8, 8);
+        assert!(world.set_voxel(&mut chunks, seam, Voxel::AIR));
+        let mut modified = settle(&mut app);
+
+        // Both chunks remesh into the assets they already had.
+        let mesh = |chunk| {
+            let chunk = app.world().get::<Chunk>(chunk).unwrap();
+            chunk.mesh.as_ref().unwrap().id()
+        };
+        let mut expected = vec![mesh(a), mesh(b)];
+        modified.sort();
+        expected.sort();
+        assert_eq!(modified, expected);
+
+        // B's first layer of voxels now shows one face toward A, on the plane between them.
+        let revealed = vertices_facing(&app, b, Vec3::NEG_X);
+        assert_eq!(revealed.len(), 4, "{revealed:?}");
+        assert!(revealed
+            .iter()
+            .all(|pos| pos.x == -0.5 && (pos.y - 8.0).abs() == 0.5 && (pos.z - 8.0).abs() == 0.5));
+    }
+}
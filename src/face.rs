@@ -0,0 +1,322 @@
+use bevy::math::{IVec3, Rect, Vec2, Vec3, Vec4};
+
+/// One of the six faces of a voxel.
+///
+/// Variants are declared in [`index`](Face::index) order, which face-indexed data such as
+/// [`ChunkNeighborhood::neighbors`](crate::mesh::ChunkNeighborhood::neighbors) and packed vertex
+/// directions use, and which the chunk shader mirrors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Face {
+    /// Top.
+    PosY,
+    /// Bottom.
+    NegY,
+    /// Right.
+    PosX,
+    /// Left.
+    NegX,
+    /// Back.
+    PosZ,
+    /// Forward.
+    NegZ,
+}
+
+impl Face {
+    pub const ALL: [Self; 6] = [
+        Self::PosY,
+        Self::NegY,
+        Self::PosX,
+        Self::NegX,
+        Self::PosZ,
+        Self::NegZ,
+    ];
+
+    /// The face whose [`offset`](Self::offset) is `offset`, if it is a unit axis.
+    #[inline]
+    pub fn from_offset(offset: IVec3) -> Option<Self> {
+        Self::ALL.into_iter().find(|face| face.offset() == offset)
+    }
+
+    #[inline]
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Axis the normal points along, `0` for x through `2` for z.
+    #[inline]
+    pub const fn axis(self) -> usize {
+        match self {
+            Self::PosX | Self::NegX => 0,
+            Self::PosY | Self::NegY => 1,
+            Self::PosZ | Self::NegZ => 2,
+        }
+    }
+
+    /// Whether the normal points along the positive axis.
+    #[inline]
+    pub const fn is_positive(self) -> bool {
+        matches!(self, Self::PosX | Self::PosY | Self::PosZ)
+    }
+
+    /// Outward unit normal.
+    #[inline]
+    pub fn normal(self) -> Vec3 {
+        self.offset().as_vec3()
+    }
+
+    /// Unit tangent along which the face's UV `x` grows, with the bitangent's handedness in `w`.
+    ///
+    /// The bitangent, `normal × tangent * w`, points up the texture, i.e. against UV `y`, as
+    /// tangent space normal maps expect. Faces are axis aligned, so this is fixed per direction.
+    pub fn tangent(self) -> Vec4 {
+        let [u, v] = self.uv_axes();
+        let mut tangent = Vec3::ZERO;
+        tangent[u] = if self.axis() == 0 { -1.0 } else { 1.0 };
+        let mut up = Vec3::ZERO;
+        up[v] = 1.0;
+
+        let handedness = self.normal().cross(tangent).dot(up).signum();
+        tangent.extend(handedness)
+    }
+
+    /// Like [`tangent`](Self::tangent), for UVs turned by `transform`.
+    pub fn transformed_tangent(self, transform: UvTransform) -> Vec4 {
+        let [right, up] = self.texture_axes(transform);
+        right.extend(self.normal().cross(right).dot(up).signum())
+    }
+
+    /// Unit directions the texture's right and up run along on the face, with its UVs turned by
+    /// `transform`.
+    pub fn texture_axes(self, transform: UvTransform) -> [Vec3; 2] {
+        let corners = self.corners(Vec3::ZERO, 1.0);
+        let uvs = self.transformed_uv_corners(transform);
+        // Solves for the derivatives of position by UV across the corners' two edges.
+        let (e1, e2) = (corners[1] - corners[0], corners[3] - corners[0]);
+        let (d1, d2) = (uvs[1] - uvs[0], uvs[3] - uvs[0]);
+        let det = d1.x * d2.y - d2.x * d1.y;
+        let right = (e1 * d2.y - e2 * d1.y) / det;
+        let down = (e2 * d1.x - e1 * d2.x) / det;
+        [right, -down]
+    }
+
+    /// The face pointing the other way.
+    #[inline]
+    pub fn opposite(self) -> Self {
+        Self::from_offset(-self.offset()).expect("negated offsets are faces")
+    }
+
+    /// Offset to the voxel across this face.
+    #[inline]
+    pub fn offset(self) -> IVec3 {
+        let mut offset = IVec3::ZERO;
+        offset[self.axis()] = if self.is_positive() { 1 } else { -1 };
+        offset
+    }
+
+    /// Position axes the `x` and `y` of the face's UVs run along.
+    #[inline]
+    pub const fn uv_axes(self) -> [usize; 2] {
+        match self {
+            Self::PosY | Self::NegY => [2, 0],
+            Self::PosX | Self::NegX => [2, 1],
+            Self::PosZ | Self::NegZ => [0, 1],
+        }
+    }
+
+    /// Corners of this face of the cube of edge `size` centered on `origin`. Consecutive corners
+    /// share an edge.
+    pub fn corners(self, origin: Vec3, size: f32) -> [Vec3; 4] {
+        const SQUARE: [Vec2; 4] = [
+            Vec2::new(-0.5, -0.5),
+            Vec2::new(0.5, -0.5),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(-0.5, 0.5),
+        ];
+        // Orders the square's axes so every face lists its corners in the same rotation.
+        let [s, t] = match self.axis() {
+            0 => [2, 1],
+            1 => [0, 2],
+            _ => [1, 0],
+        };
+
+        SQUARE.map(|corner| {
+            let mut vertex = self.normal() * 0.5;
+            vertex[s] = corner.x;
+            vertex[t] = corner.y;
+            origin + vertex * size
+        })
+    }
+
+    /// Which corner of a UV rect each of [`corners`](Self::corners) samples, `0` for min and `1`
+    /// for max. `y` runs against its UV axis so side textures stand upright, and `x` runs along
+    /// its UV axis except on x faces.
+    pub fn uv_corners(self) -> [Vec2; 4] {
+        let [u, v] = self.uv_axes();
+        let u_sign = if self.axis() == 0 { -1.0 } else { 1.0 };
+
+        self.corners(Vec3::ZERO, 1.0)
+            .map(|corner| Vec2::new(0.5 + u_sign * corner[u], 0.5 - corner[v]))
+    }
+
+    /// UV of `local`, a point on this face relative to the center of its voxel, with `rect`
+    /// mapped over the whole face like [`transformed_uvs`](Self::transformed_uvs) does. For
+    /// faces covering only part of the cell, e.g. of a slab.
+    pub fn uv_at(self, local: Vec3, rect: Rect, transform: UvTransform) -> Vec2 {
+        let [u, v] = self.uv_axes();
+        let u_sign = if self.axis() == 0 { -1.0 } else { 1.0 };
+        let corner = transform.apply(Vec2::new(0.5 + u_sign * local[u], 0.5 - local[v]));
+
+        rect.min + corner * rect.size()
+    }
+
+    /// [`uv_corners`](Self::uv_corners) turned by `transform`.
+    #[inline]
+    pub fn transformed_uv_corners(self, transform: UvTransform) -> [Vec2; 4] {
+        self.uv_corners().map(|corner| transform.apply(corner))
+    }
+
+    /// UVs of each of [`corners`](Self::corners), mapping `rect` over the face.
+    pub fn uvs(self, rect: Rect) -> [Vec2; 4] {
+        self.transformed_uvs(rect, UvTransform::IDENTITY)
+    }
+
+    /// Like [`uvs`](Self::uvs), with `rect` turned by `transform`.
+    pub fn transformed_uvs(self, rect: Rect, transform: UvTransform) -> [Vec2; 4] {
+        self.transformed_uv_corners(transform)
+            .map(|corner| Vec2::select(corner.cmpeq(Vec2::ZERO), rect.min, rect.max))
+    }
+
+    /// Triangles relative to the face's first corner, wound counter-clockwise seen from outside.
+    #[inline]
+    pub const fn indices(self) -> [u32; 6] {
+        if self.is_positive() {
+            [0, 3, 1, 1, 3, 2]
+        } else {
+            [0, 1, 3, 1, 2, 3]
+        }
+    }
+}
+
+/// How a face's texture is turned within its UV rect: mirrored left to right if `flip` is set,
+/// then turned by `rotation` quarter turns. Only moves the UVs, so a face's winding and normal are
+/// the same under every transform.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UvTransform {
+    /// Quarter turns, `0` to `3`.
+    pub rotation: u8,
+    pub flip: bool,
+}
+
+impl UvTransform {
+    pub const IDENTITY: Self = Self::new(0, false);
+
+    /// Every distinct transform, each rotation unflipped and then flipped.
+    pub const ALL: [Self; 8] = [
+        Self::new(0, false),
+        Self::new(1, false),
+        Self::new(2, false),
+        Self::new(3, false),
+        Self::new(0, true),
+        Self::new(1, true),
+        Self::new(2, true),
+        Self::new(3, true),
+    ];
+
+    #[inline]
+    pub const fn new(rotation: u8, flip: bool) -> Self {
+        Self {
+            rotation: rotation % 4,
+            flip,
+        }
+    }
+
+    /// Whether the transform swaps which face axis the texture's `x` and `y` run along.
+    #[inline]
+    pub const fn swaps_axes(self) -> bool {
+        self.rotation % 2 == 1
+    }
+
+    /// Moves a corner of the unit UV square, `0` for min and `1` for max on each axis.
+    pub fn apply(self, corner: Vec2) -> Vec2 {
+        let mut corner = corner;
+        if self.flip {
+            corner.x = 1.0 - corner.x;
+        }
+        for _ in 0..self.rotation {
+            corner = Vec2::new(1.0 - corner.y, corner.x);
+        }
+        corner
+    }
+
+    /// The transform applying `self` and then `next`.
+    pub const fn then(self, next: Self) -> Self {
+        // A mirror reverses the direction of the turns made before it.
+        let rotation = if next.flip {
+            next.rotation + 4 - self.rotation
+        } else {
+            next.rotation + self.rotation
+        };
+
+        Self::new(rotation, self.flip != next.flip)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Twice the signed area of the quad `uvs` traces, negative if it runs clockwise.
+    fn winding(uvs: [Vec2; 4]) -> f32 {
+        (0..4).map(|i| uvs[i].perp_dot(uvs[(i + 1) % 4])).sum()
+    }
+
+    #[test]
+    fn every_uv_transform_is_a_distinct_symmetry_of_the_square() {
+        for face in Face::ALL {
+            let identity = face.uv_corners();
+            let transformed =
+                UvTransform::ALL.map(|transform| face.transformed_uv_corners(transform));
+
+            for (transform, uvs) in UvTransform::ALL.into_iter().zip(transformed) {
+                let mut sorted = uvs.map(|uv| (uv.x as u8, uv.y as u8));
+                sorted.sort();
+                assert_eq!(
+                    sorted,
+                    [(0, 0), (0, 1), (1, 0), (1, 1)],
+                    "{face:?} {transform:?}"
+                );
+                // Mirroring reverses the direction the UVs run around the face; turning doesn't.
+                let reversed = winding(uvs) != winding(identity);
+                assert_eq!(reversed, transform.flip, "{face:?} {transform:?}");
+
+                let [right, up] = face.texture_axes(transform);
+                assert!((right.length() - 1.0).abs() < 1e-5 && (up.length() - 1.0).abs() < 1e-5);
+                assert_eq!(right.dot(up), 0.0);
+                assert_eq!(right.dot(face.normal()), 0.0);
+            }
+            for (i, uvs) in transformed.iter().enumerate() {
+                assert!(
+                    !transformed[..i].contains(uvs),
+                    "{face:?} repeats a transform"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn composed_transforms_apply_in_order() {
+        let corners = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        for first in UvTransform::ALL {
+            for next in UvTransform::ALL {
+                let composed = first.then(next);
+                for corner in corners {
+                    assert_eq!(
+                        composed.apply(corner),
+                        next.apply(first.apply(corner)),
+                        "{first:?} then {next:?}"
+                    );
+                }
+            }
+        }
+    }
+}
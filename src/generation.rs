@@ -0,0 +1,247 @@
+use crate::{
+    chunk::{Chunk, ChunkData},
+    registry::VoxelRegistry,
+    voxel::Voxel,
+    world::ChunkPos,
+};
+use bevy::{
+    log::info_span,
+    math::{vec3, IVec2, IVec3, Vec2},
+};
+use std::f32::consts::TAU;
+
+/// Columns whose surface is at or below this height are flooded with water.
+const SEA_LEVEL: i32 = 6;
+
+/// Center and radius, in world voxels, of a pond dug into the demo terrain above sea level.
+const POND_CENTER: IVec2 = IVec2::new(24, 24);
+const POND_RADIUS: f32 = 5.0;
+
+/// Seed the demo world is decorated with.
+pub const DEMO_SEED: u32 = 0x5eed;
+
+/// Side, in voxels, of the grid cells trees are scattered over: each cell holds at most one tree,
+/// at least [`TREE_MARGIN`] voxels in from its edges so canopies of neighboring cells never overlap.
+const TREE_CELL: i32 = 8;
+const TREE_MARGIN: i32 = 2;
+/// Chance out of 100 that a cell grows a tree.
+const TREE_CHANCE: u32 = 40;
+
+/// Generates a `width` × `depth` grid of demo chunks starting at chunk `(0, 0, 0)`.
+pub fn generate_area(width: u32, depth: u32, registry: &VoxelRegistry) -> Vec<Chunk> {
+    (0..width)
+        .flat_map(|x| (0..depth).map(move |z| vec3(x as f32, 0.0, z as f32)))
+        .map(|position| {
+            let mut data = ChunkData::cubic(Chunk::SIZE as u32);
+            generate_chunk(
+                &mut data,
+                position.as_ivec3() * Chunk::SIZE as i32,
+                registry,
+            );
+            let mut chunk = Chunk::from_data(position, data);
+            let pos = chunk.chunk_pos();
+            decorate(&mut chunk, pos, DEMO_SEED, registry);
+            chunk
+        })
+        .collect()
+}
+
+/// Fills chunk data whose `(0, 0, 0)` voxel sits at world voxel `origin` with demo terrain built
+/// from the default registry's blocks.
+///
+/// Voxel types missing from `registry` fall back to air.
+pub fn generate_chunk(data: &mut ChunkData, origin: IVec3, registry: &VoxelRegistry) {
+    let _span = info_span!("generate_chunk", origin = ?origin).entered();
+    let id = |name: &str| registry.id(name).unwrap_or(0);
+    let (stone, dirt, grass, sand, water, glowstone, iron_ore) = (
+        id("stone"),
+        id("dirt"),
+        id("grass"),
+        id("sand"),
+        id("water"),
+        id("glowstone"),
+        id("iron_ore"),
+    );
+
+    let pond_level = pond_level();
+    let dims = data.dims();
+    for z in 0..dims.z as usize {
+        for x in 0..dims.x as usize {
+            let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+            let (height, water_level) = column(world_x, world_z, pond_level);
+            let beach = height <= water_level + 1;
+
+            for y in 0..dims.y as usize {
+                let world_y = origin.y + y as i32;
+                let id = if world_y > height {
+                    if world_y > water_level {
+                        continue;
+                    }
+
+                    water
+                } else if world_y + 3 > height {
+                    match (beach, world_y == height) {
+                        (true, _) => sand,
+                        (false, true) => grass,
+                        (false, false) => dirt,
+                    }
+                } else {
+                    match hash(world_x, world_y, world_z) % 97 {
+                        0 => glowstone,
+                        1..=4 => iron_ore,
+                        _ => stone,
+                    }
+                };
+
+                data.set(x, y, z, Voxel::new(id));
+            }
+        }
+    }
+}
+
+/// Grows trees on the grass of a generated chunk at `chunk_pos`, returning how many were placed.
+///
+/// Trees are scattered over a grid of [`TREE_CELL`] cells in world space, so the same `seed`
+/// always places the same trees no matter which chunks are generated or in what order. A tree
+/// belongs to the chunk its trunk stands in; leaves reaching past the chunk are clipped at its
+/// border. Trees only replace air, and voxel types missing from `registry` skip decoration.
+pub fn decorate(
+    chunk: &mut Chunk,
+    chunk_pos: ChunkPos,
+    seed: u32,
+    registry: &VoxelRegistry,
+) -> usize {
+    let (Some(grass), Some(log), Some(leaves)) = (
+        registry.id("grass"),
+        registry.id("log"),
+        registry.id("leaves"),
+    ) else {
+        return 0;
+    };
+
+    let dims = chunk.dims().as_ivec3();
+    let origin = chunk_pos.0 * dims;
+    let cells =
+        |min: i32, size: i32| min.div_euclid(TREE_CELL)..=(min + size - 1).div_euclid(TREE_CELL);
+    let mut placed = 0;
+    for cell_z in cells(origin.z, dims.z) {
+        for cell_x in cells(origin.x, dims.x) {
+            let h = hash(cell_x, seed as i32, cell_z);
+            if h % 100 >= TREE_CHANCE {
+                continue;
+            }
+
+            let span = (TREE_CELL - 2 * TREE_MARGIN) as u32;
+            let x = cell_x * TREE_CELL + TREE_MARGIN + ((h >> 8) % span) as i32;
+            let z = cell_z * TREE_CELL + TREE_MARGIN + ((h >> 16) % span) as i32;
+            let (local_x, local_z) = (x - origin.x, z - origin.z);
+            if !(0..dims.x).contains(&local_x) || !(0..dims.z).contains(&local_z) {
+                continue;
+            }
+
+            let Some(ground) = chunk.surface_height(local_x as usize, local_z as usize) else {
+                continue;
+            };
+            let ground = ground as i32;
+            if chunk
+                .get(local_x as usize, ground as usize, local_z as usize)
+                .map(|voxel| voxel.id)
+                != Some(grass)
+            {
+                continue;
+            }
+
+            let trunk = 4 + (h >> 24) as i32 % 2;
+            let base = IVec3::new(local_x, ground, local_z);
+            grow_tree(chunk, base, trunk, Voxel::new(log), Voxel::new(leaves));
+            placed += 1;
+        }
+    }
+
+    placed
+}
+
+/// Places a trunk of `trunk` logs on the ground voxel at local `base`, under a rounded canopy of
+/// leaves, skipping voxels outside `chunk` or not air.
+fn grow_tree(chunk: &mut Chunk, base: IVec3, trunk: i32, log: Voxel, leaves: Voxel) {
+    let mut place = |position: IVec3, voxel: Voxel| {
+        if position.cmplt(IVec3::ZERO).any() {
+            return;
+        }
+        let (x, y, z) = (
+            position.x as usize,
+            position.y as usize,
+            position.z as usize,
+        );
+        if chunk.get(x, y, z).is_some_and(|voxel| voxel.id == 0) {
+            chunk.set(x, y, z, voxel);
+        }
+    };
+
+    let top = base + IVec3::Y * trunk;
+    (1..=trunk).for_each(|y| place(base + IVec3::Y * y, log));
+    for dy in -1..=1 {
+        let radius: i32 = if dy == 1 { 1 } else { 2 };
+        for dz in -radius..=radius {
+            for dx in -radius..=radius {
+                // Rounded corners, and no leaves where the trunk runs.
+                let corner = dx.abs() == radius && dz.abs() == radius && radius > 1;
+                if corner || (dx == 0 && dz == 0 && dy < 1) {
+                    continue;
+                }
+                place(top + IVec3::new(dx, dy, dz), leaves);
+            }
+        }
+    }
+    place(top + IVec3::Y * 2, leaves);
+}
+
+/// Height of the topmost solid voxel and the water level of the column at `(x, z)`, with the
+/// pond dug out.
+fn column(x: i32, z: i32, pond_level: i32) -> (i32, i32) {
+    let height = surface_height(x, z);
+    let distance = IVec2::new(x, z).as_vec2().distance(POND_CENTER.as_vec2());
+    if distance >= POND_RADIUS {
+        return (height, SEA_LEVEL);
+    }
+
+    // Up to three voxels deep in the middle, one at the edge.
+    let depth = (3.0 * (1.0 - distance / POND_RADIUS)).ceil() as i32;
+
+    (height.min(pond_level - depth), pond_level.max(SEA_LEVEL))
+}
+
+/// Water level of the pond: one below the lowest terrain just outside its rim, so the water is
+/// contained on every side.
+fn pond_level() -> i32 {
+    (0..32)
+        .map(|i| {
+            let rim = Vec2::from_angle(i as f32 / 32.0 * TAU) * (POND_RADIUS + 1.0);
+            let rim = (POND_CENTER.as_vec2() + rim).round().as_ivec2();
+            surface_height(rim.x, rim.y)
+        })
+        .min()
+        .unwrap_or(SEA_LEVEL)
+        - 1
+}
+
+/// Height of the topmost solid voxel of the column at `(x, z)` in world space, before the pond is
+/// dug out.
+fn surface_height(x: i32, z: i32) -> i32 {
+    let (x, z) = (x as f32, z as f32);
+    let height =
+        8.0 + 2.5 * (x * 0.21).sin() + 2.0 * (z * 0.17).cos() + 1.5 * ((x + z) * 0.09).sin();
+
+    height.max(1.0) as i32
+}
+
+/// Cheap deterministic integer hash used to scatter rare blocks.
+fn hash(x: i32, y: i32, z: i32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+
+    h ^ (h >> 15)
+}
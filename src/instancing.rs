@@ -0,0 +1,635 @@
+use crate::{
+    chunk::{Chunk, ChunkChild, VoxelStorage},
+    face::Face,
+    mesh::{self, ChunkNeighborhood, MeshBuffers, MeshPass},
+    registry::VoxelRegistry,
+    schedule::{self, VoxelSet},
+    voxel::Voxel,
+    world::VoxelWorld,
+};
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    asset::{AssetServer, Assets, Handle},
+    core_pipeline::core_3d::Transparent3d,
+    ecs::{
+        change_detection::{DetectChanges, Ref},
+        component::Component,
+        entity::Entity,
+        schedule::{common_conditions::resource_exists, IntoSystemConfigs},
+        system::{lifetimeless::SRes, Commands, Query, Res, ResMut, Resource, SystemParamItem},
+        world::{FromWorld, World},
+    },
+    math::{IVec3, Rect, Vec3},
+    pbr::{
+        MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+    },
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        mesh::{GpuBufferInfo, GpuMesh, Mesh, MeshVertexBufferLayoutRef},
+        prelude::SpatialBundle,
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
+            RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+        },
+        render_resource::{
+            binding_types::{sampler, texture_2d_array},
+            BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, Buffer,
+            BufferInitDescriptor, BufferUsages, PipelineCache, RenderPipelineDescriptor,
+            SamplerBindingType, Shader, ShaderStages, SpecializedMeshPipeline,
+            SpecializedMeshPipelineError, SpecializedMeshPipelines, TextureSampleType,
+            VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode,
+        },
+        renderer::{RenderDevice, RenderQueue},
+        texture::{GpuImage, Image},
+        view::{ExtractedView, Msaa, NoFrustumCulling},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+    },
+    utils::{HashMap, HashSet},
+};
+use bytemuck::{Pod, Zeroable};
+
+pub const INSTANCING_SHADER: &str = "shaders/voxel_instancing.wgsl";
+
+/// Draws chunks as instances of one cube, one per exposed voxel, instead of meshing them. Slower
+/// to draw than chunk meshes, but a voxel can change without rebuilding anything but its
+/// chunk's instance list, which suits editor-style workflows. Insert it before startup; the
+/// cubes sample [`VoxelInstanceTexture`], so it needs
+/// [`ChunkTextureMode::Array`](crate::material::ChunkTextureMode::Array).
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct InstancedVoxels;
+
+/// One instanced cube, read by the shader as per-instance vertex data.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct VoxelInstance {
+    /// World position of the voxel's center.
+    pub pos: [f32; 3],
+    /// Texture array layer drawn on every face.
+    pub tex_index: u32,
+}
+
+/// The cubes an entity draws, one per instance of its mesh.
+#[derive(Debug, Clone, Default, Component)]
+pub struct VoxelInstances(pub Vec<VoxelInstance>);
+
+/// The texture array instanced cubes sample, one layer per atlas tile.
+#[derive(Debug, Clone, Resource, ExtractResource)]
+pub struct VoxelInstanceTexture(pub Handle<Image>);
+
+/// The cube mesh every chunk's instances share.
+#[derive(Debug, Resource)]
+pub struct VoxelCube(pub Handle<Mesh>);
+
+pub struct VoxelInstancingPlugin;
+
+impl Plugin for VoxelInstancingPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.add_plugins(ExtractResourcePlugin::<VoxelInstanceTexture>::default())
+            .add_systems(
+                Startup,
+                setup_voxel_cube
+                    .in_set(VoxelSet::Mesh)
+                    .run_if(resource_exists::<InstancedVoxels>),
+            )
+            .add_systems(
+                Update,
+                instance_chunks
+                    .in_set(VoxelSet::Mesh)
+                    .run_if(resource_exists::<InstancedVoxels>),
+            );
+
+        app.sub_app_mut(RenderApp)
+            .add_render_command::<Transparent3d, DrawVoxelInstances>()
+            .init_resource::<SpecializedMeshPipelines<VoxelInstancePipeline>>()
+            .init_resource::<ExtractedInstances>()
+            .init_resource::<InstanceBuffers>()
+            .add_systems(ExtractSchedule, extract_voxel_instances)
+            .add_systems(
+                Render,
+                (
+                    queue_voxel_instances.in_set(RenderSet::QueueMeshes),
+                    prepare_instance_buffers.in_set(RenderSet::PrepareResources),
+                    prepare_texture_bind_group.in_set(RenderSet::PrepareBindGroups),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<VoxelInstancePipeline>();
+    }
+}
+
+/// Instances for every voxel of `chunks.center` with at least one visible face, positioned for
+/// a chunk whose first voxel sits at `origin`. Faces use the voxel type's side texture.
+pub fn chunk_instances<S: VoxelStorage>(
+    chunks: ChunkNeighborhood<S>,
+    registry: &VoxelRegistry,
+    origin: IVec3,
+) -> Vec<VoxelInstance> {
+    chunks
+        .center
+        .iter()
+        .filter_map(|(pos, voxel)| {
+            let voxel_type = registry.get(voxel.id)?;
+            let pos = pos.as_ivec3();
+            let exposed = Face::ALL.into_iter().any(|face| {
+                !mesh::is_face_hidden(registry, voxel, face, chunks.get(pos + face.offset()))
+            });
+
+            exposed.then(|| VoxelInstance {
+                pos: ((origin + pos).as_vec3() * Voxel::SIZE).to_array(),
+                tex_index: voxel_type.textures.side,
+            })
+        })
+        .collect()
+}
+
+fn setup_voxel_cube(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    let mut builder = MeshBuffers::default();
+    let uv_rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+    Face::ALL.into_iter().for_each(|face| {
+        mesh::push_scaled_face(&mut builder, Vec3::ZERO, face, uv_rect, Voxel::SIZE);
+    });
+
+    commands.insert_resource(VoxelCube(meshes.add(builder.build())));
+}
+
+/// Builds instance lists for dirty chunks, in place of meshing them. A chunk's list replaces the
+/// one its entity already draws, and chunks without instances get no entity, losing the one
+/// they had once they empty.
+pub fn instance_chunks(
+    mut commands: Commands,
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    cube: Res<VoxelCube>,
+    mut chunk_query: Query<(Entity, &mut Chunk)>,
+    mut instanced: Query<(Entity, &ChunkChild, &mut VoxelInstances)>,
+) {
+    let built: Vec<_> = chunk_query
+        .iter()
+        .filter(|(_, chunk)| chunk.is_dirty())
+        .map(|(entity, chunk)| {
+            let neighbors = chunk.chunk_pos().neighbors().map(|pos| {
+                let neighbor = world.chunk_at(pos)?;
+                chunk_query
+                    .get(neighbor)
+                    .ok()
+                    .map(|(_, chunk)| chunk.data())
+            });
+            // Instances only cull faces, which never look past a face neighbor.
+            let chunks = ChunkNeighborhood {
+                center: chunk.data(),
+                neighbors,
+                diagonals: [None; 20],
+            };
+
+            (entity, chunk_instances(chunks, &registry, chunk.origin()))
+        })
+        .collect();
+    if built.is_empty() {
+        return;
+    }
+
+    let mut existing: HashMap<_, _> = instanced
+        .iter()
+        .map(|(drawing, ChunkChild(chunk), _)| (*chunk, drawing))
+        .collect();
+    for (entity, instances) in built {
+        if let Ok((_, mut chunk)) = chunk_query.get_mut(entity) {
+            chunk.mark_clean();
+        }
+        let previous = existing.remove(&entity);
+        if instances.is_empty() {
+            if let Some(previous) = previous {
+                commands.entity(previous).despawn();
+            }
+            continue;
+        }
+        if let Some((_, _, mut drawn)) = previous.and_then(|e| instanced.get_mut(e).ok()) {
+            drawn.0 = instances;
+            continue;
+        }
+
+        // Instances are positioned in world space, so the entity keeps an identity transform,
+        // and the cube's bounds say nothing about where they are drawn.
+        commands.spawn((
+            SpatialBundle::INHERITED_IDENTITY,
+            cube.0.clone(),
+            VoxelInstances(instances),
+            NoFrustumCulling,
+            MeshPass::Opaque,
+            ChunkChild(entity),
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_voxel_instances(
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    instance_pipeline: Res<VoxelInstancePipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<VoxelInstancePipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<GpuMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    extracted: Res<ExtractedInstances>,
+    mut phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    views: Query<(Entity, &ExtractedView)>,
+) {
+    let draw_function = draw_functions.read().id::<DrawVoxelInstances>();
+    let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples());
+
+    for (view_entity, view) in &views {
+        let Some(phase) = phases.get_mut(&view_entity) else {
+            continue;
+        };
+        let view_key = msaa_key | MeshPipelineKey::from_hdr(view.hdr);
+        let rangefinder = view.rangefinder3d();
+
+        for &entity in &extracted.live {
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+            let key =
+                view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
+            let Ok(pipeline) =
+                pipelines.specialize(&pipeline_cache, &instance_pipeline, key, &mesh.layout)
+            else {
+                continue;
+            };
+
+            phase.add(Transparent3d {
+                entity,
+                pipeline,
+                draw_function,
+                distance: rangefinder.distance_translation(&mesh_instance.translation),
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+/// The entities drawing [`VoxelInstances`] this frame, and the lists that changed since the
+/// last extraction, waiting for [`prepare_instance_buffers`] to upload them.
+#[derive(Debug, Default, Resource)]
+struct ExtractedInstances {
+    live: HashSet<Entity>,
+    changed: Vec<(Entity, Vec<VoxelInstance>)>,
+}
+
+/// Copies out only the instance lists that changed; the rest keep their uploaded buffers.
+fn extract_voxel_instances(
+    mut extracted: ResMut<ExtractedInstances>,
+    instanced: Extract<Query<(Entity, Ref<VoxelInstances>)>>,
+) {
+    let ExtractedInstances { live, changed } = &mut *extracted;
+    live.clear();
+    for (entity, instances) in &instanced {
+        live.insert(entity);
+        if instances.is_changed() {
+            changed.push((entity, instances.0.clone()));
+        }
+    }
+}
+
+/// GPU copy of an entity's [`VoxelInstances`]. Generic over the buffer so tests can stand in
+/// for the GPU.
+struct InstanceBuffer<B = Buffer> {
+    buffer: B,
+    /// Bytes the buffer holds, which lists up to that size are written into.
+    capacity: u64,
+    length: usize,
+}
+
+/// Instance buffers kept across frames, by the entity drawing them.
+#[derive(Resource)]
+struct InstanceBuffers<B: Send + Sync + 'static = Buffer>(HashMap<Entity, InstanceBuffer<B>>);
+
+impl<B: Send + Sync + 'static> Default for InstanceBuffers<B> {
+    fn default() -> Self {
+        Self(HashMap::default())
+    }
+}
+
+impl<B: Send + Sync + 'static> InstanceBuffers<B> {
+    /// Drops the buffers of entities no longer drawing instances, then uploads the lists that
+    /// changed: written over a buffer they fit, into a new buffer from `create` otherwise, and
+    /// dropped along with their buffer once empty.
+    fn sync(
+        &mut self,
+        extracted: &mut ExtractedInstances,
+        mut create: impl FnMut(&[u8]) -> B,
+        mut write: impl FnMut(&mut B, &[u8]),
+    ) {
+        let ExtractedInstances { live, changed } = extracted;
+        self.0.retain(|entity, _| live.contains(entity));
+        for (entity, instances) in changed.drain(..) {
+            let contents: &[u8] = bytemuck::cast_slice(&instances);
+            let bytes = contents.len() as u64;
+            match self.0.get_mut(&entity) {
+                _ if instances.is_empty() => {
+                    self.0.remove(&entity);
+                }
+                Some(cached) if cached.capacity >= bytes => {
+                    write(&mut cached.buffer, contents);
+                    cached.length = instances.len();
+                }
+                _ => {
+                    let buffer = InstanceBuffer {
+                        buffer: create(contents),
+                        capacity: bytes,
+                        length: instances.len(),
+                    };
+                    self.0.insert(entity, buffer);
+                }
+            }
+        }
+    }
+}
+
+fn prepare_instance_buffers(
+    mut extracted: ResMut<ExtractedInstances>,
+    mut buffers: ResMut<InstanceBuffers>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    buffers.sync(
+        &mut extracted,
+        |contents| {
+            render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("voxel_instance_buffer"),
+                contents,
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            })
+        },
+        |buffer, contents| render_queue.write_buffer(buffer, 0, contents),
+    );
+}
+
+/// Binds [`VoxelInstanceTexture`] once it has been uploaded.
+#[derive(Resource)]
+struct VoxelTextureBindGroup(BindGroup);
+
+fn prepare_texture_bind_group(
+    mut commands: Commands,
+    pipeline: Res<VoxelInstancePipeline>,
+    texture: Option<Res<VoxelInstanceTexture>>,
+    bind_group: Option<Res<VoxelTextureBindGroup>>,
+    images: Res<RenderAssets<GpuImage>>,
+    render_device: Res<RenderDevice>,
+) {
+    if bind_group.is_some() {
+        return;
+    }
+    let Some(image) = texture.and_then(|texture| images.get(&texture.0)) else {
+        return;
+    };
+
+    let bind_group = render_device.create_bind_group(
+        "voxel_instance_texture",
+        &pipeline.texture_layout,
+        &BindGroupEntries::sequential((&image.texture_view, &image.sampler)),
+    );
+    commands.insert_resource(VoxelTextureBindGroup(bind_group));
+}
+
+/// The mesh pipeline, extended with the per-instance vertex buffer and the texture array.
+#[derive(Resource)]
+struct VoxelInstancePipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+    texture_layout: BindGroupLayout,
+}
+
+impl FromWorld for VoxelInstancePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let texture_layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "voxel_instance_texture_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d_array(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+
+        Self {
+            shader: world.resource::<AssetServer>().load(INSTANCING_SHADER),
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            texture_layout,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for VoxelInstancePipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: std::mem::size_of::<VoxelInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![
+                VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 8,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: VertexFormat::Float32x3.size(),
+                    shader_location: 9,
+                },
+            ],
+        });
+        descriptor.layout.push(self.texture_layout.clone());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = self.shader.clone();
+        }
+
+        Ok(descriptor)
+    }
+}
+
+type DrawVoxelInstances = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetVoxelTextureBindGroup<2>,
+    DrawMeshInstanced,
+);
+
+struct SetVoxelTextureBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetVoxelTextureBindGroup<I> {
+    type Param = Option<SRes<VoxelTextureBindGroup>>;
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        _entity: Option<()>,
+        bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bind_group) = bind_group else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, &bind_group.into_inner().0, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawMeshInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+    type Param = (
+        SRes<RenderAssets<GpuMesh>>,
+        SRes<RenderMeshInstances>,
+        SRes<InstanceBuffers>,
+    );
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        _entity: Option<()>,
+        (meshes, render_mesh_instances, buffers): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(item.entity())
+        else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(instance_buffer) = buffers.into_inner().0.get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+
+        let instances = 0..instance_buffer.length as u32;
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, instances);
+            }
+            GpuBufferInfo::NonIndexed => pass.draw(0..gpu_mesh.vertex_count, instances),
+        }
+
+        RenderCommandResult::Success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{
+        ecs::system::{IntoSystem, System},
+        render::MainWorld,
+    };
+
+    #[test]
+    fn instance_buffers_upload_only_what_changed_from_empty_to_full_and_back() {
+        let mut render = World::new();
+        render.init_resource::<MainWorld>();
+        render.init_resource::<ExtractedInstances>();
+        let mut extract = IntoSystem::into_system(extract_voxel_instances);
+        extract.initialize(&mut render);
+        // Buffers are byte vectors here, each tagged with the upload that made it.
+        let mut buffers = InstanceBuffers::<(Vec<u8>, usize)>::default();
+        let mut uploads = 0;
+        // One frame: edit the main world, extract it, and sync the buffers, returning each
+        // entity's buffer as (upload that created it, instances drawn, bytes held).
+        let mut frame = |edit: &mut dyn FnMut(&mut World)| {
+            let main = &mut **render.resource_mut::<MainWorld>();
+            main.increment_change_tick();
+            edit(main);
+            extract.run((), &mut render);
+            buffers.sync(
+                &mut render.resource_mut::<ExtractedInstances>(),
+                |contents| {
+                    uploads += 1;
+                    (contents.to_vec(), uploads)
+                },
+                |(buffer, _), contents| buffer[..contents.len()].copy_from_slice(contents),
+            );
+            let mut state: Vec<_> = buffers
+                .0
+                .iter()
+                .map(|(entity, cached)| {
+                    let (bytes, upload) = &cached.buffer;
+                    (*entity, *upload, cached.length, bytes.clone())
+                })
+                .collect();
+            state.sort_by_key(|(entity, ..)| *entity);
+            state
+        };
+        let instances = |tex_indices: &[u32]| -> Vec<_> {
+            tex_indices
+                .iter()
+                .map(|&tex_index| VoxelInstance {
+                    pos: [0.0; 3],
+                    tex_index,
+                })
+                .collect()
+        };
+        let bytes = |tex_indices: &[u32]| bytemuck::cast_slice(&instances(tex_indices)).to_vec();
+
+        assert!(frame(&mut |_| {}).is_empty());
+
+        let mut entity = Entity::PLACEHOLDER;
+        let spawned = frame(&mut |main| {
+            entity = main.spawn(VoxelInstances(instances(&[1, 2, 3, 4]))).id();
+        });
+        assert_eq!(spawned, [(entity, 1, 4, bytes(&[1, 2, 3, 4]))]);
+        // Unchanged lists upload nothing.
+        assert_eq!(frame(&mut |_| {}), spawned);
+
+        // Shorter lists are written over the front of the buffer they already have.
+        let shrunk = frame(&mut |main| {
+            main.get_mut::<VoxelInstances>(entity).unwrap().0 = instances(&[5]);
+        });
+        assert_eq!(shrunk, [(entity, 1, 1, bytes(&[5, 2, 3, 4]))]);
+        let grown = frame(&mut |main| {
+            main.get_mut::<VoxelInstances>(entity).unwrap().0 = instances(&[6; 5]);
+        });
+        assert_eq!(grown, [(entity, 2, 5, bytes(&[6; 5]))]);
+
+        let emptied = frame(&mut |main| {
+            main.get_mut::<VoxelInstances>(entity).unwrap().0.clear();
+        });
+        assert!(emptied.is_empty());
+        frame(&mut |main| {
+            main.get_mut::<VoxelInstances>(entity).unwrap().0 = instances(&[7]);
+        });
+        assert!(frame(&mut |main| {
+            main.despawn(entity);
+        })
+        .is_empty());
+    }
+}
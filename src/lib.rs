@@ -0,0 +1,39 @@
+//! A voxel engine on Bevy: chunked terrain meshed on background tasks, lit, edited and saved.
+//! Add [`VoxelEnginePlugins`] to an app to run it.
+
+pub mod atlas;
+pub mod chunk;
+pub mod codec;
+pub mod collision;
+#[cfg(feature = "debug_ui")]
+pub mod debug_ui;
+pub mod editing;
+mod engine;
+pub mod face;
+pub mod generation;
+pub mod instancing;
+pub mod light;
+pub mod material;
+pub mod mesh;
+#[cfg(feature = "physics")]
+pub mod physics;
+pub mod raycast;
+pub mod raymarch;
+pub mod region;
+pub mod registry;
+pub mod render;
+pub mod save;
+pub mod scene;
+pub mod schedule;
+pub mod schematic;
+pub mod stats;
+pub mod surface_nets;
+pub mod tick;
+pub mod voxel;
+pub mod world;
+
+pub use engine::{
+    CameraController, ChunkStreamingPlugin, DebugPlugin, EditingPlugin, FlyCameraPlugin,
+    LightingPlugin, MeshingPlugin, SpawnConfig, VoxelEnginePlugins, WorldConfig,
+};
+pub use schedule::VoxelSet;
@@ -0,0 +1,508 @@
+use crate::{
+    chunk::{Chunk, ChunkModified, VoxelStorage},
+    face::Face,
+    mesh::{ChunkNeighborhood, MeshBuffers, DIAGONAL_OFFSETS},
+    registry::{Transparency, VoxelRegistry},
+    stats::FrameTimings,
+    voxel::Voxel,
+    world::{ChunkPos, VoxelWorld},
+};
+use bevy::{
+    ecs::{
+        change_detection::DetectChanges,
+        component::Component,
+        entity::Entity,
+        event::EventReader,
+        query::Without,
+        system::{Commands, Query, Res, ResMut},
+    },
+    log::info_span,
+    math::{IVec3, UVec3, Vec3},
+    utils::{HashMap, HashSet},
+};
+use std::{collections::VecDeque, iter, sync::Mutex, time::Instant};
+
+/// Light level of a full strength light source. Light loses one level per voxel it travels.
+pub const MAX_LIGHT: u8 = 15;
+
+/// Sky light level below the surface of a column. Sky light doesn't propagate yet, so everything
+/// above the highest opaque voxel of a column gets [`MAX_LIGHT`] and everything under it this.
+pub const UNDERGROUND_SKY_LIGHT: u8 = 4;
+
+/// Brightness of a vertex in total darkness, so unlit caves stay faintly visible.
+pub const MIN_BRIGHTNESS: f32 = 0.1;
+
+/// Block light level of every voxel of a chunk, from `0` for darkness to [`MAX_LIGHT`], kept up to
+/// date by [`propagate_light`].
+#[derive(Debug, Clone, Component)]
+pub struct ChunkLight {
+    levels: Vec<u8>,
+    dims: UVec3,
+}
+
+impl ChunkLight {
+    /// Creates a dark light grid of `dims`.
+    pub fn new(dims: UVec3) -> Self {
+        Self {
+            levels: vec![0; (dims.x * dims.y * dims.z) as usize],
+            dims,
+        }
+    }
+
+    #[inline]
+    pub fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    /// The light level at a local coordinate, `0` if it is out of bounds.
+    #[inline]
+    pub fn get(&self, pos: UVec3) -> u8 {
+        self.index(pos).map_or(0, |index| self.levels[index])
+    }
+
+    #[inline]
+    fn set(&mut self, pos: UVec3, level: u8) {
+        if let Some(index) = self.index(pos) {
+            self.levels[index] = level;
+        }
+    }
+
+    #[inline]
+    fn index(&self, pos: UVec3) -> Option<usize> {
+        let dims = self.dims;
+        pos.cmplt(dims)
+            .all()
+            .then(|| ((pos.z * dims.y + pos.y) * dims.x + pos.x) as usize)
+    }
+}
+
+/// Packs a sky and a block light level into one byte, sky light in the high nibble.
+#[inline]
+pub fn pack_light(sky: u8, block: u8) -> u8 {
+    (sky.min(MAX_LIGHT) << 4) | block.min(MAX_LIGHT)
+}
+
+/// The sky and block levels of a [`pack_light`]ed byte.
+#[inline]
+pub const fn unpack_light(light: u8) -> (u8, u8) {
+    (light >> 4, light & 0xf)
+}
+
+/// Color multiplier for a [`pack_light`]ed byte, from [`MIN_BRIGHTNESS`] to `1` by the brighter of
+/// its two levels. The chunk shader repeats this for packed meshes.
+pub fn brightness(light: u8) -> f32 {
+    let (sky, block) = unpack_light(light);
+    let level = sky.max(block) as f32 / MAX_LIGHT as f32;
+    MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS) * level
+}
+
+/// Light level emitted by `voxel`: [`MAX_LIGHT`] for an
+/// [`emission`](crate::registry::VoxelType::emission) of `1` or more, proportionally less below.
+pub fn emitted_light(registry: &VoxelRegistry, voxel: Voxel) -> u8 {
+    registry.get(voxel.id).map_or(0, |voxel_type| {
+        (voxel_type.emission.clamp(0.0, 1.0) * MAX_LIGHT as f32).ceil() as u8
+    })
+}
+
+/// Whether light spreads into `voxel`. Everything but opaque voxels lets it through.
+fn passes_light(registry: &VoxelRegistry, voxel: Option<&Voxel>) -> bool {
+    voxel
+        .and_then(|voxel| registry.get(voxel.id))
+        .is_none_or(|voxel_type| voxel_type.transparency != Transparency::Opaque)
+}
+
+/// Gives every chunk without one a dark [`ChunkLight`], for [`propagate_light`] to fill.
+pub fn add_chunk_light(
+    mut commands: Commands,
+    chunks: Query<(Entity, &Chunk), Without<ChunkLight>>,
+) {
+    for (entity, chunk) in &chunks {
+        commands
+            .entity(entity)
+            .insert(ChunkLight::new(chunk.dims()));
+    }
+}
+
+/// Light of a chunk and the layer of voxels around it, for lighting its mesh's vertices.
+///
+/// Each voxel holds a [`pack_light`]ed byte of sky light, by the surface rule of
+/// [`UNDERGROUND_SKY_LIGHT`], and the [`ChunkLight`] block light of the chunk it is in.
+#[derive(Debug, Clone)]
+pub struct VertexLight {
+    levels: Vec<u8>,
+    /// Chunk dims plus the layer on each side.
+    dims: IVec3,
+}
+
+impl VertexLight {
+    /// Samples the light around `chunks.center` from the block light of the center and each face
+    /// neighbor, indexed like [`ChunkNeighborhood::neighbors`]. Missing chunks are dark, and the
+    /// edges of the layer, which no face looks into, only get sky light.
+    pub fn new<S: VoxelStorage>(
+        chunks: ChunkNeighborhood<S>,
+        registry: &VoxelRegistry,
+        center: Option<&ChunkLight>,
+        neighbors: [Option<&ChunkLight>; 6],
+    ) -> Self {
+        let chunk_dims = chunks.center.dims().as_ivec3();
+        let dims = chunk_dims + 2;
+        let block_light = |pos: IVec3| {
+            let outside = (pos.cmplt(IVec3::ZERO) | pos.cmpge(chunk_dims)).bitmask();
+            let light = match outside.count_ones() {
+                0 => center,
+                1 => {
+                    let offset = pos.div_euclid(chunk_dims);
+                    Face::ALL
+                        .into_iter()
+                        .find(|face| face.offset() == offset)
+                        .and_then(|face| neighbors[face.index()])
+                }
+                _ => None,
+            };
+            let local = pos.rem_euclid(chunk_dims).as_uvec3();
+            light.map_or(0, |light| light.get(local))
+        };
+
+        let mut levels = vec![0; (dims.x * dims.y * dims.z) as usize];
+        for z in -1..=chunk_dims.z {
+            for x in -1..=chunk_dims.x {
+                // The column's surface, looking as far up as the chunk above reaches.
+                let surface = (-1..chunk_dims.y * 2)
+                    .rev()
+                    .find(|y| !passes_light(registry, chunks.get(IVec3::new(x, *y, z))));
+                for y in -1..=chunk_dims.y {
+                    let pos = IVec3::new(x, y, z);
+                    let sky = if surface.is_some_and(|surface| y <= surface) {
+                        UNDERGROUND_SKY_LIGHT
+                    } else {
+                        MAX_LIGHT
+                    };
+                    let index = Self::index_in(dims, pos).unwrap_or_default();
+                    levels[index] = pack_light(sky, block_light(pos));
+                }
+            }
+        }
+
+        Self { levels, dims }
+    }
+
+    /// The [`pack_light`]ed light of the voxel at `pos` relative to the chunk, full sky light
+    /// past the sampled layer.
+    #[inline]
+    pub fn get(&self, pos: IVec3) -> u8 {
+        Self::index_in(self.dims, pos).map_or(pack_light(MAX_LIGHT, 0), |index| self.levels[index])
+    }
+
+    /// Fills [`MeshBuffers::light`] for the vertices of `builder`.
+    ///
+    /// Vertices come in quads of four, as every mesher emits them. Each corner takes the light of
+    /// the voxel its face is emitted into, the air side, at that corner of the quad, so light
+    /// changes along greedy-merged quads too.
+    pub fn shade(&self, builder: &mut MeshBuffers) {
+        builder.light.clear();
+        for (positions, normals) in builder
+            .positions
+            .chunks_exact(4)
+            .zip(builder.normals.chunks_exact(4))
+        {
+            let positions = positions.iter().map(|p| Vec3::from(*p));
+            let center = positions.clone().sum::<Vec3>() / 4.0;
+            for (position, normal) in positions.zip(normals.iter().map(|n| Vec3::from(*n))) {
+                let inward = center - position;
+                let inward = inward - normal * normal.dot(inward);
+                let step = Vec3::select(
+                    inward.abs().cmpgt(Vec3::splat(1e-3)),
+                    inward.signum() * 0.5,
+                    Vec3::ZERO,
+                );
+                let voxel = position + normal * 0.5 + step;
+                builder.light.push(self.get(voxel.round().as_ivec3()));
+            }
+        }
+    }
+
+    #[inline]
+    fn index_in(dims: IVec3, pos: IVec3) -> Option<usize> {
+        let padded = pos + IVec3::ONE;
+        (padded.cmpge(IVec3::ZERO).all() && padded.cmplt(dims).all())
+            .then(|| ((padded.z * dims.y + padded.y) * dims.x + padded.x) as usize)
+    }
+}
+
+/// Relights chunks a [`ChunkModified`] reports and chunks that just got their [`ChunkLight`],
+/// spreading light across chunk borders until it settles.
+///
+/// Light travels at most [`MAX_LIGHT`] - 1 voxels, less than a chunk across, so a change can only
+/// affect light in its own chunk and the chunks touching it. That region is relit from scratch
+/// in two steps:
+///
+/// 1. Every chunk of the region, in parallel, is reset and flooded from its own light sources
+///    and the still valid light shining in from chunks outside the region. Light reaching the
+///    chunk's border is queued for the chunk across it.
+/// 2. The queued light is spread serially, cell by cell and chunk to chunk, wherever it brightens
+///    a cell, until nothing is left to brighten.
+///
+/// Chunks whose light ended up different are marked dirty, so their vertex light is remeshed.
+/// The time taken is recorded in [`FrameTimings`].
+pub fn propagate_light(
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    mut events: EventReader<ChunkModified>,
+    mut timings: ResMut<FrameTimings>,
+    mut chunks: Query<(&mut Chunk, &mut ChunkLight)>,
+) {
+    let start = Instant::now();
+    let added: Vec<_> = chunks
+        .iter_mut()
+        .filter(|(_, light)| light.is_added())
+        .map(|(chunk, _)| chunk.chunk_pos())
+        .collect();
+    let stale: HashSet<_> = events
+        .read()
+        .map(|event| event.coord)
+        .chain(added)
+        .collect();
+    if stale.is_empty() {
+        return;
+    }
+
+    let faces = Face::ALL.map(Face::offset);
+    let region: HashSet<_> = stale
+        .iter()
+        .flat_map(|pos| {
+            iter::once(IVec3::ZERO)
+                .chain(faces)
+                .chain(DIAGONAL_OFFSETS)
+                .map(move |offset| *pos + offset)
+        })
+        .filter(|pos| world.chunk_at(*pos).is_some())
+        .collect();
+
+    let mut seeds: HashMap<ChunkPos, Vec<(UVec3, u8)>> = HashMap::default();
+    for pos in &region {
+        let Some((chunk, _)) = world.chunk_at(*pos).and_then(|e| chunks.get(e).ok()) else {
+            continue;
+        };
+        let dims = chunk.dims();
+        for face in Face::ALL {
+            let outside = pos.offset(face);
+            if region.contains(&outside) {
+                continue;
+            }
+            let Some((_, light)) = world.chunk_at(outside).and_then(|e| chunks.get(e).ok()) else {
+                continue;
+            };
+
+            let chunk_seeds = seeds.entry(*pos).or_default();
+            for cell in border_cells(dims, face) {
+                let across = (cell.as_ivec3() + face.offset()).rem_euclid(dims.as_ivec3());
+                let level = light.get(across.as_uvec3());
+                if level > 1 {
+                    chunk_seeds.push((cell, level - 1));
+                }
+            }
+        }
+    }
+
+    let before: HashMap<_, _> = chunks
+        .iter()
+        .filter(|(chunk, _)| region.contains(&chunk.chunk_pos()))
+        .map(|(chunk, light)| (chunk.chunk_pos(), light.levels.clone()))
+        .collect();
+
+    let spills = Mutex::new(Vec::new());
+    chunks.par_iter_mut().for_each(|(chunk, mut light)| {
+        let pos = chunk.chunk_pos();
+        if !region.contains(&pos) {
+            return;
+        }
+
+        let seeds = seeds.get(&pos).into_iter().flatten().copied();
+        let outgoing = relight_chunk(&registry, &chunk, &mut light, seeds);
+        spills.lock().unwrap().extend(outgoing);
+    });
+
+    let mut queue: VecDeque<_> = spills.into_inner().unwrap().into();
+    while let Some((position, level)) = queue.pop_front() {
+        let (pos, local) = world.split(position);
+        if !region.contains(&pos) {
+            continue;
+        }
+        let Some((chunk, mut light)) = world.chunk_at(pos).and_then(|e| chunks.get_mut(e).ok())
+        else {
+            continue;
+        };
+
+        let [x, y, z] = local.to_array().map(|c| c as usize);
+        if light.get(local) >= level || !passes_light(&registry, chunk.get(x, y, z)) {
+            continue;
+        }
+        light.set(local, level);
+        if level > 1 {
+            queue.extend(faces.map(|offset| (position + offset, level - 1)));
+        }
+    }
+
+    for (mut chunk, light) in &mut chunks {
+        if before
+            .get(&chunk.chunk_pos())
+            .is_some_and(|levels| *levels != light.levels)
+        {
+            chunk.mark_dirty();
+        }
+    }
+    timings.record("light", start.elapsed());
+}
+
+/// Resets `light` and floods it from the chunk's light sources and `seeds`, returning the world
+/// position and level of light leaving the chunk.
+fn relight_chunk(
+    registry: &VoxelRegistry,
+    chunk: &Chunk,
+    light: &mut ChunkLight,
+    seeds: impl Iterator<Item = (UVec3, u8)>,
+) -> Vec<(IVec3, u8)> {
+    let _span = info_span!("relight_chunk", chunk = ?chunk.chunk_pos().0).entered();
+    light.levels.fill(0);
+
+    let mut queue = VecDeque::new();
+    for (pos, voxel) in chunk.data().iter() {
+        let level = emitted_light(registry, voxel);
+        if level > 0 {
+            light.set(pos, level);
+            queue.push_back((pos, level));
+        }
+    }
+    for (pos, level) in seeds {
+        let [x, y, z] = pos.to_array().map(|c| c as usize);
+        if light.get(pos) < level && passes_light(registry, chunk.get(x, y, z)) {
+            light.set(pos, level);
+            queue.push_back((pos, level));
+        }
+    }
+
+    let dims = chunk.dims().as_ivec3();
+    let mut outgoing = Vec::new();
+    while let Some((pos, level)) = queue.pop_front() {
+        if level <= 1 {
+            continue;
+        }
+
+        for face in Face::ALL {
+            let next = pos.as_ivec3() + face.offset();
+            if next.cmplt(IVec3::ZERO).any() || next.cmpge(dims).any() {
+                outgoing.push((chunk.origin() + next, level - 1));
+                continue;
+            }
+
+            let next = next.as_uvec3();
+            let [x, y, z] = next.to_array().map(|c| c as usize);
+            if light.get(next) < level - 1 && passes_light(registry, chunk.get(x, y, z)) {
+                light.set(next, level - 1);
+                queue.push_back((next, level - 1));
+            }
+        }
+    }
+
+    outgoing
+}
+
+/// Local coordinates of the layer of a chunk of `dims` facing `face`.
+fn border_cells(dims: UVec3, face: Face) -> impl Iterator<Item = UVec3> {
+    let axis = face.axis();
+    let [u, v] = face.uv_axes();
+    let layer = if face.is_positive() {
+        dims[axis] - 1
+    } else {
+        0
+    };
+
+    (0..dims[u]).flat_map(move |i| {
+        (0..dims[v]).map(move |j| {
+            let mut pos = UVec3::ZERO;
+            pos[axis] = layer;
+            pos[u] = i;
+            pos[v] = j;
+            pos
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{
+        app::{App, Update},
+        ecs::schedule::IntoSystemConfigs,
+        MinimalPlugins,
+    };
+
+    #[test]
+    fn light_settles_across_chunk_borders_and_clears_without_its_source() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .init_resource::<VoxelRegistry>()
+            .init_resource::<VoxelWorld>()
+            .init_resource::<FrameTimings>()
+            .add_event::<ChunkModified>()
+            .add_systems(Update, (add_chunk_light, propagate_light).chain());
+        let size = Chunk::SIZE as i32;
+        let mut grid = Vec::new();
+        for z in 0..3 {
+            for x in 0..3 {
+                let pos = ChunkPos::new(x, 0, z);
+                let chunk = Chunk::new(pos.0.as_vec3());
+                let entity = app.world_mut().spawn(chunk).id();
+                app.world_mut()
+                    .resource_mut::<VoxelWorld>()
+                    .insert_chunk(pos, entity);
+                grid.push((pos, entity));
+            }
+        }
+        // In the middle chunk, a voxel from its -x border.
+        let (center, center_entity) = grid[4];
+        let source = IVec3::new(size + 1, 8, size + 8);
+        let glowstone = app.world().resource::<VoxelRegistry>().id("glowstone");
+        let mut chunk = app.world_mut().get_mut::<Chunk>(center_entity).unwrap();
+        chunk.set(1, 8, 8, Voxel::new(glowstone.unwrap()));
+        app.update();
+
+        // Every voxel's world position and light level.
+        let levels = |app: &App| -> Vec<_> {
+            let mut levels = Vec::new();
+            for (pos, entity) in &grid {
+                let light = app.world().get::<ChunkLight>(*entity).unwrap();
+                for z in 0..size {
+                    for y in 0..size {
+                        for x in 0..size {
+                            let local = IVec3::new(x, y, z);
+                            levels.push((pos.0 * size + local, light.get(local.as_uvec3())));
+                        }
+                    }
+                }
+            }
+            levels
+        };
+        let lit = levels(&app);
+        for &(position, level) in &lit {
+            let distance = (position - source).abs().element_sum();
+            let expected = (MAX_LIGHT as i32 - distance).max(0) as u8;
+            assert_eq!(level, expected, "at {position}");
+        }
+        // Across the seam, one voxel further into the neighbor, and just out of the light's range.
+        let at = |position| lit.iter().find(|(p, _)| *p == position).unwrap().1;
+        assert_eq!(at(IVec3::new(size - 1, 8, size + 8)), MAX_LIGHT - 2);
+        assert_eq!(at(IVec3::new(size - 2, 8, size + 8)), MAX_LIGHT - 3);
+        assert_eq!(at(IVec3::new(size - 14, 8, size + 8)), 0);
+
+        let mut chunk = app.world_mut().get_mut::<Chunk>(center_entity).unwrap();
+        chunk.set(1, 8, 8, Voxel::AIR);
+        app.world_mut().send_event(ChunkModified {
+            chunk: center_entity,
+            coord: center,
+            region: None,
+        });
+        app.update();
+        assert!(levels(&app).iter().all(|(_, level)| *level == 0));
+    }
+}
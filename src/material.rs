@@ -0,0 +1,219 @@
+use crate::{
+    atlas::AtlasLayout,
+    mesh::{FaceShading, MeshPass, ATTRIBUTE_LIGHT, ATTRIBUTE_PACKED},
+};
+use bevy::{
+    asset::{Asset, Assets, Handle},
+    ecs::system::{Commands, Res, ResMut, Resource},
+    math::{Vec3, Vec4},
+    pbr::{
+        ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline,
+        StandardMaterial,
+    },
+    reflect::Reflect,
+    render::{
+        mesh::{Mesh, MeshVertexBufferLayoutRef},
+        render_resource::{
+            AsBindGroup, Face, RenderPipelineDescriptor, ShaderDefVal, ShaderRef,
+            SpecializedMeshPipelineError,
+        },
+        texture::Image,
+    },
+};
+use std::fmt;
+
+pub const CHUNK_SHADER: &str = "shaders/chunk.wgsl";
+
+/// Chunk material sampling a texture array, one layer per atlas tile. Unlike the atlas, UVs can
+/// wrap inside a layer, so greedy-merged quads tile correctly and mips never mix block types.
+pub type ChunkMaterial = ExtendedMaterial<StandardMaterial, ChunkArrayTexture>;
+
+#[derive(Debug, Clone, Asset, Reflect, AsBindGroup)]
+pub struct ChunkArrayTexture {
+    #[texture(100, dimension = "2d_array")]
+    #[sampler(101)]
+    pub array_texture: Handle<Image>,
+    /// Ambient occlusion strength applied to packed meshes, whose vertices carry AO levels
+    /// rather than baked shades.
+    #[uniform(102)]
+    pub ao_strength: f32,
+    /// [`FaceShading`] of packed meshes, whose vertices carry no colors to bake it into, see
+    /// [`face_shades`].
+    #[uniform(103)]
+    pub face_shades: [Vec4; 2],
+}
+
+/// [`FaceShading`] per axis as uniform data: the shades of faces pointing along each axis,
+/// then of those pointing against it. `w` is unused.
+pub fn face_shades(shading: &FaceShading) -> [Vec4; 2] {
+    [1.0, -1.0].map(|sign| {
+        Vec3::new(
+            shading.shade(Vec3::X * sign),
+            shading.shade(Vec3::Y * sign),
+            shading.shade(Vec3::Z * sign),
+        )
+        .extend(0.0)
+    })
+}
+
+impl MaterialExtension for ChunkArrayTexture {
+    fn vertex_shader() -> ShaderRef {
+        CHUNK_SHADER.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        CHUNK_SHADER.into()
+    }
+
+    /// Reads [`ATTRIBUTE_PACKED`] in place of the separate attributes for packed meshes, and
+    /// [`ATTRIBUTE_LIGHT`] at location 6 if they are lit. The prepass keeps its default layout,
+    /// which only needs positions.
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        let prepass = descriptor
+            .vertex
+            .shader_defs
+            .contains(&ShaderDefVal::from("PREPASS_PIPELINE"));
+        if prepass || !layout.0.contains(ATTRIBUTE_PACKED) {
+            return Ok(());
+        }
+
+        let mut attributes = vec![
+            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+            ATTRIBUTE_PACKED.at_shader_location(1),
+        ];
+        let lit = layout.0.contains(ATTRIBUTE_LIGHT);
+        if lit {
+            attributes.push(ATTRIBUTE_LIGHT.at_shader_location(6));
+        }
+        descriptor.vertex.buffers = vec![layout.0.get_layout(&attributes)?];
+
+        // The packed vertex still produces UVs, tangents and colors for the fragment shader.
+        let defs = [
+            "PACKED_VERTICES",
+            "VERTEX_UVS",
+            "VERTEX_UVS_A",
+            "VERTEX_UVS_B",
+            "VERTEX_TANGENTS",
+            "VERTEX_COLORS",
+        ];
+        if lit {
+            descriptor.vertex.shader_defs.push("VERTEX_LIGHT".into());
+        }
+        for def in defs {
+            descriptor.vertex.shader_defs.push(def.into());
+            if let Some(fragment) = descriptor.fragment.as_mut() {
+                fragment.shader_defs.push(def.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether chunk materials cull the back faces of triangles. Turning it off shows geometry from
+/// both sides, so holes and inside-out faces stand out while debugging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct BackfaceCulling {
+    pub enabled: bool,
+}
+
+impl Default for BackfaceCulling {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+impl BackfaceCulling {
+    /// The `cull_mode` chunk materials are given.
+    #[inline]
+    pub fn cull_mode(&self) -> Option<Face> {
+        self.enabled.then_some(Face::Back)
+    }
+}
+
+/// Whether chunk meshes are drawn as wireframes by Bevy's `WireframePlugin`. Only takes effect
+/// if the adapter can draw lines, which
+/// [`RenderConfig::wgpu_settings`](crate::render::RenderConfig::wgpu_settings) requests.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub struct ChunkWireframes {
+    pub enabled: bool,
+}
+
+/// Which material chunk meshes are drawn with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum ChunkTextureMode {
+    /// [`ChunkMaterial`] over the atlas reinterpreted as a texture array, drawing
+    /// [`PackedVertex`](crate::mesh::PackedVertex) meshes without normals.
+    #[default]
+    Array,
+    /// A [`StandardMaterial`] sampling the atlas directly, so meshes carry normals, UVs and colors
+    /// as separate attributes. Greedy meshes don't tile under it.
+    Atlas,
+}
+
+/// One material per [`MeshPass`], differing only in alpha mode.
+#[derive(Clone)]
+pub struct ChunkMaterials<M: Asset> {
+    pub opaque: Handle<M>,
+    pub translucent: Handle<M>,
+}
+
+// Derived `Debug` would require it of the material too, which extended materials lack.
+impl<M: Asset> fmt::Debug for ChunkMaterials<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ChunkMaterials")
+            .field("opaque", &self.opaque)
+            .field("translucent", &self.translucent)
+            .finish()
+    }
+}
+
+impl<M: Asset> ChunkMaterials<M> {
+    #[inline]
+    pub fn get(&self, pass: MeshPass) -> &Handle<M> {
+        match pass {
+            MeshPass::Opaque => &self.opaque,
+            MeshPass::Translucent => &self.translucent,
+        }
+    }
+}
+
+/// Handles to the materials shared by every chunk mesh.
+#[derive(Debug, Clone)]
+pub enum ChunkMaterialHandle {
+    Standard(ChunkMaterials<StandardMaterial>),
+    Array(ChunkMaterials<ChunkMaterial>),
+}
+
+/// The atlas waiting to load, and the handle reserved for its texture array copy.
+#[derive(Debug, Resource)]
+pub struct PendingArrayTexture {
+    pub atlas: Handle<Image>,
+    pub array: Handle<Image>,
+}
+
+/// Once the atlas is loaded, copies it into the reserved texture array handle with one layer per
+/// tile. The atlas must be a single column of tiles.
+pub fn build_array_texture(
+    mut commands: Commands,
+    pending: Option<Res<PendingArrayTexture>>,
+    layout: Res<AtlasLayout>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(pending) = pending else {
+        return;
+    };
+    let Some(atlas) = images.get(&pending.atlas) else {
+        return;
+    };
+
+    let mut array = atlas.clone();
+    array.reinterpret_stacked_2d_as_array(layout.len());
+    images.insert(&pending.array, array);
+    commands.remove_resource::<PendingArrayTexture>();
+}
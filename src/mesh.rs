@@ -1,123 +1,3166 @@
-use bevy::render::{
-    mesh::{Indices, Mesh, PrimitiveTopology},
-    render_asset::RenderAssetUsages,
+use crate::{
+    atlas::AtlasLayout,
+    chunk::{ChunkStorage, SparseChunk, VoxelStorage},
+    face::{Face, UvTransform},
+    light::{self, VertexLight},
+    registry::{FaceTextures, Transparency, VoxelRegistry, VoxelShape, VoxelType},
+    voxel::{Orientation, Voxel, VoxelId},
 };
+use bevy::{
+    ecs::{component::Component, system::Resource},
+    math::{IVec3, Rect, UVec2, UVec3, Vec2, Vec3},
+    render::{
+        mesh::{Indices, Mesh, MeshVertexAttribute, PrimitiveTopology, VertexAttributeValues},
+        primitives::Aabb,
+        render_asset::RenderAssetUsages,
+        render_resource::VertexFormat,
+    },
+};
+use std::{array, error::Error, fmt, ops::Range, sync::Arc, time::Duration};
 
-pub fn generate_cube() -> Mesh {
-    let vertices = vec![
-        // top (+y)
-        [-0.5, 0.5, -0.5],
-        [0.5, 0.5, -0.5],
-        [0.5, 0.5, 0.5],
-        [-0.5, 0.5, 0.5],
-        // bottom   (-y)
-        [-0.5, -0.5, -0.5],
-        [0.5, -0.5, -0.5],
-        [0.5, -0.5, 0.5],
-        [-0.5, -0.5, 0.5],
-        // right    (+x)
-        [0.5, -0.5, -0.5],
-        [0.5, -0.5, 0.5],
-        [0.5, 0.5, 0.5],
-        [0.5, 0.5, -0.5],
-        // left     (-x)
-        [-0.5, -0.5, -0.5],
-        [-0.5, -0.5, 0.5],
-        [-0.5, 0.5, 0.5],
-        [-0.5, 0.5, -0.5],
-        // back     (+z)
-        [-0.5, -0.5, 0.5],
-        [-0.5, 0.5, 0.5],
-        [0.5, 0.5, 0.5],
-        [0.5, -0.5, 0.5],
-        // forward  (-z)
-        [-0.5, -0.5, -0.5],
-        [-0.5, 0.5, -0.5],
-        [0.5, 0.5, -0.5],
-        [0.5, -0.5, -0.5],
-    ];
+/// Scale applied to overlay cubes so they sit just outside the voxel they decorate.
+pub const OVERLAY_INFLATE: f32 = 1.002;
+
+/// Scale applied to the [`selection_box_mesh`], enough to keep its edges clear of both the chunk
+/// mesh and the crack overlay out to [`REACH`](crate::editing::REACH).
+pub const SELECTION_INFLATE: f32 = 1.01;
+
+/// Height of a liquid's surface, as a fraction of a voxel, where nothing but air or other
+/// non-opaque voxels are above it.
+pub const LIQUID_SURFACE_HEIGHT: f32 = 14.0 / 16.0;
+
+/// Offsets of the chunks sharing only an edge (the first twelve) or a corner with a chunk.
+pub const DIAGONAL_OFFSETS: [IVec3; 20] = [
+    IVec3::new(-1, -1, 0),
+    IVec3::new(1, -1, 0),
+    IVec3::new(-1, 1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(-1, 0, -1),
+    IVec3::new(1, 0, -1),
+    IVec3::new(-1, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(0, -1, -1),
+    IVec3::new(0, 1, -1),
+    IVec3::new(0, -1, 1),
+    IVec3::new(0, 1, 1),
+    IVec3::new(-1, -1, -1),
+    IVec3::new(1, -1, -1),
+    IVec3::new(-1, 1, -1),
+    IVec3::new(1, 1, -1),
+    IVec3::new(-1, -1, 1),
+    IVec3::new(1, -1, 1),
+    IVec3::new(-1, 1, 1),
+    IVec3::new(1, 1, 1),
+];
+
+/// A chunk's voxels together with its six face-adjacent neighbors, indexed by
+/// [`Face::index`], so faces on the chunk border can be culled against the next chunk over.
+#[derive(Debug)]
+pub struct ChunkNeighborhood<'a, S: VoxelStorage = ChunkStorage> {
+    pub center: &'a S,
+    /// Neighbors that aren't loaded are `None`.
+    pub neighbors: [Option<&'a S>; 6],
+    /// Edge and corner neighbors, indexed like [`DIAGONAL_OFFSETS`], for samples that reach
+    /// diagonally past the center, e.g. ambient occlusion or smooth surfaces. `None` where
+    /// they aren't loaded or aren't needed.
+    pub diagonals: [Option<&'a S>; 20],
+}
+
+// Derived impls would require `S: Copy`, but only references are copied.
+impl<S: VoxelStorage> Clone for ChunkNeighborhood<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S: VoxelStorage> Copy for ChunkNeighborhood<'_, S> {}
+
+impl<'a, S: VoxelStorage> ChunkNeighborhood<'a, S> {
+    /// Reads the voxel at `pos` relative to the center chunk. Positions outside it are looked up
+    /// in the neighbor across that face, or in [`diagonals`](Self::diagonals) if outside on more
+    /// than one axis.
+    pub fn get(&self, pos: IVec3) -> Option<&'a Voxel> {
+        let dims = self.center.dims().as_ivec3();
+        let outside_axes = (pos.cmplt(IVec3::ZERO) | pos.cmpge(dims)).bitmask();
+        if outside_axes.count_ones() > 1 {
+            let offset = pos.div_euclid(dims).clamp(IVec3::NEG_ONE, IVec3::ONE);
+            let index = DIAGONAL_OFFSETS.iter().position(|o| *o == offset)?;
+            let data = self.diagonals[index]?;
+            let pos = pos.rem_euclid(data.dims().as_ivec3());
+            return data.get(pos.x as usize, pos.y as usize, pos.z as usize);
+        }
+
+        let outside = if pos.y >= dims.y {
+            Some(Face::PosY)
+        } else if pos.y < 0 {
+            Some(Face::NegY)
+        } else if pos.x >= dims.x {
+            Some(Face::PosX)
+        } else if pos.x < 0 {
+            Some(Face::NegX)
+        } else if pos.z >= dims.z {
+            Some(Face::PosZ)
+        } else if pos.z < 0 {
+            Some(Face::NegZ)
+        } else {
+            None
+        };
+
+        let (data, pos) = match outside {
+            None => (self.center, pos),
+            Some(face) => {
+                let data = self.neighbors[face.index()]?;
+                (data, pos.rem_euclid(data.dims().as_ivec3()))
+            }
+        };
+
+        data.get(pos.x as usize, pos.y as usize, pos.z as usize)
+    }
+}
+
+/// An owned copy of the voxels meshing a chunk reads, so it can be meshed off the main thread
+/// while the chunk itself keeps changing.
+#[derive(Debug, Clone)]
+pub struct ChunkSnapshot {
+    pub center: ChunkStorage,
+    /// The layer of each neighbor touching the center, indexed like
+    /// [`ChunkNeighborhood::neighbors`]. Meshing never looks further into a neighbor.
+    pub borders: [Option<ChunkStorage>; 6],
+    /// The row or single voxel of each edge and corner neighbor touching the center, indexed
+    /// like [`ChunkNeighborhood::diagonals`].
+    pub diagonals: [Option<ChunkStorage>; 20],
+}
+
+impl ChunkSnapshot {
+    pub fn new(chunks: ChunkNeighborhood) -> Self {
+        let borders = Face::ALL.map(|face| {
+            chunks.neighbors[face.index()]
+                .map(|neighbor| border_region(neighbor, face.offset()).into())
+        });
+        let diagonals = array::from_fn(|i| {
+            chunks.diagonals[i].map(|neighbor| border_region(neighbor, DIAGONAL_OFFSETS[i]).into())
+        });
+
+        Self {
+            center: chunks.center.clone(),
+            borders,
+            diagonals,
+        }
+    }
+
+    /// The snapshot reduced by `factor` for level of detail meshing, see [`downsample`].
+    pub fn downsample(&self, factor: u32) -> Self {
+        let borders = Face::ALL.map(|face| {
+            self.borders[face.index()]
+                .as_ref()
+                .map(|border| downsample_border(border, face.offset(), factor).into())
+        });
+        let diagonals = array::from_fn(|i| {
+            self.diagonals[i]
+                .as_ref()
+                .map(|border| downsample_border(border, DIAGONAL_OFFSETS[i], factor).into())
+        });
+
+        Self {
+            center: downsample(&self.center, factor).into(),
+            borders,
+            diagonals,
+        }
+    }
+
+    #[inline]
+    pub fn neighborhood(&self) -> ChunkNeighborhood<'_> {
+        ChunkNeighborhood {
+            center: &self.center,
+            neighbors: self.borders.each_ref().map(Option::as_ref),
+            diagonals: self.diagonals.each_ref().map(Option::as_ref),
+        }
+    }
+}
+
+/// Reduces `data` by `factor` along each axis for level of detail meshing. Each cell of the
+/// result covers `factor`³ voxels and is solid if at least half of them are, taking the most
+/// common voxel among them.
+pub fn downsample<S: VoxelStorage>(data: &S, factor: u32) -> SparseChunk {
+    let dims = (data.dims() + factor - 1) / factor;
+    let mut reduced = SparseChunk::new(dims);
+    for z in 0..dims.z {
+        for y in 0..dims.y {
+            for x in 0..dims.x {
+                let min = UVec3::new(x, y, z) * factor;
+                if let Some(voxel) = dominant_voxel(data, min, UVec3::splat(factor)) {
+                    reduced.set(x as usize, y as usize, z as usize, voxel);
+                }
+            }
+        }
+    }
+
+    reduced
+}
+
+/// Like [`downsample`], for the [`border_region`] of the neighbor at `offset`: the region is
+/// reduced along the axes it spans and kept on the side of the result touching the center chunk.
+fn downsample_border(border: &ChunkStorage, offset: IVec3, factor: u32) -> SparseChunk {
+    let full = border.dims();
+    let dims = (full + factor - 1) / factor;
+    let range = |axis: usize| touching_range(offset, dims, axis);
+
+    let mut reduced = SparseChunk::new(dims);
+    for z in range(2) {
+        for y in range(1) {
+            for x in range(0) {
+                let (mut min, mut size) = (UVec3::new(x, y, z) * factor, UVec3::splat(factor));
+                for axis in (0..3).filter(|axis| offset[*axis] != 0) {
+                    min[axis] = touching_range(offset, full, axis).start;
+                    size[axis] = 1;
+                }
+                if let Some(voxel) = dominant_voxel(border, min, size) {
+                    reduced.set(x as usize, y as usize, z as usize, voxel);
+                }
+            }
+        }
+    }
+
+    reduced
+}
+
+/// The most common non-air voxel of the box of `size` at `min`, if at least half the box is
+/// solid. The part of the box outside `data` doesn't count.
+fn dominant_voxel<S: VoxelStorage>(data: &S, min: UVec3, size: UVec3) -> Option<Voxel> {
+    let max = (min + size).min(data.dims());
+    let mut counts: Vec<(VoxelId, u32)> = Vec::new();
+    for z in min.z..max.z {
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let Some(voxel) = data.get(x as usize, y as usize, z as usize) else {
+                    continue;
+                };
+                if voxel.id == 0 {
+                    continue;
+                }
+                match counts.iter_mut().find(|(id, _)| *id == voxel.id) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((voxel.id, 1)),
+                }
+            }
+        }
+    }
+
+    let volume = (max - min).element_product();
+    let solid: u32 = counts.iter().map(|(_, count)| count).sum();
+    if solid * 2 < volume {
+        return None;
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|(_, count)| *count)
+        .map(|(id, _)| Voxel::new(id))
+}
+
+/// The voxels of `neighbor`, the chunk at `offset` from the center, touching the center chunk:
+/// a layer for a face neighbor, a row for an edge neighbor and a single voxel for a corner.
+fn border_region(neighbor: &ChunkStorage, offset: IVec3) -> SparseChunk {
+    let dims = neighbor.dims();
+    let range = |axis: usize| touching_range(offset, dims, axis);
+
+    let mut border = SparseChunk::new(dims);
+    for z in range(2) {
+        for y in range(1) {
+            for x in range(0) {
+                let [x, y, z] = [x, y, z].map(|c| c as usize);
+                if let Some(voxel) = neighbor.get(x, y, z) {
+                    border.set(x, y, z, *voxel);
+                }
+            }
+        }
+    }
+
+    border
+}
+
+/// Coordinates along `axis` of a chunk of `dims`, at `offset` from the center, that touch the
+/// center: the first layer if the chunk lies past it, the last if before it, and all of them
+/// if the two are level on that axis.
+fn touching_range(offset: IVec3, dims: UVec3, axis: usize) -> Range<u32> {
+    match offset[axis].signum() {
+        1 => 0..1,
+        -1 => dims[axis] - 1..dims[axis],
+        _ => 0..dims[axis],
+    }
+}
+
+/// A [`PackedVertex`] per vertex, replacing the normal, UV, tile and color attributes of meshes
+/// drawn with [`ChunkMaterial`](crate::material::ChunkMaterial).
+pub const ATTRIBUTE_PACKED: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Packed", 988_540_917, VertexFormat::Uint32);
+
+/// Per-vertex light of meshes drawn with [`ChunkMaterial`](crate::material::ChunkMaterial),
+/// alongside [`ATTRIBUTE_PACKED`]: the [`pack_light`](crate::light::pack_light)ed byte of
+/// [`MeshBuffers::light`] in the low 8 bits, sky light in its high nibble and block light in its
+/// low one. The shader turns it into the same [`brightness`](crate::light::brightness) that
+/// unpacked meshes bake into their colors.
+pub const ATTRIBUTE_LIGHT: MeshVertexAttribute =
+    MeshVertexAttribute::new("Vertex_Light", 988_540_918, VertexFormat::Uint32);
+
+/// Everything but the position of a chunk mesh vertex, packed into 32 bits.
+///
+/// Positions stay a float attribute, since the prepass, shadows and bounds all read them. The
+/// normal is left out: every face is axis-aligned, so the shader looks it up by [`Self::dir`],
+/// which with the rest takes 4 bytes where the normal alone took 12.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedVertex {
+    /// [`Face::index`] of the face, 3 bits.
+    pub dir: u32,
+    /// Ambient occlusion level as returned by [`ao_levels`], 2 bits.
+    pub ao: u32,
+    /// UV in whole tiles, 5 bits per axis.
+    pub uv: UVec2,
+    /// Texture array layer, 16 bits.
+    pub layer: u32,
+    /// Whether the UVs scroll over time, as on a liquid surface. The top bit.
+    pub scroll: bool,
+}
+
+impl PackedVertex {
+    /// Largest UV on either axis, so the longest quad a packed mesh can hold is this many tiles.
+    pub const MAX_UV: u32 = 0x1f;
+
+    #[inline]
+    pub fn pack(self) -> u32 {
+        debug_assert!(
+            self.uv.cmple(UVec2::splat(Self::MAX_UV)).all(),
+            "packed UV {} out of range",
+            self.uv
+        );
+        (self.dir & 0x7)
+            | ((self.ao & 0x3) << 3)
+            | ((self.uv.x & 0x1f) << 5)
+            | ((self.uv.y & 0x1f) << 10)
+            | ((self.layer & 0xffff) << 15)
+            | ((self.scroll as u32) << 31)
+    }
+
+    #[inline]
+    pub fn unpack(packed: u32) -> Self {
+        Self {
+            dir: packed & 0x7,
+            ao: (packed >> 3) & 0x3,
+            uv: UVec2::new((packed >> 5) & 0x1f, (packed >> 10) & 0x1f),
+            layer: (packed >> 15) & 0xffff,
+            scroll: packed >> 31 != 0,
+        }
+    }
+}
+
+/// What face UVs address.
+#[derive(Debug, Clone, Copy)]
+pub enum UvLayout<'a> {
+    /// A tile of the atlas.
+    Atlas(&'a AtlasLayout),
+    /// A whole layer of a texture array, selected by the vertex's [`MeshBuffers::tiles`] entry.
+    /// Meshes are built with [`MeshBuffers::build_packed`].
+    Array,
+}
+
+impl UvLayout<'_> {
+    /// UV rect of `tile`.
+    #[inline]
+    pub fn rect(&self, tile: u32) -> Rect {
+        match self {
+            Self::Atlas(atlas) => atlas.tile_rect(tile),
+            Self::Array => Rect::new(0.0, 0.0, 1.0, 1.0),
+        }
+    }
+
+    /// Builds the mesh in the vertex format this layout's material reads.
+    #[inline]
+    pub fn build(&self, builder: &mut MeshBuffers) -> Mesh {
+        match self {
+            Self::Atlas(_) => builder.build(),
+            Self::Array => builder.build_packed(),
+        }
+    }
+}
+
+/// Which of a chunk's meshes a render entity draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
+pub enum MeshPass {
+    /// Opaque and cutout faces, drawn alpha-masked.
+    Opaque,
+    /// Translucent faces, drawn alpha-blended after the opaque pass.
+    Translucent,
+}
+
+/// Tags the render entities drawing chunk meshes, so debug views can pick them out from the
+/// block highlight, overlays and gizmos.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct ChunkMesh;
+
+/// A chunk's meshes, one per [`MeshPass`]. Either may be empty.
+#[derive(Debug, Clone)]
+pub struct ChunkMeshes {
+    pub opaque: Mesh,
+    pub translucent: Mesh,
+}
+
+impl ChunkMeshes {
+    #[inline]
+    pub fn into_passes(self) -> [(MeshPass, Mesh); 2] {
+        [
+            (MeshPass::Opaque, self.opaque),
+            (MeshPass::Translucent, self.translucent),
+        ]
+    }
+}
+
+/// Everything besides the voxels that a [`ChunkMesher`] reads.
+#[derive(Debug, Clone, Copy)]
+pub struct MeshContext<'a> {
+    pub registry: &'a VoxelRegistry,
+    pub uvs: UvLayout<'a>,
+    /// See [`AoSettings::strength`].
+    pub ao_strength: f32,
+    /// Baked into the vertex colors of every mesher's output in [`mesh_chunk`] while enabled.
+    pub face_shading: FaceShading,
+    /// See [`SkirtSettings::depth`].
+    pub skirt_depth: Option<f32>,
+    /// Factor the chunk was [`downsample`]d by, see [`LodSettings`]. Meshers work on the reduced
+    /// voxels as given; [`mesh_chunk`] scales the result back up.
+    pub lod: u32,
+    /// Factor of each neighbor, indexed like [`ChunkNeighborhood::neighbors`]. Nothing stitches
+    /// the cracks between chunks of different factors yet, beyond skirts if enabled; this is
+    /// what a mesher doing so would compare `lod` against.
+    pub neighbor_lods: [u32; 6],
+    /// Winding [`mesh_chunk`] leaves the mesher's triangles in.
+    pub winding: WindingOrder,
+    /// Light around the chunk at full resolution, shading the vertices of every mesher's output
+    /// in [`mesh_chunk`]. Meshes are left unlit without it.
+    pub light: Option<&'a VertexLight>,
+}
+
+/// Buffers a chunk's faces are written into, one per [`MeshPass`].
+#[derive(Debug, Default)]
+pub struct ChunkMeshBuffers {
+    pub opaque: MeshBuffers,
+    pub translucent: MeshBuffers,
+}
+
+impl ChunkMeshBuffers {
+    /// Builds both passes in the vertex format of `uvs`, leaving the buffers empty for reuse.
+    pub fn build(&mut self, uvs: UvLayout) -> ChunkMeshes {
+        ChunkMeshes {
+            opaque: uvs.build(&mut self.opaque),
+            translucent: uvs.build(&mut self.translucent),
+        }
+    }
+}
+
+/// Turns a chunk's voxels into faces.
+///
+/// The trait is object safe, so meshers from outside the crate can be registered with
+/// [`ChunkMeshers`] and switched between at runtime.
+pub trait ChunkMesher: Send + Sync + 'static {
+    /// Short name identifying the mesher in logs and the debug panel.
+    fn name(&self) -> &str;
+
+    /// Appends the faces of `chunks.center` to `out`, each into the buffer of its voxel's
+    /// [`MeshPass`].
+    fn mesh(&self, chunks: ChunkNeighborhood, context: &MeshContext, out: &mut ChunkMeshBuffers);
+}
+
+/// Meshes `chunks` with `mesher` into buffers taken from `pool`, adding skirts if `context` asks
+/// for them. Chunks downsampled for level of detail are scaled back to full size, then lit and
+/// face shaded, and triangles are flipped to [`MeshContext::winding`].
+pub fn mesh_chunk(
+    mesher: &dyn ChunkMesher,
+    chunks: ChunkNeighborhood,
+    context: &MeshContext,
+    pool: &mut MeshBufferPool,
+) -> ChunkMeshes {
+    let mut out = ChunkMeshBuffers {
+        opaque: pool.take(),
+        translucent: pool.take(),
+    };
+    mesher.mesh(chunks, context, &mut out);
+    if let Some(depth) = context.skirt_depth {
+        push_chunk_skirts(
+            &mut out.opaque,
+            chunks,
+            context.registry,
+            context.uvs,
+            depth,
+        );
+    }
+    if context.lod > 1 {
+        out.opaque.upscale(context.lod);
+        out.translucent.upscale(context.lod);
+    }
+    if let Some(light) = context.light {
+        light.shade(&mut out.opaque);
+        light.shade(&mut out.translucent);
+    }
+    if context.face_shading.enabled {
+        out.opaque.shade_faces(&context.face_shading);
+        out.translucent.shade_faces(&context.face_shading);
+    }
+    if context.winding == WindingOrder::Cw {
+        out.opaque.flip_winding();
+        out.translucent.flip_winding();
+    }
+
+    let meshes = out.build(context.uvs);
+    pool.give(out.opaque);
+    pool.give(out.translucent);
+    meshes
+}
+
+/// The built-in ways chunk voxels are turned into faces.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MeshingStrategy {
+    /// Every face of every voxel, see [`build_naive_chunk_mesh`]. Only useful as a baseline for
+    /// the others.
+    Naive,
+    /// One quad per visible face, see [`build_chunk_mesh`].
+    #[default]
+    Culled,
+    /// Coplanar faces of the same voxel type merged into larger quads, see
+    /// [`build_greedy_chunk_mesh`]. Merged quads repeat their texture once per voxel, which only
+    /// the texture array material can draw; with [`UvLayout::Atlas`] chunks are meshed like
+    /// [`Culled`](Self::Culled) instead.
+    Greedy,
+}
+
+impl MeshingStrategy {
+    pub const ALL: [Self; 3] = [Self::Naive, Self::Culled, Self::Greedy];
+
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Naive => "naive",
+            Self::Culled => "culled",
+            Self::Greedy => "greedy",
+        }
+    }
+}
+
+impl ChunkMesher for MeshingStrategy {
+    #[inline]
+    fn name(&self) -> &str {
+        MeshingStrategy::name(*self)
+    }
+
+    fn mesh(&self, chunks: ChunkNeighborhood, context: &MeshContext, out: &mut ChunkMeshBuffers) {
+        let MeshContext {
+            registry,
+            uvs,
+            ao_strength,
+            lod,
+            light,
+            ..
+        } = *context;
+        match self {
+            Self::Naive => build_naive_chunk_mesh(chunks, registry, uvs, out),
+            Self::Culled => build_chunk_mesh(chunks, registry, uvs, ao_strength, out),
+            // An atlas tile can't repeat across a merged quad, it would stretch over the quad.
+            Self::Greedy if matches!(uvs, UvLayout::Atlas(_)) => {
+                build_chunk_mesh(chunks, registry, uvs, ao_strength, out)
+            }
+            Self::Greedy => {
+                // Light is sampled at full resolution, so it can't tell reduced voxels apart.
+                let light = light.filter(|_| lod == 1);
+                build_greedy_chunk_mesh(chunks, registry, ao_strength, light, out)
+            }
+        }
+    }
+}
+
+/// The registered [`ChunkMesher`]s and which one chunks are meshed with. Starts out with every
+/// [`MeshingStrategy`], with the default one active.
+#[derive(Clone, Resource)]
+pub struct ChunkMeshers {
+    meshers: Vec<Arc<dyn ChunkMesher>>,
+    active: usize,
+}
+
+impl Default for ChunkMeshers {
+    fn default() -> Self {
+        let meshers = MeshingStrategy::ALL
+            .map(|strategy| Arc::new(strategy) as Arc<dyn ChunkMesher>)
+            .to_vec();
+        let active = MeshingStrategy::ALL
+            .iter()
+            .position(|strategy| *strategy == MeshingStrategy::default())
+            .unwrap_or_default();
+
+        Self { meshers, active }
+    }
+}
+
+impl fmt::Debug for ChunkMeshers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.iter().map(|mesher| mesher.name()).collect();
+        f.debug_struct("ChunkMeshers")
+            .field("meshers", &names)
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+impl ChunkMeshers {
+    /// Adds `mesher` after the registered ones, returning its index.
+    pub fn register(&mut self, mesher: impl ChunkMesher) -> usize {
+        self.meshers.push(Arc::new(mesher));
+        self.meshers.len() - 1
+    }
+
+    #[inline]
+    pub fn active(&self) -> &Arc<dyn ChunkMesher> {
+        &self.meshers[self.active]
+    }
+
+    #[inline]
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    /// Makes the mesher at `index` active. Out of range indices are ignored.
+    #[inline]
+    pub fn set_active(&mut self, index: usize) {
+        if index < self.meshers.len() {
+            self.active = index;
+        }
+    }
+
+    /// Makes the next mesher active, wrapping around.
+    #[inline]
+    pub fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.meshers.len();
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn ChunkMesher>> {
+        self.meshers.iter()
+    }
+}
+
+/// Caps how much remeshing starts each frame, so a burst of dirty chunks is spread over several
+/// frames instead of stalling one. Dirty chunks in the camera's view start before those outside
+/// it, each closest to the camera first; [urgent](crate::chunk::Chunk::mark_urgent) chunks start
+/// before all of them and always fit.
+#[derive(Debug, Clone, Resource)]
+pub struct MeshingBudget {
+    /// Most chunks handed to meshing tasks per frame.
+    pub max_chunks: usize,
+    /// Main thread time per frame spent snapshotting chunks for their tasks.
+    pub max_time: Duration,
+}
+
+impl Default for MeshingBudget {
+    fn default() -> Self {
+        Self {
+            max_chunks: 8,
+            max_time: Duration::from_millis(2),
+        }
+    }
+}
+
+/// Merges every chunk into one mesh per [`MeshPass`] instead of one entity per chunk, see
+/// [`merge_chunk_meshes`]. Insert it before startup for worlds that are never edited: merged
+/// meshes aren't split up again when a chunk changes.
+#[derive(Debug, Default, Clone, Copy, Resource)]
+pub struct StaticWorld;
+
+/// Ambient occlusion baked into chunk meshes as vertex colors.
+#[derive(Debug, Clone, Resource)]
+pub struct AoSettings {
+    /// How dark a fully occluded vertex gets, from `0.0` (not at all) to `1.0` (black).
+    pub strength: f32,
+    pub enabled: bool,
+}
+
+impl Default for AoSettings {
+    fn default() -> Self {
+        Self {
+            strength: 0.6,
+            enabled: true,
+        }
+    }
+}
+
+impl AoSettings {
+    /// Strength to bake with, `0.0` while disabled.
+    #[inline]
+    pub fn strength(&self) -> f32 {
+        if self.enabled {
+            self.strength
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Fixed brightness per face direction, multiplied into chunk mesh vertex colors along with
+/// ambient occlusion. A cheap stand-in for lighting that tells faces apart under flat ambient
+/// light; turn it off once lighting does the job.
+#[derive(Debug, Clone, Copy, PartialEq, Resource)]
+pub struct FaceShading {
+    pub top: f32,
+    pub bottom: f32,
+    /// Faces along z.
+    pub north_south: f32,
+    /// Faces along x.
+    pub east_west: f32,
+    pub enabled: bool,
+}
+
+impl Default for FaceShading {
+    fn default() -> Self {
+        Self {
+            top: 1.0,
+            bottom: 0.5,
+            north_south: 0.8,
+            east_west: 0.6,
+            enabled: true,
+        }
+    }
+}
+
+impl FaceShading {
+    /// Brightness of a face with `normal`, blending the directions a smooth normal leans
+    /// towards. `1.0` while disabled.
+    pub fn shade(&self, normal: Vec3) -> f32 {
+        if !self.enabled {
+            return 1.0;
+        }
+
+        let vertical = if normal.y > 0.0 {
+            self.top
+        } else {
+            self.bottom
+        };
+        let weights = normal * normal;
+        weights.x * self.east_west + weights.y * vertical + weights.z * self.north_south
+    }
+}
+
+/// Which way round chunk mesh triangles wind when seen from outside, i.e. which side is their
+/// front. Bevy culls clockwise triangles by default, so only meshers whose coordinate conventions
+/// turn their geometry inside out need [`Cw`](Self::Cw), which [`mesh_chunk`] flips them into.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum WindingOrder {
+    /// Counter-clockwise, as every built-in mesher emits faces.
+    #[default]
+    Ccw,
+    Cw,
+}
+
+/// Skirts hung from the side edges of chunk meshes, see [`push_chunk_skirts`].
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct SkirtSettings {
+    /// How far below the surface a skirt reaches, in voxels.
+    pub depth: f32,
+    pub enabled: bool,
+}
+
+impl Default for SkirtSettings {
+    fn default() -> Self {
+        Self {
+            depth: 2.0,
+            enabled: false,
+        }
+    }
+}
+
+impl SkirtSettings {
+    /// Depth to build skirts with, `None` while disabled.
+    #[inline]
+    pub fn depth(&self) -> Option<f32> {
+        self.enabled.then_some(self.depth)
+    }
+}
+
+/// Distance-based level of detail: chunks further from the camera are meshed from a
+/// [`downsample`]d copy of their voxels, see [`Chunk::lod`](crate::chunk::Chunk::lod).
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct LodSettings {
+    pub enabled: bool,
+    /// Distance from the camera to a chunk's center, in voxels, past which it is meshed at each
+    /// coarser entry of [`FACTORS`](Self::FACTORS).
+    pub distances: [f32; 2],
+    /// How far past a distance a chunk has to move before switching level, in voxels, so chunks
+    /// near a threshold don't flicker between levels.
+    pub hysteresis: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distances: [64.0, 128.0],
+            hysteresis: 4.0,
+        }
+    }
+}
+
+impl LodSettings {
+    /// Downsampling factor of each level, from full resolution to coarsest.
+    pub const FACTORS: [u32; 3] = [1, 2, 4];
+
+    /// The factor a chunk meshed at factor `current` should be meshed at, `distance` voxels from
+    /// the camera.
+    pub fn select(&self, current: u32, distance: f32) -> u32 {
+        if !self.enabled {
+            return 1;
+        }
+
+        let current = Self::FACTORS
+            .iter()
+            .position(|factor| *factor == current)
+            .unwrap_or_default();
+        let level = self
+            .distances
+            .iter()
+            .enumerate()
+            .filter(|(i, threshold)| {
+                // Levels at or past this threshold keep it until the chunk is well inside it.
+                let hysteresis = if current > *i {
+                    -self.hysteresis
+                } else {
+                    self.hysteresis
+                };
+                distance > **threshold + hysteresis
+            })
+            .count();
+
+        Self::FACTORS[level]
+    }
+}
+
+/// Vertex data accumulated for a [`Mesh`].
+///
+/// Building a mesh copies each attribute and the indices out into vectors of exactly their length,
+/// which the [`Mesh`] keeps, and [`clear`](Self::clear)s the buffers, which keep their capacity.
+/// Buffers reused through a [`MeshBufferPool`] never grow again once they fit the largest chunk,
+/// leaving those copies as the only allocations per build.
+#[derive(Debug, Default)]
+pub struct MeshBuffers {
+    pub positions: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub normals: Vec<[f32; 3]>,
+    /// Per-vertex tangents for normal mapping, see [`Face::tangent`]. Packed meshes leave them
+    /// to the shader, which derives them from the face direction.
+    pub tangents: Vec<[f32; 4]>,
+    /// Per-vertex atlas tile, which is also the texture array layer. Written to the `x` of
+    /// [`Mesh::ATTRIBUTE_UV_1`] so the default vertex shader passes it through.
+    pub tiles: Vec<u32>,
+    /// Per-vertex colors, left empty by meshes without baked lighting.
+    pub colors: Vec<[f32; 4]>,
+    /// Per-vertex ambient occlusion levels, kept alongside `colors` for packed meshes.
+    pub ao: Vec<u32>,
+    /// Per-vertex flag for UVs that scroll over time, read by packed meshes. Vertices past its
+    /// end don't scroll.
+    pub scrolling: Vec<bool>,
+    /// Per-vertex [`pack_light`](crate::light::pack_light)ed light, written by
+    /// [`VertexLight::shade`]. Unpacked meshes multiply it into their colors; packed ones carry it
+    /// as [`ATTRIBUTE_LIGHT`].
+    pub light: Vec<u8>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshBuffers {
+    /// Largest vertex count whose indices all fit in a `u16`.
+    pub const MAX_U16_VERTICES: usize = u16::MAX as usize + 1;
+
+    /// Empties every buffer, keeping its capacity.
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.uvs.clear();
+        self.normals.clear();
+        self.tangents.clear();
+        self.tiles.clear();
+        self.colors.clear();
+        self.ao.clear();
+        self.scrolling.clear();
+        self.light.clear();
+        self.indices.clear();
+    }
+
+    /// Scales positions meshed from voxels [`downsample`]d by `factor` back to full resolution
+    /// voxels, keeping each reduced cell centered on the voxels it covers.
+    pub fn upscale(&mut self, factor: u32) {
+        let offset = (factor - 1) as f32 / 2.0;
+        self.positions.iter_mut().for_each(|position| {
+            *position = (Vec3::from(*position) * factor as f32 + offset).to_array();
+        });
+    }
+
+    /// Multiplies the [`FaceShading`] of each vertex's normal into its color. Packed meshes
+    /// leave it to the shader instead.
+    pub fn shade_faces(&mut self, shading: &FaceShading) {
+        self.colors.resize(self.positions.len(), [1.0; 4]);
+        for (color, normal) in self.colors.iter_mut().zip(&self.normals) {
+            let shade = shading.shade(Vec3::from(*normal));
+            color[..3].iter_mut().for_each(|c| *c *= shade);
+        }
+    }
+
+    /// Reverses the winding of every triangle, turning the mesh inside out.
+    pub fn flip_winding(&mut self) {
+        self.indices
+            .chunks_exact_mut(3)
+            .for_each(|triangle| triangle.swap(1, 2));
+    }
+
+    pub fn build(&mut self) -> Mesh {
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs.clone())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone())
+        .with_inserted_indices(self.indices());
+
+        if !self.tangents.is_empty() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_TANGENT, self.tangents.clone());
+        }
+        if !self.tiles.is_empty() {
+            let tiles: Vec<_> = self.tiles.iter().map(|tile| [*tile as f32, 0.0]).collect();
+            mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, tiles);
+        }
+        if !self.light.is_empty() {
+            self.colors.resize(self.positions.len(), [1.0; 4]);
+            for (color, light) in self.colors.iter_mut().zip(&self.light) {
+                let brightness = light::brightness(*light);
+                color[..3].iter_mut().for_each(|c| *c *= brightness);
+            }
+        }
+        if !self.colors.is_empty() {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors.clone());
+        }
+
+        self.clear();
+        mesh
+    }
+
+    /// Builds a mesh of positions and [`ATTRIBUTE_PACKED`], plus [`ATTRIBUTE_LIGHT`] if lit, for
+    /// meshes whose UVs count whole tiles. Ambient occlusion and light are left to the shader,
+    /// which gets the levels rather than a shade.
+    pub fn build_packed(&mut self) -> Mesh {
+        let packed: Vec<u32> = (0..self.positions.len())
+            .map(|i| {
+                // Smooth normals are rounded to the closest face direction.
+                let normal = Vec3::from(self.normals[i]);
+                let dir = Face::ALL
+                    .iter()
+                    .map(|face| face.normal().dot(normal))
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map_or(0, |(dir, _)| dir);
+                PackedVertex {
+                    dir: dir as u32,
+                    ao: self.ao.get(i).copied().unwrap_or(3),
+                    uv: Vec2::from(self.uvs[i]).round().as_uvec2(),
+                    layer: self.tiles.get(i).copied().unwrap_or_default(),
+                    scroll: self.scrolling.get(i).copied().unwrap_or_default(),
+                }
+                .pack()
+            })
+            .collect();
+
+        let mut mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone())
+        .with_inserted_attribute(ATTRIBUTE_PACKED, packed)
+        .with_inserted_indices(self.indices());
+        if !self.light.is_empty() {
+            let light: Vec<u32> = self.light.iter().map(|light| *light as u32).collect();
+            mesh.insert_attribute(ATTRIBUTE_LIGHT, light);
+        }
+
+        self.clear();
+        mesh
+    }
+
+    /// The index buffer, narrowed to `u16` when every vertex can be addressed by one, which
+    /// halves its size for all but the densest chunks.
+    fn indices(&self) -> Indices {
+        narrow_indices(&self.indices, self.positions.len())
+    }
+}
+
+/// Spare [`MeshBuffers`] shared by meshing passes, so remeshing reuses buffers that have already
+/// grown instead of growing new ones for every chunk.
+#[derive(Debug, Default, Resource)]
+pub struct MeshBufferPool {
+    free: Vec<MeshBuffers>,
+}
+
+impl MeshBufferPool {
+    /// Empty buffers, reusing returned ones when there are any.
+    #[inline]
+    pub fn take(&mut self) -> MeshBuffers {
+        self.free.pop().unwrap_or_default()
+    }
+
+    /// Returns `buffers` to the pool, cleared but keeping their capacity.
+    pub fn give(&mut self, mut buffers: MeshBuffers) {
+        buffers.clear();
+        self.free.push(buffers);
+    }
+
+    /// Moves `count` buffers into a pool of their own, e.g. for a meshing task, allocating any
+    /// this pool is short of.
+    pub fn split_off(&mut self, count: usize) -> Self {
+        Self {
+            free: (0..count).map(|_| self.take()).collect(),
+        }
+    }
+
+    /// Takes back every buffer in `other`.
+    #[inline]
+    pub fn merge(&mut self, other: Self) {
+        self.free.extend(other.free);
+    }
+}
+
+/// Indices of a mesh of `vertex_count` vertices, as `u16` if
+/// [`MAX_U16_VERTICES`](MeshBuffers::MAX_U16_VERTICES) allows.
+fn narrow_indices(indices: &[u32], vertex_count: usize) -> Indices {
+    if vertex_count <= MeshBuffers::MAX_U16_VERTICES {
+        Indices::U16(indices.iter().map(|i| *i as u16).collect())
+    } else {
+        Indices::U32(indices.to_vec())
+    }
+}
+
+/// Appends `face` of a unit cube centered on `pos`, textured with `uv_rect`. Every mesher builds
+/// its faces from this or [`push_quad`].
+pub fn push_face(builder: &mut MeshBuffers, pos: Vec3, face: Face, uv_rect: Rect) {
+    push_scaled_face(builder, pos, face, uv_rect, 1.0);
+}
+
+/// Like [`push_face`], for a cube scaled by `scale` around `pos`.
+pub fn push_scaled_face(
+    builder: &mut MeshBuffers,
+    pos: Vec3,
+    face: Face,
+    uv_rect: Rect,
+    scale: f32,
+) {
+    push_face_with_diagonal(
+        builder,
+        pos,
+        face,
+        uv_rect,
+        UvTransform::IDENTITY,
+        scale,
+        false,
+    );
+}
+
+/// Like [`push_scaled_face`], with `uv_rect` turned by `transform` and shaded by per-vertex
+/// ambient occlusion `levels` as returned by [`ao_levels`].
+#[allow(clippy::too_many_arguments)]
+pub fn push_occluded_face(
+    builder: &mut MeshBuffers,
+    pos: Vec3,
+    face: Face,
+    uv_rect: Rect,
+    transform: UvTransform,
+    scale: f32,
+    levels: [u32; 4],
+    strength: f32,
+) {
+    // Split along the brighter diagonal so a single dark corner doesn't bleed across the quad.
+    let flip = levels[0] + levels[2] > levels[1] + levels[3];
+    push_face_with_diagonal(builder, pos, face, uv_rect, transform, scale, flip);
+
+    builder.colors.extend(levels.map(|level| {
+        let shade = 1.0 - strength * (3 - level) as f32 / 3.0;
+        [shade, shade, shade, 1.0]
+    }));
+    builder.ao.extend(levels);
+}
+
+/// Pushes a face, splitting it along the diagonal through its first and third vertex instead of
+/// the second and fourth if `flip` is set.
+fn push_face_with_diagonal(
+    builder: &mut MeshBuffers,
+    pos: Vec3,
+    face: Face,
+    uv_rect: Rect,
+    transform: UvTransform,
+    scale: f32,
+    flip: bool,
+) {
+    let base = builder.positions.len() as u32;
+    // Rotating every index by one vertex swaps the diagonal and keeps the winding.
+    let shift = flip as u32;
+
+    builder
+        .positions
+        .extend(face.corners(pos, scale).map(|corner| corner.to_array()));
+    builder.uvs.extend(
+        face.transformed_uvs(uv_rect, transform)
+            .map(|uv| uv.to_array()),
+    );
+    builder.normals.extend([face.normal().to_array(); 4]);
+    builder
+        .tangents
+        .extend([face.transformed_tangent(transform).to_array(); 4]);
+    builder
+        .indices
+        .extend(face.indices().map(|i| base + (i + shift) % 4));
+}
+
+/// Appends a copy of the faces pushed since vertex `vertices` and index `indices`, wound and
+/// facing the other way, for [`double_sided`](crate::registry::VoxelType::double_sided) voxels.
+pub fn push_back_faces(builder: &mut MeshBuffers, vertices: usize, indices: usize) {
+    // Only attributes written for every vertex so far are extended, so they stay aligned.
+    fn mirror<T: Clone>(values: &mut Vec<T>, vertices: usize, end: usize) {
+        if values.len() == end {
+            values.extend_from_within(vertices..end);
+        }
+    }
+
+    let end = builder.positions.len();
+    mirror(&mut builder.positions, vertices, end);
+    mirror(&mut builder.uvs, vertices, end);
+    mirror(&mut builder.tiles, vertices, end);
+    mirror(&mut builder.colors, vertices, end);
+    mirror(&mut builder.ao, vertices, end);
+    mirror(&mut builder.scrolling, vertices, end);
+    if builder.normals.len() == end {
+        builder.normals.extend_from_within(vertices..end);
+        builder.normals[end..]
+            .iter_mut()
+            .for_each(|normal| *normal = normal.map(|c| -c));
+    }
+    if builder.tangents.len() == end {
+        builder.tangents.extend_from_within(vertices..end);
+        // Keeps the bitangent, and with it the texture, the right way up.
+        builder.tangents[end..]
+            .iter_mut()
+            .for_each(|tangent| tangent[3] = -tangent[3]);
+    }
+
+    let offset = (end - vertices) as u32;
+    let back: Vec<_> = builder.indices[indices..]
+        .chunks_exact(3)
+        .flat_map(|triangle| [triangle[0], triangle[2], triangle[1]].map(|i| i + offset))
+        .collect();
+    builder.indices.extend(back);
+}
+
+/// Lowers the vertices pushed since vertex `from` to at most `top`, for the faces of a liquid
+/// whose surface sits below the top of its voxel. Top faces are also marked as scrolling.
+pub fn lower_liquid_face(builder: &mut MeshBuffers, from: usize, face: Face, top: f32) {
+    builder.positions[from..]
+        .iter_mut()
+        .for_each(|position| position[1] = position[1].min(top));
+
+    if face == Face::PosY {
+        builder.scrolling.resize(from, false);
+        builder.scrolling.resize(builder.positions.len(), true);
+    }
+}
+
+/// Ambient occlusion level of each vertex of `face` of the voxel at `pos`, from `0` (fully
+/// occluded) to `3` (open), counting the two side and one corner voxels in front of the vertex
+/// for which `is_solid` is true.
+pub fn ao_levels(pos: IVec3, face: Face, is_solid: impl Fn(IVec3) -> bool) -> [u32; 4] {
+    let normal = face.normal();
+    face.corners(Vec3::ZERO, 1.0).map(|vertex| {
+        // Step towards the vertex along the two axes the face spans.
+        let toward = ((vertex - normal * 0.5) * 2.0).as_ivec3();
+        let front = pos + face.offset();
+        let [a, b] = face.uv_axes().map(|axis| {
+            let mut step = IVec3::ZERO;
+            step[axis] = toward[axis];
+            step
+        });
+
+        let (side_a, side_b) = (is_solid(front + a), is_solid(front + b));
+        if side_a && side_b {
+            0
+        } else {
+            3 - side_a as u32 - side_b as u32 - is_solid(front + a + b) as u32
+        }
+    })
+}
+
+/// Appends `face` of the box spanning the voxels `min..=max`, pushed `offset` voxels out
+/// along its normal. UVs count tiles across the face so a tiling material repeats `tile` once
+/// per voxel, turned by `transform`.
+pub fn push_quad(
+    builder: &mut MeshBuffers,
+    min: IVec3,
+    max: IVec3,
+    face: Face,
+    tile: u32,
+    transform: UvTransform,
+    offset: f32,
+) {
+    let base = builder.positions.len() as u32;
+    let (min, max) = (min.as_vec3(), max.as_vec3());
+    let extent = max - min + Vec3::ONE;
+    let [u, v] = face.uv_axes();
+    // Turned a quarter, the texture's axes count tiles along each other's face axis.
+    let tiles = if transform.swaps_axes() {
+        Vec2::new(extent[v], extent[u])
+    } else {
+        Vec2::new(extent[u], extent[v])
+    };
+
+    builder
+        .positions
+        .extend(face.corners(Vec3::ZERO, 1.0).map(|vertex| {
+            let corner = Vec3::select(vertex.cmplt(Vec3::ZERO), min, max) + vertex;
+            (corner + face.normal() * offset).to_array()
+        }));
+    builder.uvs.extend(
+        face.transformed_uv_corners(transform)
+            .map(|corner| (corner * tiles).to_array()),
+    );
+    builder.normals.extend([face.normal().to_array(); 4]);
+    builder
+        .tangents
+        .extend([face.transformed_tangent(transform).to_array(); 4]);
+    builder.tiles.extend([tile; 4]);
+    builder.colors.extend([[1.0; 4]; 4]);
+    builder.ao.extend([3; 4]);
+    builder.indices.extend(face.indices().map(|i| base + i));
+}
+
+/// Shades the quad pushed by [`push_quad`] at vertex `from` with ambient occlusion `levels`,
+/// splitting it along the brighter diagonal like [`push_occluded_face`].
+fn occlude_quad(
+    builder: &mut MeshBuffers,
+    from: usize,
+    face: Face,
+    levels: [u32; 4],
+    strength: f32,
+) {
+    let shades = levels.map(|level| {
+        let shade = 1.0 - strength * (3 - level) as f32 / 3.0;
+        [shade, shade, shade, 1.0]
+    });
+    builder.colors[from..from + 4].copy_from_slice(&shades);
+    builder.ao[from..from + 4].copy_from_slice(&levels);
+
+    if levels[0] + levels[2] > levels[1] + levels[3] {
+        let base = from as u32;
+        let start = builder.indices.len() - 6;
+        for (index, i) in builder.indices[start..].iter_mut().zip(face.indices()) {
+            *index = base + (i + 1) % 4;
+        }
+    }
+}
+
+/// Appends skirts along the side borders of the chunk: in each border column, the outward face
+/// of the topmost opaque voxel with nothing opaque above it, stretched to hang `depth` voxels
+/// below the voxel's top. Only faces the neighbor hides get one, since visible faces are meshed
+/// already. Where the neighbor is meshed coarser, skirts cover the cracks along the shared edge.
+pub fn push_chunk_skirts<S: VoxelStorage>(
+    builder: &mut MeshBuffers,
+    chunks: ChunkNeighborhood<S>,
+    registry: &VoxelRegistry,
+    uvs: UvLayout,
+    depth: f32,
+) {
+    let is_opaque = |pos: IVec3| {
+        chunks
+            .get(pos)
+            .and_then(|voxel| registry.get(voxel.id))
+            .is_some_and(|voxel_type| voxel_type.transparency == Transparency::Opaque)
+    };
+
+    let dims = chunks.center.dims().as_ivec3();
+    for face in [Face::PosX, Face::NegX, Face::PosZ, Face::NegZ] {
+        let axis = face.axis();
+        // The other horizontal axis, which the border runs along.
+        let along = 2 - axis;
+        let layer = if face.is_positive() {
+            dims[axis] - 1
+        } else {
+            0
+        };
+
+        for i in 0..dims[along] {
+            let mut column = IVec3::ZERO;
+            column[axis] = layer;
+            column[along] = i;
+            let Some(pos) = (0..dims.y)
+                .rev()
+                .map(|y| column.with_y(y))
+                .find(|pos| is_opaque(*pos) && !is_opaque(*pos + IVec3::Y))
+            else {
+                continue;
+            };
+            let Some(voxel) = chunks.get(pos).copied() else {
+                continue;
+            };
+            let Some(voxel_type) = registry.get(voxel.id) else {
+                continue;
+            };
+            if !is_face_hidden(registry, voxel, face, chunks.get(pos + face.offset())) {
+                continue;
+            }
+
+            let from = builder.positions.len();
+            let texture = voxel_face_texture(voxel_type, voxel, face);
+            push_occluded_face(
+                builder,
+                pos.as_vec3(),
+                face,
+                uvs.rect(texture),
+                voxel_uv_transform(voxel_type, voxel, face),
+                1.0,
+                [3; 4],
+                0.0,
+            );
+            builder.tiles.extend([texture; 4]);
+
+            let bottom = pos.y as f32 + 0.5 - depth;
+            builder.positions[from..]
+                .iter_mut()
+                .filter(|position| position[1] < pos.y as f32)
+                .for_each(|position| position[1] = bottom);
+        }
+    }
+}
+
+/// Texture layer drawn on the world's `face` of `voxel`, turned by its
+/// [`orientation`](Voxel::orientation) if its type is [`directional`](VoxelType::directional).
+#[inline]
+pub fn voxel_face_texture(voxel_type: &VoxelType, voxel: Voxel, face: Face) -> u32 {
+    let face = if voxel_type.directional {
+        voxel.orientation().local_face(face)
+    } else {
+        face
+    };
+    face_texture(voxel_type.textures, face)
+}
+
+/// How the texture of the world's `face` of `voxel` is turned: by its voxel type's
+/// [`uv_transforms`](VoxelType::uv_transforms), and then along with the voxel's
+/// [`orientation`](Voxel::orientation) if its type is [`directional`](VoxelType::directional).
+#[inline]
+pub fn voxel_uv_transform(voxel_type: &VoxelType, voxel: Voxel, face: Face) -> UvTransform {
+    if !voxel_type.directional {
+        return voxel_type.uv_transforms[face.index()];
+    }
+
+    let orientation = voxel.orientation();
+    let local = orientation.local_face(face);
+    voxel_type.uv_transforms[local.index()].then(orientation.uv_transform(face))
+}
+
+/// Texture layer drawn on `face`.
+#[inline]
+pub fn face_texture(textures: FaceTextures, face: Face) -> u32 {
+    match face {
+        Face::PosY => textures.top,
+        Face::NegY => textures.bottom,
+        _ => textures.side,
+    }
+}
+
+/// Bounds of a chunk mesh of a chunk of `dims`, in the mesh's voxel units: every voxel's cube,
+/// padded for overlays. Chunk mesh entities get this rather than bounds computed from the mesh,
+/// which Bevy never updates when a remesh swaps the mesh handle, so they are culled by the
+/// chunk's volume whatever was meshed. Skirts hanging below the chunk's bottom are left out.
+pub fn chunk_aabb(dims: UVec3) -> Aabb {
+    let pad = 0.5 + (OVERLAY_INFLATE - 1.0) / 2.0;
+    Aabb::from_min_max(Vec3::splat(-pad), dims.as_vec3() - 1.0 + pad)
+}
+
+pub fn generate_cube(textures: FaceTextures, atlas: &AtlasLayout) -> Mesh {
+    let mut builder = MeshBuffers::default();
+    Face::ALL.into_iter().for_each(|face| {
+        let uv_rect = atlas.tile_rect(face_texture(textures, face));
+        push_face(&mut builder, Vec3::ZERO, face, uv_rect);
+    });
+
+    let mesh = builder.build();
+    debug_assert!(
+        cube_normals_match(&mesh),
+        "cube face normals disagree with their winding or point inward"
+    );
+    mesh
+}
+
+/// Whether every normal of a cube centered on the origin matches its triangles' winding and
+/// points away from the center, catching face data that was copied to the wrong direction.
+fn cube_normals_match(mesh: &Mesh) -> bool {
+    let (Some(flat), Some(VertexAttributeValues::Float32x3(normals))) =
+        (flat_normals(mesh), mesh.attribute(Mesh::ATTRIBUTE_NORMAL))
+    else {
+        return false;
+    };
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return false;
+    };
+
+    normals
+        .iter()
+        .zip(&flat)
+        .zip(positions)
+        .all(|((normal, flat), position)| {
+            let normal = Vec3::from(*normal);
+            normal.abs_diff_eq(Vec3::from(*flat), 1e-4) && normal.dot(Vec3::from(*position)) > 0.0
+        })
+}
+
+/// The twelve edges of a voxel centered on the origin as a [`PrimitiveTopology::LineList`],
+/// inflated by [`SELECTION_INFLATE`], for outlining the targeted block.
+pub fn selection_box_mesh() -> Mesh {
+    let half = Voxel::SIZE * SELECTION_INFLATE / 2.0;
+    let corners: Vec<[f32; 3]> = (0..8)
+        .map(|i| {
+            let corner = IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+            ((corner.as_vec3() * 2.0 - 1.0) * half).to_array()
+        })
+        .collect();
+    // Corners differing in exactly one bit share an edge.
+    let edges: Vec<u16> = (0..8u16)
+        .flat_map(|i| [1, 2, 4].map(|bit| [i, i | bit]))
+        .filter(|&[a, b]| a != b)
+        .flatten()
+        .collect();
+
+    Mesh::new(
+        PrimitiveTopology::LineList,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, corners)
+    .with_inserted_indices(Indices::U16(edges))
+}
+
+/// Replaces the normals of a [`PrimitiveTopology::TriangleList`] mesh with ones derived from its
+/// positions and winding, for meshers that don't write normals themselves. Returns `false`,
+/// leaving the mesh untouched, for other topologies, meshes without float positions, or indices
+/// past the last vertex.
+///
+/// Each triangle's vertices get the normal it faces, winding counter-clockwise. Vertices shared by
+/// triangles facing different ways can't hold both, so such meshes are de-indexed first, giving
+/// every triangle vertices of its own.
+pub fn recompute_flat_normals(mesh: &mut Mesh) -> bool {
+    let Some((indices, faces)) = triangle_normals(mesh) else {
+        return false;
+    };
+    let normals = match shared_normals(mesh.count_vertices(), &indices, &faces) {
+        Some(normals) => normals,
+        None => {
+            mesh.duplicate_vertices();
+            // Unindexed, each run of three vertices is one triangle.
+            faces.iter().flat_map(|face| [face.to_array(); 3]).collect()
+        }
+    };
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    true
+}
+
+/// The normals [`recompute_flat_normals`] writes to `mesh` as it is, or `None` if it can't handle
+/// it or would have to split vertices.
+fn flat_normals(mesh: &Mesh) -> Option<Vec<[f32; 3]>> {
+    let (indices, faces) = triangle_normals(mesh)?;
+    shared_normals(mesh.count_vertices(), &indices, &faces)
+}
+
+/// A triangle list's vertex indices, and the unit normal of each triangle, zero where it has no
+/// area.
+fn triangle_normals(mesh: &Mesh) -> Option<(Vec<usize>, Vec<Vec3>)> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    let faces = indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]]
+                .map(|i| positions.get(i).copied().map(Vec3::from));
+            let (a, b, c) = (a?, b?, c?);
+            Some((b - a).cross(c - a).normalize_or_zero())
+        })
+        .collect::<Option<_>>()?;
+
+    Some((indices, faces))
+}
+
+/// Normals for `vertices` vertices from the triangles using them, or `None` if a vertex is used
+/// by triangles facing different ways. Triangles without area don't count.
+fn shared_normals(vertices: usize, indices: &[usize], faces: &[Vec3]) -> Option<Vec<[f32; 3]>> {
+    let mut normals = vec![Vec3::ZERO; vertices];
+    for (triangle, face) in indices.chunks_exact(3).zip(faces) {
+        if *face == Vec3::ZERO {
+            continue;
+        }
+        for &i in triangle {
+            if normals[i] == Vec3::ZERO {
+                normals[i] = *face;
+            } else if !normals[i].abs_diff_eq(*face, 1e-4) {
+                return None;
+            }
+        }
+    }
+
+    Some(normals.iter().map(Vec3::to_array).collect())
+}
+
+/// Why [`validate_mesh`] rejected a mesh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MeshError {
+    /// The mesh has no positions to count its vertices by.
+    MissingPositions,
+    /// An attribute has a different number of values than there are positions.
+    AttributeLength {
+        attribute: &'static str,
+        len: usize,
+        vertices: usize,
+    },
+    /// The index at `position` of the index buffer points past the last vertex.
+    IndexOutOfRange {
+        position: usize,
+        index: usize,
+        vertices: usize,
+    },
+}
 
-    let uvs = vec![
-        // Assigning the UV coords for the top side.
-        [0.0, 0.2],
-        [0.0, 0.0],
-        [1.0, 0.0],
-        [1.0, 0.2],
-        // Assigning the UV coords for the bottom side.
-        [0.0, 0.45],
-        [0.0, 0.25],
-        [1.0, 0.25],
-        [1.0, 0.45],
-        // Assigning the UV coords for the right side.
-        [1.0, 0.45],
-        [0.0, 0.45],
-        [0.0, 0.2],
-        [1.0, 0.2],
-        // Assigning the UV coords for the left side.
-        [1.0, 0.45],
-        [0.0, 0.45],
-        [0.0, 0.2],
-        [1.0, 0.2],
-        // Assigning the UV coords for the back side.
-        [0.0, 0.45],
-        [0.0, 0.2],
-        [1.0, 0.2],
-        [1.0, 0.45],
-        // Assigning the UV coords for the forward side.
-        [0.0, 0.45],
-        [0.0, 0.2],
-        [1.0, 0.2],
-        [1.0, 0.45],
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::MissingPositions => write!(f, "mesh has no positions"),
+            Self::AttributeLength {
+                attribute,
+                len,
+                vertices,
+            } => {
+                let (by, relation) = if len > vertices {
+                    (len - vertices, "more")
+                } else {
+                    (vertices - len, "fewer")
+                };
+                write!(
+                    f,
+                    "{attribute} has {len} values, {by} {relation} than the {vertices} positions"
+                )
+            }
+            Self::IndexOutOfRange {
+                position,
+                index,
+                vertices,
+            } => write!(
+                f,
+                "index {position} is {index}, past the last of {vertices} vertices"
+            ),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+/// Checks that every attribute chunk meshes use has as many values as the mesh has positions,
+/// and that every index points at a vertex, which Bevy otherwise only reports by panicking
+/// somewhere in the renderer. Attributes added by meshers outside the crate aren't checked.
+pub fn validate_mesh(mesh: &Mesh) -> Result<(), MeshError> {
+    const ATTRIBUTES: [MeshVertexAttribute; 7] = [
+        Mesh::ATTRIBUTE_NORMAL,
+        Mesh::ATTRIBUTE_UV_0,
+        Mesh::ATTRIBUTE_UV_1,
+        Mesh::ATTRIBUTE_TANGENT,
+        Mesh::ATTRIBUTE_COLOR,
+        ATTRIBUTE_PACKED,
+        ATTRIBUTE_LIGHT,
     ];
 
-    let normals = vec![
-        // Normals for the top side (towards +y)
-        [0.0, 1.0, 0.0],
-        [0.0, 1.0, 0.0],
-        [0.0, 1.0, 0.0],
-        [0.0, 1.0, 0.0],
-        // Normals for the bottom side (towards -y)
-        [0.0, -1.0, 0.0],
-        [0.0, -1.0, 0.0],
-        [0.0, -1.0, 0.0],
-        [0.0, -1.0, 0.0],
-        // Normals for the right side (towards +x)
-        [1.0, 0.0, 0.0],
-        [1.0, 0.0, 0.0],
-        [1.0, 0.0, 0.0],
-        [1.0, 0.0, 0.0],
-        // Normals for the left side (towards -x)
-        [-1.0, 0.0, 0.0],
-        [-1.0, 0.0, 0.0],
-        [-1.0, 0.0, 0.0],
-        [-1.0, 0.0, 0.0],
-        // Normals for the back side (towards +z)
-        [0.0, 0.0, 1.0],
-        [0.0, 0.0, 1.0],
-        [0.0, 0.0, 1.0],
-        [0.0, 0.0, 1.0],
-        // Normals for the forward side (towards -z)
-        [0.0, 0.0, -1.0],
-        [0.0, 0.0, -1.0],
-        [0.0, 0.0, -1.0],
-        [0.0, 0.0, -1.0],
+    let vertices = mesh
+        .attribute(Mesh::ATTRIBUTE_POSITION)
+        .ok_or(MeshError::MissingPositions)?
+        .len();
+    for attribute in ATTRIBUTES {
+        let Some(values) = mesh.attribute(attribute.id) else {
+            continue;
+        };
+        if values.len() != vertices {
+            return Err(MeshError::AttributeLength {
+                attribute: attribute.name,
+                len: values.len(),
+                vertices,
+            });
+        }
+    }
+
+    let out_of_range = mesh
+        .indices()
+        .into_iter()
+        .flat_map(Indices::iter)
+        .enumerate()
+        .find(|(_, index)| *index >= vertices);
+    match out_of_range {
+        Some((position, index)) => Err(MeshError::IndexOutOfRange {
+            position,
+            index,
+            vertices,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Merges chunk meshes into a single mesh, offsetting each one's vertices by the chunk's origin in
+/// voxels, so a static world takes a handful of draw calls.
+///
+/// Meshes are expected to come from the same mesher and [`UvLayout`], so they share
+/// attributes. Attributes of the first mesh missing from a later one are dropped.
+pub fn merge_chunk_meshes(meshes: &[(IVec3, Mesh)]) -> Mesh {
+    const ATTRIBUTES: [MeshVertexAttribute; 6] = [
+        Mesh::ATTRIBUTE_POSITION,
+        Mesh::ATTRIBUTE_NORMAL,
+        Mesh::ATTRIBUTE_UV_0,
+        Mesh::ATTRIBUTE_UV_1,
+        Mesh::ATTRIBUTE_COLOR,
+        ATTRIBUTE_PACKED,
     ];
 
-    let indices = Indices::U32(vec![
-        0, 3, 1, 1, 3, 2, // triangles making up the top (+y) facing side.
-        4, 5, 7, 5, 6, 7, // bottom (-y)
-        8, 11, 9, 9, 11, 10, // right (+x)
-        12, 13, 15, 13, 14, 15, // left (-x)
-        16, 19, 17, 17, 19, 18, // back (+z)
-        20, 21, 23, 21, 22, 23, // forward (-z)
-    ]);
+    let mut attributes: Vec<_> = ATTRIBUTES
+        .into_iter()
+        .filter_map(|attribute| {
+            let (_, first) = meshes.first()?;
+            let mut values = first.attribute(attribute.id)?.clone();
+            clear_values(&mut values);
+            Some((attribute, values))
+        })
+        .collect();
+    let mut indices = Vec::new();
+    let mut vertex_count = 0;
 
-    Mesh::new(
+    for (origin, mesh) in meshes {
+        let base = vertex_count as u32;
+        attributes.retain_mut(|(attribute, values)| {
+            let Some(other) = mesh.attribute(attribute.id) else {
+                return false;
+            };
+            let offset = (attribute.id == Mesh::ATTRIBUTE_POSITION.id).then(|| origin.as_vec3());
+            extend_values(values, other, offset)
+        });
+        if let Some(mesh_indices) = mesh.indices() {
+            indices.extend(mesh_indices.iter().map(|i| base + i as u32));
+        }
+        vertex_count += mesh.count_vertices();
+    }
+
+    let mut merged = Mesh::new(
         PrimitiveTopology::TriangleList,
         RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
-    )
-    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, vertices)
-    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
-    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
-    .with_inserted_indices(indices)
+    );
+    for (attribute, values) in attributes {
+        merged.insert_attribute(attribute, values);
+    }
+
+    merged.with_inserted_indices(narrow_indices(&indices, vertex_count))
+}
+
+/// Empties `values`, keeping its format.
+fn clear_values(values: &mut VertexAttributeValues) {
+    match values {
+        VertexAttributeValues::Float32x2(values) => values.clear(),
+        VertexAttributeValues::Float32x3(values) => values.clear(),
+        VertexAttributeValues::Float32x4(values) => values.clear(),
+        VertexAttributeValues::Uint32(values) => values.clear(),
+        _ => {}
+    }
+}
+
+/// Appends `other` to `values`, adding `offset` to three component values. Returns `false` if
+/// the formats differ or aren't used by chunk meshes.
+fn extend_values(
+    values: &mut VertexAttributeValues,
+    other: &VertexAttributeValues,
+    offset: Option<Vec3>,
+) -> bool {
+    match (values, other) {
+        (VertexAttributeValues::Float32x2(values), VertexAttributeValues::Float32x2(other)) => {
+            values.extend_from_slice(other);
+        }
+        (VertexAttributeValues::Float32x3(values), VertexAttributeValues::Float32x3(other)) => {
+            let offset = offset.unwrap_or_default();
+            values.extend(other.iter().map(|v| (Vec3::from(*v) + offset).to_array()));
+        }
+        (VertexAttributeValues::Float32x4(values), VertexAttributeValues::Float32x4(other)) => {
+            values.extend_from_slice(other);
+        }
+        (VertexAttributeValues::Uint32(values), VertexAttributeValues::Uint32(other)) => {
+            values.extend_from_slice(other);
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+/// Whether `face` of `voxel`, against `neighbor`, can't be seen. Opaque neighbors hide the face,
+/// and translucent neighbors hide the faces of the same voxel type, so a body of water has no
+/// faces inside it, as long as the neighbor [`occludes`] the side it turns towards the face.
+/// Faces against anything else, including opaque faces behind water or cubes above a bottom
+/// slab, are kept.
+pub fn is_face_hidden(
+    registry: &VoxelRegistry,
+    voxel: Voxel,
+    face: Face,
+    neighbor: Option<&Voxel>,
+) -> bool {
+    occluding_shape(registry, voxel, neighbor)
+        .is_some_and(|(shape, orientation)| occludes(face.opposite(), shape, orientation))
+}
+
+/// The shape and orientation of `neighbor` if its filled subcells hide the faces of `voxel`
+/// they touch, see [`is_face_hidden`].
+fn occluding_shape(
+    registry: &VoxelRegistry,
+    voxel: Voxel,
+    neighbor: Option<&Voxel>,
+) -> Option<(VoxelShape, Orientation)> {
+    let neighbor = neighbor?;
+    let neighbor_type = registry.get(neighbor.id)?;
+    let hides = match neighbor_type.transparency {
+        Transparency::Opaque => true,
+        Transparency::Translucent => neighbor.id == voxel.id,
+        _ => false,
+    };
+
+    hides.then_some((neighbor_type.shape, neighbor.orientation()))
+}
+
+/// The subcells of `neighbor` hiding the faces of `voxel` they touch, see [`occluding_shape`].
+fn occluding_mask(registry: &VoxelRegistry, voxel: Voxel, neighbor: Option<&Voxel>) -> u8 {
+    occluding_shape(registry, voxel, neighbor)
+        .map_or(0, |(shape, orientation)| shape.mask(orientation))
+}
+
+/// [`face_mask`] of a side whose four subcells are all filled.
+pub const FULL_FACE: u8 = 0b1111;
+
+/// The subcells of a [`VoxelShape::mask`] touching the `face` side of the cell, one bit per
+/// quarter of the face. Quarters are numbered by their subcell's coordinates along the face's
+/// other two axes, so the touching sides of two neighbors number them alike.
+pub fn face_mask(mask: u8, face: Face) -> u8 {
+    let axis = face.axis();
+    let [a, b] = [(axis + 1) % 3, (axis + 2) % 3];
+    let side = face.is_positive() as usize;
+
+    (0..8usize)
+        .filter(|i| mask & 1 << i != 0 && (i >> axis) & 1 == side)
+        .fold(0, |quarters, i| {
+            quarters | 1 << (((i >> a) & 1) | ((i >> b) & 1) << 1)
+        })
+}
+
+/// Whether a voxel of `shape` turned to `orientation` fills its whole `face` side, hiding the
+/// faces of opaque neighbors against it. Culling asks it of both voxels of a pair: whether my
+/// neighbor occludes the side facing me, and whether I occlude the side facing it.
+#[inline]
+pub fn occludes(face: Face, shape: VoxelShape, orientation: Orientation) -> bool {
+    face_mask(shape.mask(orientation), face) == FULL_FACE
+}
+
+/// Appends the faces of a voxel that isn't a [`VoxelShape::Cube`]: a quarter face for each side
+/// of each filled subcell, culled against the other subcells and against the
+/// [`occluding_mask`] of the neighbors. Their UVs cover the matching part of the face's tile.
+///
+/// Shaped voxels get no ambient occlusion or overlay. Packed meshes round UVs to whole tiles, so
+/// the texture array material squeezes a tile onto each quarter face.
+fn push_shaped_voxel<S: VoxelStorage>(
+    builder: &mut MeshBuffers,
+    chunks: ChunkNeighborhood<S>,
+    registry: &VoxelRegistry,
+    uvs: UvLayout,
+    pos: IVec3,
+    voxel: Voxel,
+    voxel_type: &VoxelType,
+) {
+    let mask = voxel_type.shape.mask(voxel.orientation());
+    let subcell = |cell: IVec3| 1u8 << (cell.x | cell.y << 1 | cell.z << 2);
+
+    for i in 0..8 {
+        if mask & 1 << i == 0 {
+            continue;
+        }
+
+        let cell = IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1);
+        for face in Face::ALL {
+            let next = cell + face.offset();
+            let hidden = if next.cmpge(IVec3::ZERO).all() && next.cmplt(IVec3::splat(2)).all() {
+                mask & subcell(next) != 0
+            } else {
+                let neighbor = occluding_mask(registry, voxel, chunks.get(pos + face.offset()));
+                neighbor & subcell(next.rem_euclid(IVec3::splat(2))) != 0
+            };
+            if hidden {
+                continue;
+            }
+
+            let from = builder.positions.len();
+            let texture = voxel_face_texture(voxel_type, voxel, face);
+            let transform = voxel_uv_transform(voxel_type, voxel, face);
+            let rect = uvs.rect(texture);
+            let center = pos.as_vec3() + (cell.as_vec3() - 0.5) * 0.5;
+            push_occluded_face(builder, center, face, rect, transform, 0.5, [3; 4], 0.0);
+            for vertex in from..builder.positions.len() {
+                let local = Vec3::from(builder.positions[vertex]) - pos.as_vec3();
+                builder.uvs[vertex] = face.uv_at(local, rect, transform).to_array();
+            }
+            builder.tiles.extend([texture; 4]);
+        }
+    }
+}
+
+/// Whether the liquid `voxel` at `pos` has its surface lowered to [`LIQUID_SURFACE_HEIGHT`],
+/// i.e. the voxel above is neither opaque nor the same liquid. Columns of liquid stay full height
+/// below their surface so they connect without gaps.
+fn is_liquid_surface<S: VoxelStorage>(
+    registry: &VoxelRegistry,
+    chunks: ChunkNeighborhood<S>,
+    pos: IVec3,
+    voxel: Voxel,
+) -> bool {
+    let Some(above) = chunks.get(pos + IVec3::Y) else {
+        return true;
+    };
+
+    above.id != voxel.id
+        && registry
+            .get(above.id)
+            .is_none_or(|voxel_type| voxel_type.transparency != Transparency::Opaque)
+}
+
+/// [`is_face_hidden`] for the voxel at `pos` in `chunks`, which can also tell liquid levels
+/// apart. A full height liquid keeps its side against a lowered surface of the same liquid, so
+/// the step between the two levels has no hole. The whole side is kept rather than just the
+/// step, which is drawn behind the lower liquid.
+fn is_chunk_face_hidden<S: VoxelStorage>(
+    registry: &VoxelRegistry,
+    chunks: ChunkNeighborhood<S>,
+    pos: IVec3,
+    voxel: Voxel,
+    face: Face,
+) -> bool {
+    let neighbor = chunks.get(pos + face.offset());
+    if !is_face_hidden(registry, voxel, face, neighbor) {
+        return false;
+    }
+
+    let steps_down = face.axis() != 1
+        && neighbor.is_some_and(|neighbor| neighbor.id == voxel.id)
+        && registry
+            .get(voxel.id)
+            .is_some_and(|voxel_type| voxel_type.liquid)
+        && !is_liquid_surface(registry, chunks, pos, voxel)
+        && is_liquid_surface(registry, chunks, pos + face.offset(), voxel);
+    !steps_down
+}
+
+/// Appends every face of every voxel in a chunk, hidden or not, with translucent voxels in their
+/// own buffer. Face UVs address each voxel type's tiles as laid out by `uvs`. Overlays, ambient
+/// occlusion and lowered liquid surfaces are left out, and voxels that aren't cubes are meshed
+/// the way [`build_chunk_mesh`] does.
+pub fn build_naive_chunk_mesh<S: VoxelStorage>(
+    chunks: ChunkNeighborhood<S>,
+    registry: &VoxelRegistry,
+    uvs: UvLayout,
+    out: &mut ChunkMeshBuffers,
+) {
+    for (pos, voxel) in chunks.center.iter() {
+        let Some(voxel_type) = registry.get(voxel.id) else {
+            continue;
+        };
+        let builder = match voxel_type.transparency {
+            Transparency::Invisible => continue,
+            Transparency::Translucent => &mut out.translucent,
+            Transparency::Opaque | Transparency::Cutout => &mut out.opaque,
+        };
+        if voxel_type.shape != VoxelShape::Cube {
+            push_shaped_voxel(
+                builder,
+                chunks,
+                registry,
+                uvs,
+                pos.as_ivec3(),
+                voxel,
+                voxel_type,
+            );
+            continue;
+        }
+
+        let from = (builder.positions.len(), builder.indices.len());
+        for face in Face::ALL {
+            let texture = voxel_face_texture(voxel_type, voxel, face);
+            push_occluded_face(
+                builder,
+                pos.as_vec3(),
+                face,
+                uvs.rect(texture),
+                voxel_uv_transform(voxel_type, voxel, face),
+                1.0,
+                [3; 4],
+                0.0,
+            );
+            builder.tiles.extend([texture; 4]);
+        }
+        if voxel_type.double_sided {
+            push_back_faces(builder, from.0, from.1);
+        }
+    }
+}
+
+/// Appends the faces of every visible voxel in a chunk to `out`, with translucent voxels in their
+/// own buffer.
+///
+/// Vertices are in voxel units relative to the chunk's `(0, 0, 0)` voxel, which is centered on
+/// the origin. Faces are culled as described by [`is_face_hidden`], except where a liquid steps
+/// down to a lowered surface, including across the chunk border when the adjacent chunk is
+/// loaded; border faces next to an unloaded chunk are emitted.
+/// Overlays are appended as inflated faces in the same mesh, so it is meant to be drawn with an
+/// alpha-masked material. Face UVs address each voxel type's tiles as laid out by `uvs`.
+///
+/// Ambient occlusion of `ao_strength` is baked into vertex colors. The voxels it samples diagonally
+/// across a chunk edge or corner are read from [`ChunkNeighborhood::diagonals`], and count as
+/// open where those aren't loaded.
+pub fn build_chunk_mesh<S: VoxelStorage>(
+    chunks: ChunkNeighborhood<S>,
+    registry: &VoxelRegistry,
+    uvs: UvLayout,
+    ao_strength: f32,
+    out: &mut ChunkMeshBuffers,
+) {
+    let is_opaque = |pos: IVec3| {
+        chunks
+            .get(pos)
+            .and_then(|voxel| registry.get(voxel.id))
+            .is_some_and(|voxel_type| voxel_type.transparency == Transparency::Opaque)
+    };
+
+    for (pos, voxel) in chunks.center.iter() {
+        let Some(voxel_type) = registry.get(voxel.id) else {
+            continue;
+        };
+        let builder = match voxel_type.transparency {
+            Transparency::Invisible => continue,
+            Transparency::Translucent => &mut out.translucent,
+            Transparency::Opaque | Transparency::Cutout => &mut out.opaque,
+        };
+
+        let pos = pos.as_ivec3();
+        if voxel_type.shape != VoxelShape::Cube {
+            push_shaped_voxel(builder, chunks, registry, uvs, pos, voxel, voxel_type);
+            continue;
+        }
+
+        let surface = voxel_type.liquid && is_liquid_surface(registry, chunks, pos, voxel);
+        let voxel_from = (builder.positions.len(), builder.indices.len());
+        for face in Face::ALL {
+            if is_chunk_face_hidden(registry, chunks, pos, voxel, face) {
+                continue;
+            }
+
+            let from = builder.positions.len();
+
+            let levels = ao_levels(pos, face, is_opaque);
+            let texture = voxel_face_texture(voxel_type, voxel, face);
+            let transform = voxel_uv_transform(voxel_type, voxel, face);
+            push_occluded_face(
+                builder,
+                pos.as_vec3(),
+                face,
+                uvs.rect(texture),
+                transform,
+                1.0,
+                levels,
+                ao_strength,
+            );
+            builder.tiles.extend([texture; 4]);
+
+            if let Some(overlay) = voxel_type.overlay {
+                let texture = face_texture(overlay, face);
+                push_occluded_face(
+                    builder,
+                    pos.as_vec3(),
+                    face,
+                    uvs.rect(texture),
+                    transform,
+                    OVERLAY_INFLATE,
+                    levels,
+                    ao_strength,
+                );
+                builder.tiles.extend([texture; 4]);
+            }
+
+            if surface {
+                let top = pos.y as f32 - 0.5 + LIQUID_SURFACE_HEIGHT;
+                lower_liquid_face(builder, from, face, top);
+            }
+        }
+        if voxel_type.double_sided {
+            push_back_faces(builder, voxel_from.0, voxel_from.1);
+        }
+    }
+}
+
+/// Everything that decides how a visible face looks. The greedy mesher only merges faces with
+/// equal keys, so a merged quad never stretches one look over faces meant to differ; anything
+/// new that varies per face belongs here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FaceKey {
+    pub voxel: VoxelId,
+    /// Texture array layer of the face, see [`face_texture`].
+    pub texture: u32,
+    /// How the face's texture is turned, see [`voxel_uv_transform`].
+    pub uv: UvTransform,
+    /// Whether the face is part of a lowered liquid surface.
+    pub surface: bool,
+    /// [`ao_levels`] of the face's corners.
+    pub ao: [u32; 4],
+    /// [`pack_light`](light::pack_light)ed light of the voxel the face is emitted into.
+    pub light: u8,
+}
+
+impl FaceKey {
+    /// Whether the face can grow into a larger quad. Merging faces whose corners are shaded
+    /// differently would lose the shading of all but the quad's outer corners.
+    #[inline]
+    pub fn mergeable(&self) -> bool {
+        self.ao.iter().all(|level| *level == self.ao[0])
+    }
+}
+
+/// Like [`build_chunk_mesh`], but merges visible faces of the same [`FaceKey`] in each slice of
+/// the chunk into as few rectangles as it greedily can.
+///
+/// Merged quads can't address a single atlas tile, so UVs count tiles across each quad and the
+/// tile is stored in [`MeshBuffers::tiles`]; the mesh must be drawn with
+/// [`ChunkMaterial`](crate::material::ChunkMaterial), which wraps UVs inside that tile, to look
+/// the same as the culled mesh. Ambient occlusion of `ao_strength` is baked like the culled mesh
+/// does. Faces only merge where `light`, if given, is the same in front of them, which must then
+/// sample the voxels as given rather than at another level of detail. Voxels that aren't cubes
+/// never merge and are meshed on their own like [`build_chunk_mesh`] does. Quads are at most
+/// [`PackedVertex::MAX_UV`] voxels long on either side.
+pub fn build_greedy_chunk_mesh<S: VoxelStorage>(
+    chunks: ChunkNeighborhood<S>,
+    registry: &VoxelRegistry,
+    ao_strength: f32,
+    light: Option<&VertexLight>,
+    out: &mut ChunkMeshBuffers,
+) {
+    let is_opaque = |pos: IVec3| {
+        chunks
+            .get(pos)
+            .and_then(|voxel| registry.get(voxel.id))
+            .is_some_and(|voxel_type| voxel_type.transparency == Transparency::Opaque)
+    };
+
+    let dims = chunks.center.dims().as_ivec3();
+    for face in Face::ALL {
+        let normal = face.offset();
+        let axis = face.axis();
+        let [u, v] = face.uv_axes();
+        let at = |slice: i32, i: i32, j: i32| {
+            let mut pos = IVec3::ZERO;
+            pos[axis] = slice;
+            pos[u] = i;
+            pos[v] = j;
+            pos
+        };
+
+        let mut mask = vec![None; (dims[u] * dims[v]) as usize];
+        for slice in 0..dims[axis] {
+            mask.iter_mut().enumerate().for_each(|(index, cell)| {
+                let pos = at(slice, index as i32 % dims[u], index as i32 / dims[u]);
+                *cell = chunks
+                    .get(pos)
+                    .filter(|voxel| {
+                        registry.get(voxel.id).is_some_and(|voxel_type| {
+                            voxel_type.transparency != Transparency::Invisible
+                                && voxel_type.shape == VoxelShape::Cube
+                        })
+                    })
+                    .filter(|voxel| !is_chunk_face_hidden(registry, chunks, pos, **voxel, face))
+                    .and_then(|voxel| {
+                        let voxel_type = registry.get(voxel.id)?;
+                        let surface =
+                            voxel_type.liquid && is_liquid_surface(registry, chunks, pos, *voxel);
+                        Some(FaceKey {
+                            voxel: voxel.id,
+                            texture: voxel_face_texture(voxel_type, *voxel, face),
+                            uv: voxel_uv_transform(voxel_type, *voxel, face),
+                            surface,
+                            ao: ao_levels(pos, face, is_opaque),
+                            light: light.map_or(0, |light| light.get(pos + normal)),
+                        })
+                    });
+            });
+
+            for j in 0..dims[v] {
+                let mut i = 0;
+                while i < dims[u] {
+                    let Some(key) = mask[(j * dims[u] + i) as usize] else {
+                        i += 1;
+                        continue;
+                    };
+
+                    let same = |i: i32, j: i32| mask[(j * dims[u] + i) as usize] == Some(key);
+                    // Quads are split where their UVs would no longer fit a packed vertex.
+                    let longest = PackedVertex::MAX_UV as usize;
+                    let (width, height) = if key.mergeable() {
+                        let width = (i..dims[u])
+                            .take_while(|&i| same(i, j))
+                            .take(longest)
+                            .count() as i32;
+                        let height = (j..dims[v])
+                            .take_while(|&j| (i..i + width).all(|i| same(i, j)))
+                            .take(longest)
+                            .count() as i32;
+                        (width, height)
+                    } else {
+                        (1, 1)
+                    };
+
+                    for j in j..j + height {
+                        for i in i..i + width {
+                            mask[(j * dims[u] + i) as usize] = None;
+                        }
+                    }
+
+                    if let Some(voxel_type) = registry.get(key.voxel) {
+                        let builder = if voxel_type.transparency == Transparency::Translucent {
+                            &mut out.translucent
+                        } else {
+                            &mut out.opaque
+                        };
+                        let (min, max) =
+                            (at(slice, i, j), at(slice, i + width - 1, j + height - 1));
+                        let (from, indices_from) = (builder.positions.len(), builder.indices.len());
+                        push_quad(builder, min, max, face, key.texture, key.uv, 0.0);
+                        occlude_quad(builder, from, face, key.ao, ao_strength);
+
+                        if let Some(overlay) = voxel_type.overlay {
+                            let offset = (OVERLAY_INFLATE - 1.0) / 2.0;
+                            let overlay_from = builder.positions.len();
+                            let texture = face_texture(overlay, face);
+                            push_quad(builder, min, max, face, texture, key.uv, offset);
+                            occlude_quad(builder, overlay_from, face, key.ao, ao_strength);
+                        }
+
+                        // Liquid surfaces never stack, so a surface quad is one voxel high.
+                        if key.surface {
+                            let top = max.y as f32 - 0.5 + LIQUID_SURFACE_HEIGHT;
+                            lower_liquid_face(builder, from, face, top);
+                        }
+                        if voxel_type.double_sided {
+                            push_back_faces(builder, from, indices_from);
+                        }
+                    }
+
+                    i += width;
+                }
+            }
+        }
+    }
+
+    for (pos, voxel) in chunks.center.iter() {
+        let Some(voxel_type) = registry
+            .get(voxel.id)
+            .filter(|voxel_type| voxel_type.shape != VoxelShape::Cube)
+        else {
+            continue;
+        };
+        let builder = match voxel_type.transparency {
+            Transparency::Invisible => continue,
+            Transparency::Translucent => &mut out.translucent,
+            Transparency::Opaque | Transparency::Cutout => &mut out.opaque,
+        };
+        let pos = pos.as_ivec3();
+        push_shaped_voxel(
+            builder,
+            chunks,
+            registry,
+            UvLayout::Array,
+            pos,
+            voxel,
+            voxel_type,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chunk::{Chunk, ChunkData},
+        generation,
+        light::{pack_light, ChunkLight},
+    };
+    use bevy::utils::{HashMap, HashSet};
+
+    fn context<'a>(registry: &'a VoxelRegistry, uvs: UvLayout<'a>) -> MeshContext<'a> {
+        MeshContext {
+            registry,
+            uvs,
+            ao_strength: 1.0,
+            face_shading: FaceShading::default(),
+            skirt_depth: None,
+            lod: 1,
+            neighbor_lods: [1; 6],
+            winding: WindingOrder::default(),
+            light: None,
+        }
+    }
+
+    /// `center` with no neighbors loaded.
+    fn alone(center: &ChunkStorage) -> ChunkNeighborhood<'_> {
+        ChunkNeighborhood {
+            center,
+            neighbors: [None; 6],
+            diagonals: [None; 20],
+        }
+    }
+
+    fn index_count(mesh: &Mesh) -> usize {
+        mesh.indices().map_or(0, Indices::len)
+    }
+
+    /// A cubic chunk of `size` with stone wherever `filled` says.
+    fn filled(registry: &VoxelRegistry, size: u32, filled: impl Fn(UVec3) -> bool) -> ChunkStorage {
+        let stone = Voxel::new(registry.id("stone").unwrap());
+        let mut data = ChunkData::cubic(size);
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    if filled(UVec3::new(x, y, z)) {
+                        data.set(x as usize, y as usize, z as usize, stone);
+                    }
+                }
+            }
+        }
+        data.into()
+    }
+
+    /// Meshes `chunks` into atlas vertices with `strategy`.
+    fn mesh_atlas(strategy: MeshingStrategy, chunks: ChunkNeighborhood) -> ChunkMeshes {
+        let registry = VoxelRegistry::default();
+        let atlas = AtlasLayout::default();
+        let context = context(&registry, UvLayout::Atlas(&atlas));
+        mesh_chunk(&strategy, chunks, &context, &mut MeshBufferPool::default())
+    }
+
+    #[test]
+    fn generated_terrain_meshes_without_a_renderer() {
+        let registry = VoxelRegistry::default();
+        let atlas = AtlasLayout::default();
+        let mut data = ChunkData::cubic(16);
+        generation::generate_chunk(&mut data, IVec3::ZERO, &registry);
+        let chunk = data.into();
+
+        let context = context(&registry, UvLayout::Atlas(&atlas));
+        let mut pool = MeshBufferPool::default();
+        let meshes = mesh_chunk(&MeshingStrategy::Culled, alone(&chunk), &context, &mut pool);
+
+        assert!(meshes.opaque.count_vertices() > 0);
+        assert!(index_count(&meshes.opaque) > 0);
+        validate_mesh(&meshes.opaque).unwrap();
+        validate_mesh(&meshes.translucent).unwrap();
+    }
+
+    #[test]
+    fn single_voxel_meshes_to_one_cube() {
+        let registry = VoxelRegistry::default();
+        let chunk = filled(&registry, 3, |pos| pos == UVec3::ONE);
+        let meshes = mesh_atlas(MeshingStrategy::Culled, alone(&chunk));
+
+        assert_eq!(meshes.opaque.count_vertices(), 24);
+        assert_eq!(index_count(&meshes.opaque), 36);
+        assert_eq!(meshes.translucent.count_vertices(), 0);
+    }
+
+    /// Vertices of `mesh` whose normal is `normal`.
+    fn vertices_facing(mesh: &Mesh, normal: Vec3) -> usize {
+        match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(normals)) => {
+                normals.iter().filter(|n| Vec3::from(**n) == normal).count()
+            }
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn faces_between_solid_chunks_are_culled() {
+        let registry = VoxelRegistry::default();
+        let wall = filled(&registry, 4, |_| true);
+        let mut left = alone(&wall);
+        left.neighbors[Face::PosX.index()] = Some(&wall);
+        let mut right = alone(&wall);
+        right.neighbors[Face::NegX.index()] = Some(&wall);
+
+        let left = mesh_atlas(MeshingStrategy::Culled, left).opaque;
+        let right = mesh_atlas(MeshingStrategy::Culled, right).opaque;
+        assert_eq!(vertices_facing(&left, Vec3::X), 0);
+        assert_eq!(vertices_facing(&right, Vec3::NEG_X), 0);
+        // The outer faces of the wall are still there, against chunks that aren't loaded.
+        assert_eq!(vertices_facing(&left, Vec3::NEG_X), 4 * 16);
+        assert_eq!(vertices_facing(&right, Vec3::X), 4 * 16);
+    }
+
+    /// Total area of the triangles in `buffers` facing `face`.
+    fn area_facing(buffers: &MeshBuffers, face: Face) -> f32 {
+        let position = |i: u32| Vec3::from(buffers.positions[i as usize]);
+        buffers
+            .indices
+            .chunks_exact(3)
+            .filter(|tri| Vec3::from(buffers.normals[tri[0] as usize]) == face.normal())
+            .map(|tri| {
+                let [a, b, c] = [tri[0], tri[1], tri[2]].map(position);
+                (b - a).cross(c - a).length() / 2.0
+            })
+            .sum()
+    }
+
+    /// Culled and greedy buffers of `chunk` meshed alone.
+    fn culled_and_greedy(chunk: &ChunkStorage) -> [ChunkMeshBuffers; 2] {
+        let registry = VoxelRegistry::default();
+        let mut culled = ChunkMeshBuffers::default();
+        build_chunk_mesh(alone(chunk), &registry, UvLayout::Array, 1.0, &mut culled);
+        let mut greedy = ChunkMeshBuffers::default();
+        build_greedy_chunk_mesh(alone(chunk), &registry, 1.0, None, &mut greedy);
+        [culled, greedy]
+    }
+
+    #[test]
+    fn greedy_merges_a_flat_layer_into_one_quad() {
+        let registry = VoxelRegistry::default();
+        let layer = filled(&registry, 16, |pos| pos.y == 0);
+        let [culled, greedy] = culled_and_greedy(&layer);
+
+        let top = |buffers: &MeshBuffers| {
+            let normals = buffers.normals.iter().map(|n| Vec3::from(*n));
+            normals.filter(|n| *n == Vec3::Y).count() / 4
+        };
+        assert_eq!(top(&culled.opaque), 256);
+        assert_eq!(top(&greedy.opaque), 1);
+    }
+
+    #[test]
+    fn greedy_and_culled_cover_the_same_area() {
+        let registry = VoxelRegistry::default();
+        // Overlays are inflated differently by the two meshers, so these are plain stone.
+        let hills = filled(&registry, 16, |pos| pos.y <= (pos.x * 3 + pos.z * 5) % 7);
+        let checkerboard = filled(&registry, 8, |pos| (pos.x + pos.y + pos.z) % 2 == 0);
+
+        for chunk in [hills, checkerboard] {
+            let [culled, greedy] = culled_and_greedy(&chunk);
+            for face in Face::ALL {
+                let opaque = area_facing(&culled.opaque, face);
+                assert!(opaque > 0.0);
+                assert_eq!(opaque, area_facing(&greedy.opaque, face), "{face:?}");
+                let translucent = area_facing(&culled.translucent, face);
+                assert_eq!(
+                    translucent,
+                    area_facing(&greedy.translucent, face),
+                    "{face:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn untouched_chunk_meshes_to_nothing() {
+        let chunk = Chunk::new(Vec3::ZERO);
+        for strategy in MeshingStrategy::ALL {
+            let meshes = mesh_atlas(strategy, alone(chunk.data()));
+            for mesh in [meshes.opaque, meshes.translucent] {
+                assert_eq!(mesh.count_vertices(), 0, "{}", strategy.name());
+                assert_eq!(index_count(&mesh), 0, "{}", strategy.name());
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_with_a_hole_meshes_every_solid_voxel() {
+        let registry = VoxelRegistry::default();
+        let hollow = filled(&registry, 3, |pos| pos != UVec3::ONE);
+        assert_eq!(hollow.iter().count(), 26);
+
+        let naive = mesh_atlas(MeshingStrategy::Naive, alone(&hollow)).opaque;
+        assert_eq!(naive.count_vertices(), 26 * 6 * 4);
+        // The outside of the cube plus the six faces around the hole.
+        let culled = mesh_atlas(MeshingStrategy::Culled, alone(&hollow)).opaque;
+        assert_eq!(culled.count_vertices(), (9 * 6 + 6) * 4);
+    }
+
+    #[test]
+    fn inside_corner_occludes_its_vertices() {
+        // A floor voxel against walls running along x and z, one voxel up.
+        let is_solid = |pos: IVec3| pos.y == 1 && (pos.x == 0 || pos.z == 0);
+        let levels = ao_levels(IVec3::new(1, 0, 1), Face::PosY, is_solid);
+
+        let corners = Face::PosY.corners(Vec3::ZERO, 1.0);
+        for (corner, level) in corners.into_iter().zip(levels) {
+            let expected = match (corner.x < 0.0, corner.z < 0.0) {
+                // Both walls, which always fully occlude.
+                (true, true) => 0,
+                // One wall and the corner voxel inside it.
+                (true, false) | (false, true) => 1,
+                (false, false) => 3,
+            };
+            assert_eq!(level, expected, "{corner}");
+        }
+        assert_eq!(ao_levels(IVec3::new(1, 0, 1), Face::NegY, is_solid), [3; 4]);
+    }
+
+    #[test]
+    fn packed_vertices_round_trip() {
+        const ZERO: PackedVertex = PackedVertex {
+            dir: 0,
+            ao: 0,
+            uv: UVec2::ZERO,
+            layer: 0,
+            scroll: false,
+        };
+        for dir in 0..Face::ALL.len() as u32 {
+            for ao in 0..4 {
+                for x in 0..=PackedVertex::MAX_UV {
+                    for y in 0..=PackedVertex::MAX_UV {
+                        for scroll in [false, true] {
+                            for layer in [0, 1, 255, 256, u16::MAX as u32] {
+                                let uv = UVec2::new(x, y);
+                                let vertex = PackedVertex {
+                                    dir,
+                                    ao,
+                                    uv,
+                                    layer,
+                                    scroll,
+                                };
+                                assert_eq!(PackedVertex::unpack(vertex.pack()), vertex);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Walking a one through every bit of every field sets that bit alone, so no field
+        // bleeds into another, and together the fields use the whole word.
+        // Each field's width in bits, and a vertex with only that field set.
+        type Field = (u32, fn(u32) -> PackedVertex);
+        let fields: [Field; 6] = [
+            (3, |dir| PackedVertex { dir, ..ZERO }),
+            (2, |ao| PackedVertex { ao, ..ZERO }),
+            (5, |x| PackedVertex {
+                uv: UVec2::new(x, 0),
+                ..ZERO
+            }),
+            (5, |y| PackedVertex {
+                uv: UVec2::new(0, y),
+                ..ZERO
+            }),
+            (16, |layer| PackedVertex { layer, ..ZERO }),
+            (1, |scroll| PackedVertex {
+                scroll: scroll != 0,
+                ..ZERO
+            }),
+        ];
+        let mut used = 0;
+        for (bits, field) in fields {
+            for bit in 0..bits {
+                let vertex = field(1 << bit);
+                let packed = vertex.pack();
+                assert_eq!(packed.count_ones(), 1, "{vertex:?}");
+                assert_eq!(used & packed, 0, "{vertex:?} overlaps another field");
+                assert_eq!(PackedVertex::unpack(packed), vertex);
+                used |= packed;
+            }
+        }
+        assert_eq!(used, u32::MAX);
+    }
+
+    #[test]
+    fn greedy_quads_are_split_to_fit_packed_uvs() {
+        let registry = VoxelRegistry::default();
+        let layer = filled(&registry, 64, |pos| pos.y == 0);
+        let mut out = ChunkMeshBuffers::default();
+        build_greedy_chunk_mesh(alone(&layer), &registry, 1.0, None, &mut out);
+
+        // 64 voxels split into 31 + 31 + 2 along each axis, on top, bottom and four sides.
+        let top = out
+            .opaque
+            .normals
+            .iter()
+            .filter(|n| Vec3::from(**n) == Vec3::Y);
+        assert_eq!(top.count() / 4, 9);
+        let max = PackedVertex::MAX_UV as f32;
+        assert!(out.opaque.uvs.iter().flatten().all(|uv| *uv <= max));
+        // Packing would panic in debug builds on UVs that don't fit.
+        let mesh = out.opaque.build_packed();
+        assert_eq!(mesh.count_vertices(), (9 * 2 + 4 * 3) * 4);
+    }
+
+    /// Buffers of `vertices` vertices in a fan of triangles reaching the last one.
+    fn fan(vertices: usize) -> MeshBuffers {
+        let last = vertices as u32 - 1;
+        MeshBuffers {
+            positions: vec![[0.0; 3]; vertices],
+            uvs: vec![[0.0; 2]; vertices],
+            normals: vec![[0.0, 1.0, 0.0]; vertices],
+            indices: vec![0, last - 1, last],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn indices_narrow_to_u16_up_to_the_boundary() {
+        let at_limit = fan(MeshBuffers::MAX_U16_VERTICES).build();
+        match at_limit.indices() {
+            Some(Indices::U16(indices)) => assert_eq!(indices, &[0, u16::MAX - 1, u16::MAX]),
+            indices => panic!("expected u16 indices, got {indices:?}"),
+        }
+
+        let past_limit = fan(MeshBuffers::MAX_U16_VERTICES + 1).build_packed();
+        match past_limit.indices() {
+            Some(Indices::U32(indices)) => assert_eq!(indices, &[0, 65535, 65536]),
+            indices => panic!("expected u32 indices, got {indices:?}"),
+        }
+    }
+
+    #[test]
+    fn checkerboard_indices_widen_only_past_the_boundary() {
+        let registry = VoxelRegistry::default();
+        for (size, wide) in [(16, false), (32, true)] {
+            let checkerboard = filled(&registry, size, |pos| (pos.x + pos.y + pos.z) % 2 == 0);
+            let mesh = mesh_atlas(MeshingStrategy::Culled, alone(&checkerboard)).opaque;
+            assert_eq!(mesh.count_vertices(), size.pow(3) as usize / 2 * 24);
+            assert_eq!(
+                matches!(mesh.indices(), Some(Indices::U32(_))),
+                wide,
+                "{size}"
+            );
+        }
+    }
+
+    #[test]
+    fn generated_cube_has_the_vertices_of_the_hand_written_one() {
+        // The tables `generate_cube` was written out as, with positions doubled, by normal.
+        let faces = [
+            ([0, 1, 0], [[-1, 1, -1], [1, 1, -1], [1, 1, 1], [-1, 1, 1]]),
+            (
+                [0, -1, 0],
+                [[-1, -1, -1], [1, -1, -1], [1, -1, 1], [-1, -1, 1]],
+            ),
+            ([1, 0, 0], [[1, -1, -1], [1, -1, 1], [1, 1, 1], [1, 1, -1]]),
+            (
+                [-1, 0, 0],
+                [[-1, -1, -1], [-1, -1, 1], [-1, 1, 1], [-1, 1, -1]],
+            ),
+            ([0, 0, 1], [[-1, -1, 1], [-1, 1, 1], [1, 1, 1], [1, -1, 1]]),
+            (
+                [0, 0, -1],
+                [[-1, -1, -1], [-1, 1, -1], [1, 1, -1], [1, -1, -1]],
+            ),
+        ];
+        let mut expected: Vec<_> = faces
+            .iter()
+            .flat_map(|(normal, corners)| corners.map(|corner| (corner, *normal)))
+            .collect();
+        expected.sort();
+
+        let cube = generate_cube(FaceTextures::all(0), &AtlasLayout::default());
+        let (
+            Some(VertexAttributeValues::Float32x3(positions)),
+            Some(VertexAttributeValues::Float32x3(normals)),
+        ) = (
+            cube.attribute(Mesh::ATTRIBUTE_POSITION),
+            cube.attribute(Mesh::ATTRIBUTE_NORMAL),
+        )
+        else {
+            panic!("cube is missing positions or normals");
+        };
+        let mut actual: Vec<_> = positions
+            .iter()
+            .zip(normals)
+            .map(|(position, normal)| {
+                let position = (Vec3::from(*position) * 2.0).round().as_ivec3();
+                (
+                    position.to_array(),
+                    Vec3::from(*normal).as_ivec3().to_array(),
+                )
+            })
+            .collect();
+        actual.sort();
+
+        assert_eq!(actual, expected);
+        assert_eq!(index_count(&cube), 36);
+        assert!(cube_normals_match(&cube));
+    }
+
+    #[test]
+    fn pooled_buffers_keep_their_capacity() {
+        let registry = VoxelRegistry::default();
+        let atlas = AtlasLayout::default();
+        let chunk = filled(&registry, 16, |pos| (pos.x + pos.y + pos.z) % 2 == 0);
+        let mut pool = MeshBufferPool::default();
+
+        // Allocation and capacity of the positions and indices of both pooled buffers, sorted so
+        // it doesn't matter which pass got which buffer.
+        let allocations = |pool: &mut MeshBufferPool| {
+            let buffers = [pool.take(), pool.take()];
+            assert!(buffers.iter().all(|buffers| buffers.positions.is_empty()));
+            let mut allocations = buffers.each_ref().map(|buffers| {
+                let (positions, indices) = (&buffers.positions, &buffers.indices);
+                (
+                    positions.as_ptr(),
+                    positions.capacity(),
+                    indices.as_ptr(),
+                    indices.capacity(),
+                )
+            });
+            allocations.sort();
+            buffers.into_iter().for_each(|buffers| pool.give(buffers));
+            allocations
+        };
+
+        for uvs in [UvLayout::Atlas(&atlas), UvLayout::Array] {
+            let context = context(&registry, uvs);
+            mesh_chunk(&MeshingStrategy::Culled, alone(&chunk), &context, &mut pool);
+            let grown = allocations(&mut pool);
+            assert!(grown.iter().any(|(_, capacity, ..)| *capacity >= 2048 * 24));
+
+            // Remeshing the same chunk writes into the same allocations without growing them.
+            for _ in 0..3 {
+                mesh_chunk(&MeshingStrategy::Culled, alone(&chunk), &context, &mut pool);
+                assert_eq!(allocations(&mut pool), grown);
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_are_unit_and_orthogonal_to_their_normal() {
+        let mut buffers = MeshBuffers::default();
+        let uv_rect = Rect::new(0.0, 0.0, 1.0, 1.0);
+        for face in Face::ALL {
+            for transform in UvTransform::ALL {
+                push_occluded_face(
+                    &mut buffers,
+                    Vec3::ZERO,
+                    face,
+                    uv_rect,
+                    transform,
+                    1.0,
+                    [3; 4],
+                    0.0,
+                );
+            }
+        }
+
+        assert_eq!(buffers.tangents.len(), buffers.normals.len());
+        for (tangent, normal) in buffers.tangents.iter().zip(&buffers.normals) {
+            let (tangent, normal) = (bevy::math::Vec4::from(*tangent), Vec3::from(*normal));
+            assert!(
+                (tangent.truncate().length() - 1.0).abs() < 1e-5,
+                "{tangent}"
+            );
+            assert!(
+                tangent.truncate().dot(normal).abs() < 1e-5,
+                "{tangent} on {normal}"
+            );
+            assert_eq!(tangent.w.abs(), 1.0, "{tangent}");
+        }
+    }
+
+    #[test]
+    fn packed_meshes_carry_one_light_per_vertex() {
+        let registry = VoxelRegistry::default();
+        let chunk = filled(&registry, 4, |pos| pos == UVec3::ONE);
+        let block_light = ChunkLight::new(chunk.dims());
+        let light = VertexLight::new(alone(&chunk), &registry, Some(&block_light), [None; 6]);
+        let context = MeshContext {
+            light: Some(&light),
+            ..context(&registry, UvLayout::Array)
+        };
+        let meshes = mesh_chunk(
+            &MeshingStrategy::Culled,
+            alone(&chunk),
+            &context,
+            &mut MeshBufferPool::default(),
+        );
+
+        let Some(VertexAttributeValues::Uint32(levels)) = meshes.opaque.attribute(ATTRIBUTE_LIGHT)
+        else {
+            panic!("packed mesh has no light attribute");
+        };
+        assert_eq!(levels.len(), meshes.opaque.count_vertices());
+        // Only the bottom face looks into the voxel's own column, under its surface.
+        let underground = pack_light(light::UNDERGROUND_SKY_LIGHT, 0) as u32;
+        let open_sky = pack_light(light::MAX_LIGHT, 0) as u32;
+        assert_eq!(levels.iter().filter(|l| **l == underground).count(), 4);
+        assert_eq!(levels.iter().filter(|l| **l == open_sky).count(), 20);
+    }
+
+    #[test]
+    fn flat_normals_recompute_for_both_index_widths() {
+        let cube = generate_cube(FaceTextures::all(0), &AtlasLayout::default());
+        let Some(Indices::U16(narrow)) = cube.indices() else {
+            panic!("cube should have u16 indices");
+        };
+        let mut wide = cube.clone();
+        wide.insert_indices(Indices::U32(narrow.iter().map(|i| *i as u32).collect()));
+
+        for mut mesh in [cube.clone(), wide] {
+            mesh.insert_attribute(
+                Mesh::ATTRIBUTE_NORMAL,
+                vec![[0.0; 3]; mesh.count_vertices()],
+            );
+            assert!(recompute_flat_normals(&mut mesh));
+            assert_eq!(
+                mesh.attribute(Mesh::ATTRIBUTE_NORMAL).unwrap().as_float3(),
+                cube.attribute(Mesh::ATTRIBUTE_NORMAL).unwrap().as_float3(),
+            );
+        }
+
+        let mut lines = selection_box_mesh();
+        assert!(!recompute_flat_normals(&mut lines));
+
+        // Three faces of a cube corner, all sharing its vertex, split so each stays flat.
+        let corner = [[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        for indices in [
+            Indices::U16(vec![0, 2, 1, 0, 1, 3, 0, 3, 2]),
+            Indices::U32(vec![0, 2, 1, 0, 1, 3, 0, 3, 2]),
+        ] {
+            let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all())
+                .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, corner.to_vec())
+                .with_inserted_indices(indices);
+            assert!(recompute_flat_normals(&mut mesh));
+            assert!(mesh.indices().is_none());
+            let expected: Vec<_> = [Vec3::NEG_Z, Vec3::NEG_Y, Vec3::NEG_X]
+                .into_iter()
+                .flat_map(|normal| [normal.to_array(); 3])
+                .collect();
+            let normals = mesh.attribute(Mesh::ATTRIBUTE_NORMAL).unwrap().as_float3();
+            assert_eq!(normals, Some(&expected[..]));
+        }
+    }
+
+    #[test]
+    fn greedy_never_merges_across_materials() {
+        let registry = VoxelRegistry::default();
+        let [stone, dirt] = ["stone", "dirt"].map(|name| Voxel::new(registry.id(name).unwrap()));
+        let layer = |material: fn(u32, u32) -> bool| -> ChunkStorage {
+            let mut data = ChunkData::cubic(16);
+            for z in 0..16 {
+                for x in 0..16 {
+                    let voxel = if material(x, z) { stone } else { dirt };
+                    data.set(x as usize, 0, z as usize, voxel);
+                }
+            }
+            data.into()
+        };
+        let tops = |chunk: &ChunkStorage| {
+            let [_, greedy] = culled_and_greedy(chunk);
+            let normals = greedy.opaque.normals.iter().map(|n| Vec3::from(*n));
+            normals.filter(|n| *n == Vec3::Y).count() / 4
+        };
+
+        assert_eq!(tops(&layer(|x, z| (x + z) % 2 == 0)), 256);
+        assert_eq!(tops(&layer(|x, _| x < 8)), 2);
+    }
+
+    #[test]
+    fn chunk_aabb_covers_every_meshed_vertex() {
+        let registry = VoxelRegistry::default();
+        let mut terrain = ChunkData::cubic(16);
+        generation::generate_chunk(&mut terrain, IVec3::ZERO, &registry);
+        let full = filled(&registry, 16, |_| true);
+
+        let aabb = chunk_aabb(UVec3::splat(16));
+        let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+        for chunk in [terrain.into(), full] {
+            for meshed in culled_and_greedy(&chunk) {
+                for position in meshed
+                    .opaque
+                    .positions
+                    .iter()
+                    .chain(&meshed.translucent.positions)
+                {
+                    let position = Vec3::from(*position);
+                    assert!(
+                        position.cmpge(min).all() && position.cmple(max).all(),
+                        "{position}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn selection_box_outlines_the_twelve_edges_of_a_voxel() {
+        let mesh = selection_box_mesh();
+        assert_eq!(mesh.primitive_topology(), PrimitiveTopology::LineList);
+        let corners = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .unwrap()
+            .as_float3()
+            .unwrap();
+        let Some(Indices::U16(indices)) = mesh.indices() else {
+            panic!("selection box should have u16 indices");
+        };
+        assert_eq!(corners.len(), 8);
+        assert_eq!(indices.len(), 12 * 2);
+
+        let side = Voxel::SIZE * SELECTION_INFLATE;
+        let mut edges = HashSet::new();
+        for edge in indices.chunks_exact(2) {
+            let [a, b] = [edge[0], edge[1]].map(|i| Vec3::from(corners[i as usize]));
+            let along = (b - a).abs();
+            assert_eq!(
+                along.cmpgt(Vec3::ZERO).bitmask().count_ones(),
+                1,
+                "{a} to {b}"
+            );
+            assert!((along.max_element() - side).abs() < 1e-5);
+            assert!(edges.insert((edge[0].min(edge[1]), edge[0].max(edge[1]))));
+        }
+        assert!(corners
+            .iter()
+            .all(|c| c.iter().all(|c| (c.abs() - side / 2.0).abs() < 1e-5)));
+    }
+
+    /// A snapshot fixture: a chunk, with the chunk across its +x face if the fixture needs one.
+    struct Fixture {
+        name: &'static str,
+        center: ChunkStorage,
+        pos_x: Option<ChunkStorage>,
+    }
+
+    fn snapshot_fixtures(registry: &VoxelRegistry) -> [Fixture; 6] {
+        let fixture = |name, center| Fixture {
+            name,
+            center,
+            pos_x: None,
+        };
+        [
+            fixture("single", filled(registry, 4, |pos| pos == UVec3::ONE)),
+            fixture("full", filled(registry, 4, |_| true)),
+            fixture("layer", filled(registry, 8, |pos| pos.y == 0)),
+            fixture(
+                "checkerboard",
+                filled(registry, 4, |pos| (pos.x + pos.y + pos.z) % 2 == 0),
+            ),
+            fixture(
+                "hills",
+                filled(registry, 8, |pos| pos.y <= (pos.x * 3 + pos.z * 5) % 7),
+            ),
+            // A run along x to the border, turning up in the next chunk.
+            Fixture {
+                name: "l-shape",
+                center: filled(registry, 4, |pos| pos.x >= 1 && pos.y == 1 && pos.z == 1),
+                pos_x: Some(filled(registry, 4, |pos| {
+                    pos.x == 0 && (1..=2).contains(&pos.y) && pos.z == 1
+                })),
+            },
+        ]
+    }
+
+    /// Where snapshots are kept, one file per mesher and fixture.
+    const SNAPSHOTS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots");
+
+    /// `mesh`'s positions and triangles, a line each, so a snapshot diff shows what moved.
+    fn snapshot(mesh: &Mesh) -> String {
+        use std::fmt::Write;
+
+        let mut text = String::new();
+        if let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        {
+            for [x, y, z] in positions {
+                writeln!(text, "v {x} {y} {z}").unwrap();
+            }
+        }
+        let indices: Vec<_> = mesh.indices().into_iter().flat_map(Indices::iter).collect();
+        for triangle in indices.chunks(3) {
+            writeln!(text, "f {} {} {}", triangle[0], triangle[1], triangle[2]).unwrap();
+        }
+        text
+    }
+
+    /// Meshes every fixture of [`snapshot_fixtures`] with each mesher, and compares the opaque
+    /// pass against its file in [`SNAPSHOTS`]. Set `UPDATE_SNAPSHOTS=1` to rewrite the files
+    /// after a mesher change that is meant to move geometry.
+    #[test]
+    fn meshers_match_their_snapshots() {
+        let registry = VoxelRegistry::default();
+        let context = context(&registry, UvLayout::Array);
+        let meshers: [&dyn ChunkMesher; 4] = [
+            &MeshingStrategy::Naive,
+            &MeshingStrategy::Culled,
+            &MeshingStrategy::Greedy,
+            &crate::surface_nets::SurfaceNets,
+        ];
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        if update {
+            std::fs::create_dir_all(SNAPSHOTS).unwrap();
+        }
+
+        let mut moved = Vec::new();
+        for mesher in meshers {
+            for fixture in snapshot_fixtures(&registry) {
+                let mut chunks = alone(&fixture.center);
+                chunks.neighbors[Face::PosX as usize] = fixture.pos_x.as_ref();
+                let mut pool = MeshBufferPool::default();
+                let text = snapshot(&mesh_chunk(mesher, chunks, &context, &mut pool).opaque);
+
+                // Meshing is deterministic, storage backing included.
+                let sparse =
+                    ChunkStorage::Sparse(SparseChunk::from_dense(&fixture.center.to_dense()));
+                let again = mesh_chunk(
+                    mesher,
+                    ChunkNeighborhood {
+                        center: &sparse,
+                        ..chunks
+                    },
+                    &context,
+                    &mut pool,
+                );
+                assert_eq!(
+                    text,
+                    snapshot(&again.opaque),
+                    "{} {}",
+                    mesher.name(),
+                    fixture.name
+                );
+
+                let file = format!("{}-{}.txt", mesher.name().replace(' ', "-"), fixture.name);
+                let path = std::path::Path::new(SNAPSHOTS).join(&file);
+                if update {
+                    std::fs::write(&path, &text).unwrap();
+                } else if std::fs::read_to_string(&path).ok().as_ref() != Some(&text) {
+                    moved.push(file);
+                }
+            }
+        }
+
+        assert!(
+            moved.is_empty(),
+            "meshes differ from their snapshots in {SNAPSHOTS}: {moved:?}; \
+             rerun with UPDATE_SNAPSHOTS=1 if the change is intended"
+        );
+    }
+
+    /// A chunk of `voxels` meshed culled on its own.
+    fn culled_fixture(voxels: &[(UVec3, Voxel)]) -> ChunkMeshBuffers {
+        let mut data = ChunkData::cubic(4);
+        for (pos, voxel) in voxels {
+            data.set(pos.x as usize, pos.y as usize, pos.z as usize, *voxel);
+        }
+        let [culled, _] = culled_and_greedy(&data.into());
+        culled
+    }
+
+    #[test]
+    fn slabs_and_stairs_mesh_their_outline() {
+        let registry = VoxelRegistry::default();
+        let [slab, stairs] =
+            ["stone_slab", "stone_stairs"].map(|name| Voxel::new(registry.id(name).unwrap()));
+        let areas = |voxel| {
+            let culled = culled_fixture(&[(UVec3::ONE, voxel)]);
+            Face::ALL.map(|face| area_facing(&culled.opaque, face))
+        };
+
+        // In face order: up, down, +x, -x, +z, -z.
+        assert_eq!(areas(slab), [1.0, 1.0, 0.5, 0.5, 0.5, 0.5]);
+        assert_eq!(areas(stairs), [1.0, 1.0, 0.75, 0.75, 1.0, 1.0]);
+        let hanging = areas(slab.with_orientation(Orientation::NegY));
+        assert_eq!(hanging, areas(slab));
+    }
+
+    #[test]
+    fn slabs_only_hide_the_faces_they_cover() {
+        let registry = VoxelRegistry::default();
+        let stone = Voxel::new(registry.id("stone").unwrap());
+        let slab = Voxel::new(registry.id("stone_slab").unwrap());
+        let area =
+            |voxels: &[(UVec3, Voxel)], face| area_facing(&culled_fixture(voxels).opaque, face);
+
+        // Stone resting on a bottom slab keeps its bottom face, and the slab its top.
+        let on_slab = [(UVec3::ONE, slab), (UVec3::new(1, 2, 1), stone)];
+        assert_eq!(area(&on_slab, Face::NegY), 1.0 + 1.0);
+        assert_eq!(area(&on_slab, Face::PosY), 1.0 + 1.0);
+        // The slab's bottom covers the top of the stone under it.
+        let under_slab = [(UVec3::ONE, slab), (UVec3::new(1, 0, 1), stone)];
+        assert_eq!(area(&under_slab, Face::PosY), 1.0);
+        assert_eq!(area(&under_slab, Face::NegY), 1.0);
+    }
+
+    #[test]
+    fn occlusion_matches_across_chunk_seams() {
+        let registry = VoxelRegistry::default();
+        let stone = Voxel::new(registry.id("stone").unwrap());
+        let solid = |pos: IVec3| pos.y <= (pos.x * 3 + pos.z * 5) % 7 + (pos.x + pos.z) / 8;
+        let mut whole = ChunkData::new(UVec3::new(32, 16, 32));
+        let quarters: HashMap<IVec3, ChunkStorage> = (0..4)
+            .map(|i| {
+                let offset = IVec3::new(i & 1, 0, i >> 1);
+                let mut data = ChunkData::cubic(16);
+                for (x, y, z) in
+                    (0..16).flat_map(|z| (0..16).flat_map(move |y| (0..16).map(move |x| (x, y, z))))
+                {
+                    let world = offset * 16 + IVec3::new(x, y, z);
+                    if solid(world) {
+                        data.set(x as usize, y as usize, z as usize, stone);
+                        let [x, y, z] = world.to_array().map(|c| c as usize);
+                        whole.set(x, y, z, stone);
+                    }
+                }
+                (offset, data.into())
+            })
+            .collect();
+
+        // Every vertex by its doubled world position and normal, with its occlusion level.
+        let vertices = |offset: IVec3, chunks: ChunkNeighborhood| {
+            let mut out = ChunkMeshBuffers::default();
+            build_chunk_mesh(chunks, &registry, UvLayout::Array, 1.0, &mut out);
+            let buffers = out.opaque;
+            (0..buffers.positions.len())
+                .map(|i| {
+                    let position = Vec3::from(buffers.positions[i]) + (offset * 16).as_vec3();
+                    let normal = Vec3::from(buffers.normals[i]);
+                    let key = (position * 2.0).round().as_ivec3().to_array();
+                    (key, normal.as_ivec3().to_array(), buffers.ao[i])
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let whole = ChunkStorage::from(whole);
+        let mut expected = vertices(IVec3::ZERO, alone(&whole));
+        let mut seamed = Vec::new();
+        for (offset, center) in &quarters {
+            let chunks = ChunkNeighborhood {
+                center,
+                neighbors: Face::ALL.map(|face| quarters.get(&(*offset + face.offset()))),
+                diagonals: DIAGONAL_OFFSETS.map(|diagonal| quarters.get(&(*offset + diagonal))),
+            };
+            seamed.extend(vertices(*offset, chunks));
+        }
+
+        expected.sort();
+        seamed.sort();
+        assert!(seamed.iter().any(|(_, _, ao)| *ao < 3));
+        assert_eq!(seamed, expected);
+    }
+
+    #[test]
+    fn pools_only_mesh_their_surface() {
+        let registry = VoxelRegistry::default();
+        let [stone, water] = ["stone", "water"].map(|name| Voxel::new(registry.id(name).unwrap()));
+        // A 4 by 4 pool two deep, in a stone basin one voxel higher than the water.
+        let mut data = ChunkData::cubic(8);
+        for z in 1..7 {
+            for y in 0..4 {
+                for x in 1..7 {
+                    let inside = (2..6).contains(&x) && (2..6).contains(&z) && y > 0;
+                    let voxel = match (inside, y) {
+                        (true, 3) => continue,
+                        (true, _) => water,
+                        _ => stone,
+                    };
+                    data.set(x, y, z, voxel);
+                }
+            }
+        }
+
+        for meshed in culled_and_greedy(&data.into()) {
+            let translucent = &meshed.translucent;
+            assert!(translucent.positions.len() / 4 <= 16);
+            for face in Face::ALL {
+                let expected = if face == Face::PosY { 16.0 } else { 0.0 };
+                assert_eq!(area_facing(translucent, face), expected, "{face:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn packed_vertices_are_a_fraction_of_unpacked_ones() {
+        let registry = VoxelRegistry::default();
+        let atlas = AtlasLayout::default();
+        let chunk = filled(&registry, 16, |pos| pos.y <= (pos.x * 3 + pos.z * 5) % 7);
+        let vertex_size = |uvs| {
+            let meshes = mesh_chunk(
+                &MeshingStrategy::Culled,
+                alone(&chunk),
+                &context(&registry, uvs),
+                &mut MeshBufferPool::default(),
+            );
+            meshes.opaque.get_vertex_size()
+        };
+
+        // Position, UV, normal, tangent, tile and color, against position and packed.
+        assert_eq!(
+            vertex_size(UvLayout::Atlas(&atlas)),
+            12 + 8 + 12 + 16 + 8 + 16
+        );
+        assert_eq!(vertex_size(UvLayout::Array), 12 + 4);
+    }
+
+    #[test]
+    fn validate_mesh_reports_what_is_wrong() {
+        let cube = || generate_cube(FaceTextures::all(0), &AtlasLayout::default());
+        assert_eq!(validate_mesh(&cube()), Ok(()));
+
+        let empty = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        );
+        assert_eq!(validate_mesh(&empty), Err(MeshError::MissingPositions));
+
+        let mut short_normals = cube();
+        short_normals.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; 20]);
+        let error = validate_mesh(&short_normals).unwrap_err();
+        assert_eq!(
+            error,
+            MeshError::AttributeLength {
+                attribute: Mesh::ATTRIBUTE_NORMAL.name,
+                len: 20,
+                vertices: 24,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Vertex_Normal has 20 values, 4 fewer than the 24 positions"
+        );
+
+        let mut past_end = cube();
+        past_end.insert_indices(Indices::U16(vec![0, 1, 24]));
+        let error = validate_mesh(&past_end).unwrap_err();
+        assert_eq!(
+            error,
+            MeshError::IndexOutOfRange {
+                position: 2,
+                index: 24,
+                vertices: 24,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "index 2 is 24, past the last of 24 vertices"
+        );
+    }
 }
@@ -0,0 +1,155 @@
+use crate::{
+    chunk::{Chunk, ChunkModified, VoxelStorage},
+    registry::VoxelRegistry,
+    schedule::{self, VoxelSet},
+};
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::Component,
+        event::EventReader,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res},
+    },
+    math::{primitives::Cuboid, UVec3, Vec3},
+};
+
+/// One box of a [`ColliderShape`], spanning the voxels `min..=max` in the chunk's local voxel
+/// coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColliderBox {
+    pub min: UVec3,
+    pub max: UVec3,
+}
+
+impl ColliderBox {
+    /// Center of the box in local voxel units, where voxels are centered on integer coordinates
+    /// like in chunk meshes.
+    #[inline]
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max).as_vec3() / 2.0
+    }
+
+    #[inline]
+    pub fn cuboid(&self) -> Cuboid {
+        Cuboid::from_size((self.max - self.min + UVec3::ONE).as_vec3())
+    }
+}
+
+/// The [collidable](crate::registry::VoxelType::collidable) volume of a chunk as boxes merged
+/// greedily along x, then y, then z, for physics engines to build a compound collider from.
+///
+/// Merged boxes keep bodies from snagging on the internal edges a trimesh of the same surface
+/// would have. Boxes are in local voxel units: add the chunk's
+/// [`origin`](crate::chunk::Chunk::origin) and scale by
+/// [`Voxel::SIZE`](crate::voxel::Voxel::SIZE) for world space.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Component)]
+pub struct ColliderShape {
+    pub boxes: Vec<ColliderBox>,
+}
+
+impl ColliderShape {
+    /// Every box with its center, for engines that take positioned primitives.
+    pub fn cuboids(&self) -> impl Iterator<Item = (Vec3, Cuboid)> + '_ {
+        self.boxes.iter().map(|b| (b.center(), b.cuboid()))
+    }
+}
+
+/// Merges the collidable voxels of `chunk` into a [`ColliderShape`].
+///
+/// Each box starts at the first voxel not yet covered, grows along x while voxels are
+/// collidable, then along y while the whole row is, then along z while the whole slab is.
+pub fn chunk_collider(chunk: &Chunk, registry: &VoxelRegistry) -> ColliderShape {
+    let dims = chunk.dims();
+    let index = |pos: UVec3| ((pos.z * dims.y + pos.y) * dims.x + pos.x) as usize;
+    let mut open = vec![false; (dims.x * dims.y * dims.z) as usize];
+    for (pos, voxel) in chunk.data().iter() {
+        open[index(pos)] = registry.is_collidable(voxel.id);
+    }
+
+    let filled = |open: &[bool], lo: UVec3, hi: UVec3| {
+        (lo.z..=hi.z)
+            .all(|z| (lo.y..=hi.y).all(|y| (lo.x..=hi.x).all(|x| open[index(UVec3::new(x, y, z))])))
+    };
+
+    let mut boxes = Vec::new();
+    for z in 0..dims.z {
+        for y in 0..dims.y {
+            for x in 0..dims.x {
+                let min = UVec3::new(x, y, z);
+                if !open[index(min)] {
+                    continue;
+                }
+
+                let mut max = min;
+                while max.x + 1 < dims.x && open[index(UVec3::new(max.x + 1, y, z))] {
+                    max.x += 1;
+                }
+                while max.y + 1 < dims.y
+                    && filled(
+                        &open,
+                        UVec3::new(x, max.y + 1, z),
+                        UVec3::new(max.x, max.y + 1, z),
+                    )
+                {
+                    max.y += 1;
+                }
+                while max.z + 1 < dims.z
+                    && filled(
+                        &open,
+                        UVec3::new(x, y, max.z + 1),
+                        UVec3::new(max.x, max.y, max.z + 1),
+                    )
+                {
+                    max.z += 1;
+                }
+
+                for z in min.z..=max.z {
+                    for y in min.y..=max.y {
+                        for x in min.x..=max.x {
+                            open[index(UVec3::new(x, y, z))] = false;
+                        }
+                    }
+                }
+                boxes.push(ColliderBox { min, max });
+            }
+        }
+    }
+
+    ColliderShape { boxes }
+}
+
+/// Rebuilds the [`ColliderShape`] of every chunk a [`ChunkModified`] reports, removing it from
+/// chunks left without collidable voxels.
+pub fn update_chunk_colliders(
+    mut commands: Commands,
+    registry: Res<VoxelRegistry>,
+    mut events: EventReader<ChunkModified>,
+    chunks: Query<&Chunk>,
+) {
+    for event in events.read() {
+        let Ok(chunk) = chunks.get(event.chunk) else {
+            continue;
+        };
+
+        let shape = chunk_collider(chunk, &registry);
+        let mut entity = commands.entity(event.chunk);
+        if shape.boxes.is_empty() {
+            entity.remove::<ColliderShape>();
+        } else {
+            entity.insert(shape);
+        }
+    }
+}
+
+/// Keeps a [`ColliderShape`] on every chunk with collidable voxels, for a physics integration to
+/// turn into its own colliders.
+#[derive(Debug, Default)]
+pub struct ChunkPhysicsPlugin;
+
+impl Plugin for ChunkPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.add_systems(Update, update_chunk_colliders.in_set(VoxelSet::Mesh));
+    }
+}
@@ -0,0 +1,74 @@
+use bevy::math::{IVec3, Vec3};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// Voxel that was hit.
+    pub position: IVec3,
+    /// Normal of the face the ray entered through, zero if the ray started inside the voxel.
+    pub normal: IVec3,
+    pub distance: f32,
+}
+
+/// Walks the unit voxel grid along a ray, returning the first voxel for which `is_hit` is true.
+///
+/// Voxel `p` covers `p..p + 1` on every axis; `origin` and `max_distance` are in voxel units.
+pub fn raycast(
+    origin: Vec3,
+    direction: Vec3,
+    max_distance: f32,
+    mut is_hit: impl FnMut(IVec3) -> bool,
+) -> Option<RaycastHit> {
+    let direction = direction.normalize_or_zero();
+    if direction == Vec3::ZERO {
+        return None;
+    }
+
+    let mut position = origin.floor().as_ivec3();
+    let mut step = IVec3::ZERO;
+    let mut t_max = Vec3::INFINITY;
+    let mut t_delta = Vec3::INFINITY;
+    for axis in 0..3 {
+        let d = direction[axis];
+        if d > 0.0 {
+            step[axis] = 1;
+            t_delta[axis] = 1.0 / d;
+            t_max[axis] = (position[axis] as f32 + 1.0 - origin[axis]) / d;
+        } else if d < 0.0 {
+            step[axis] = -1;
+            t_delta[axis] = -1.0 / d;
+            t_max[axis] = (origin[axis] - position[axis] as f32) / -d;
+        }
+    }
+
+    let mut normal = IVec3::ZERO;
+    let mut distance = 0.0;
+    while distance <= max_distance {
+        if is_hit(position) {
+            return Some(RaycastHit {
+                position,
+                normal,
+                distance,
+            });
+        }
+
+        let axis = if t_max.x < t_max.y {
+            if t_max.x < t_max.z {
+                0
+            } else {
+                2
+            }
+        } else if t_max.y < t_max.z {
+            1
+        } else {
+            2
+        };
+
+        distance = t_max[axis];
+        position[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+        normal = IVec3::ZERO;
+        normal[axis] = -step[axis];
+    }
+
+    None
+}
@@ -0,0 +1,153 @@
+use crate::{
+    chunk::{Chunk, ChunkChild, ChunkModified},
+    render::RenderMode,
+    schedule::{self, VoxelSet},
+    voxel::Voxel,
+};
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{Asset, Assets, Handle},
+    ecs::{
+        component::Component,
+        event::EventReader,
+        schedule::{common_conditions::resource_equals, IntoSystemConfigs},
+        system::{Commands, Query, ResMut},
+    },
+    math::{primitives::Cuboid, Vec3, Vec4},
+    pbr::{Material, MaterialMeshBundle, MaterialPipeline, MaterialPipelineKey, MaterialPlugin},
+    reflect::Reflect,
+    render::{
+        mesh::{Mesh, MeshVertexBufferLayoutRef},
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            AsBindGroup, Extent3d, Face, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, TextureDimension, TextureFormat,
+        },
+        texture::Image,
+    },
+    transform::components::Transform,
+};
+
+pub const RAYMARCH_SHADER: &str = "shaders/raymarch.wgsl";
+
+/// Draws a chunk by raymarching its voxel ids in the fragment shader of a box covering the
+/// chunk, instead of meshing it. Voxels are shaded by id and face direction only, with no
+/// textures, lighting or translucency.
+#[derive(Debug, Clone, Asset, Reflect, AsBindGroup)]
+pub struct RaymarchMaterial {
+    /// The chunk's voxel ids, see [`chunk_volume`].
+    #[texture(0, dimension = "3d", sample_type = "u_int")]
+    pub voxels: Handle<Image>,
+    /// World position of the chunk's minimum corner, with the voxel size in `w`.
+    #[uniform(1)]
+    pub min_corner: Vec4,
+}
+
+impl Material for RaymarchMaterial {
+    fn fragment_shader() -> ShaderRef {
+        RAYMARCH_SHADER.into()
+    }
+
+    /// Draws the box's back faces, which stay on screen with the camera inside the chunk.
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.primitive.cull_mode = Some(Face::Front);
+        Ok(())
+    }
+}
+
+/// The 3D texture a chunk is raymarched through, kept on the chunk entity.
+#[derive(Debug, Clone, Component)]
+pub struct RaymarchVolume {
+    pub image: Handle<Image>,
+}
+
+/// Uploads the voxel ids of `chunk` as a 3D texture, one texel per voxel.
+pub fn chunk_volume(chunk: &Chunk) -> Image {
+    let dims = chunk.dims();
+    let mut data = Vec::with_capacity((dims.x * dims.y * dims.z) as usize * 2);
+    for z in 0..dims.z as usize {
+        for y in 0..dims.y as usize {
+            for x in 0..dims.x as usize {
+                let id = chunk.get(x, y, z).map_or(0, |voxel| voxel.id);
+                data.extend(id.to_le_bytes());
+            }
+        }
+    }
+
+    Image::new(
+        Extent3d {
+            width: dims.x,
+            height: dims.y,
+            depth_or_array_layers: dims.z,
+        },
+        TextureDimension::D3,
+        data,
+        TextureFormat::R16Uint,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Reuploads the volume of every chunk a [`ChunkModified`] reports, spawning a raymarched box for
+/// chunks that don't have one yet.
+pub fn update_raymarch_volumes(
+    mut commands: Commands,
+    mut events: EventReader<ChunkModified>,
+    chunks: Query<(&Chunk, Option<&RaymarchVolume>)>,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<RaymarchMaterial>>,
+) {
+    for event in events.read() {
+        let Ok((chunk, volume)) = chunks.get(event.chunk) else {
+            continue;
+        };
+        if let Some(volume) = volume {
+            images.insert(&volume.image, chunk_volume(chunk));
+            continue;
+        }
+
+        let image = images.add(chunk_volume(chunk));
+        let dims = chunk.dims().as_vec3();
+        // Voxels are centered on integer coordinates, so the chunk starts half a voxel early.
+        let min_corner = (chunk.origin().as_vec3() - 0.5) * Voxel::SIZE;
+        let material = materials.add(RaymarchMaterial {
+            voxels: image.clone(),
+            min_corner: min_corner.extend(Voxel::SIZE),
+        });
+        commands.spawn((
+            MaterialMeshBundle {
+                mesh: meshes.add(Cuboid::from_size(dims)),
+                material,
+                transform: Transform::from_translation(min_corner + dims / 2.0 * Voxel::SIZE)
+                    .with_scale(Vec3::splat(Voxel::SIZE)),
+                ..Default::default()
+            },
+            ChunkChild(event.chunk),
+        ));
+        commands
+            .entity(event.chunk)
+            .insert(RaymarchVolume { image });
+    }
+}
+
+/// Draws chunks with [`RaymarchMaterial`] while [`RenderMode::Raymarch`] is active.
+#[derive(Debug, Default)]
+pub struct RaymarchPlugin;
+
+impl Plugin for RaymarchPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.add_plugins(MaterialPlugin::<RaymarchMaterial>::default())
+            .add_systems(
+                Update,
+                update_raymarch_volumes
+                    .in_set(VoxelSet::Mesh)
+                    .run_if(resource_equals(RenderMode::Raymarch)),
+            );
+    }
+}
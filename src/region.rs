@@ -0,0 +1,284 @@
+use crate::{
+    chunk::{Chunk, ChunkData},
+    codec::PalettedChunk,
+    world::ChunkPos,
+};
+use bevy::math::IVec3;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// Chunks along each axis of a region.
+pub const REGION_CHUNKS: i32 = 16;
+
+const CHUNKS_PER_REGION: usize = (REGION_CHUNKS * REGION_CHUNKS * REGION_CHUNKS) as usize;
+/// Chunks are stored in whole sectors, so most rewrites fit where the old data was.
+const SECTOR: u64 = 4096;
+/// Bytes of one header entry: the first sector and the sector count of a chunk.
+const ENTRY: u64 = 8;
+const HEADER_SECTORS: u32 = (CHUNKS_PER_REGION as u64 * ENTRY).div_ceil(SECTOR) as u32;
+
+/// Where a chunk's data lies in a region file, in sectors. Zero sectors means the chunk was
+/// never written.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    offset: u32,
+    sectors: u32,
+}
+
+/// A file holding the chunks of one region, [`REGION_CHUNKS`] chunks on a side, like
+/// Minecraft's `.mca` files.
+///
+/// The file starts with a header of one [`Entry`] per chunk, followed by each chunk's
+/// length-prefixed [`PalettedChunk::to_bytes`] padded to whole sectors. Chunks are read and
+/// written on their own through the header: a rewritten chunk stays in place while it fits its
+/// sectors and moves to the end of the file otherwise, leaving the old sectors unused.
+#[derive(Debug)]
+pub struct RegionFile {
+    file: File,
+    region: IVec3,
+    header: Vec<Entry>,
+}
+
+impl RegionFile {
+    /// Opens the file of `region` at `path`, creating it with an empty header if it doesn't
+    /// exist.
+    pub fn open(path: impl AsRef<Path>, region: IVec3) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let header_len = HEADER_SECTORS as u64 * SECTOR;
+        if file.metadata()?.len() < header_len {
+            file.set_len(header_len)?;
+        }
+        let mut bytes = vec![0; (CHUNKS_PER_REGION as u64 * ENTRY) as usize];
+        file.seek(SeekFrom::Start(0))?;
+        file.read_exact(&mut bytes)?;
+        let header = bytes
+            .chunks_exact(ENTRY as usize)
+            .map(|entry| Entry {
+                offset: u32::from_le_bytes(entry[..4].try_into().unwrap()),
+                sectors: u32::from_le_bytes(entry[4..].try_into().unwrap()),
+            })
+            .collect();
+
+        Ok(Self {
+            file,
+            region,
+            header,
+        })
+    }
+
+    /// Region coordinate of the file, counted in regions.
+    #[inline]
+    pub fn region(&self) -> IVec3 {
+        self.region
+    }
+
+    /// Reads the chunk at `pos`, or `None` if it was never written.
+    #[inline]
+    pub fn read_chunk(&mut self, pos: ChunkPos) -> io::Result<Option<Chunk>> {
+        let data = self.read_data(pos)?;
+        Ok(data.map(|data| Chunk::from_data(pos.0.as_vec3(), data)))
+    }
+
+    /// Like [`read_chunk`](Self::read_chunk), returning the voxels alone, e.g. to remap their
+    /// ids before they become a chunk.
+    pub fn read_data(&mut self, pos: ChunkPos) -> io::Result<Option<ChunkData>> {
+        let entry = self.header[self.index(pos)?];
+        if entry.sectors == 0 {
+            return Ok(None);
+        }
+
+        self.file
+            .seek(SeekFrom::Start(entry.offset as u64 * SECTOR))?;
+        let mut len = [0; 4];
+        self.file.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as u64;
+        if len + 4 > entry.sectors as u64 * SECTOR {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "chunk overruns its sectors",
+            ));
+        }
+        let mut bytes = vec![0; len as usize];
+        self.file.read_exact(&mut bytes)?;
+
+        Ok(Some(PalettedChunk::from_bytes(&bytes)?.decode()))
+    }
+
+    /// Writes `chunk` at `pos`, touching only its own sectors and header entry.
+    #[inline]
+    pub fn write_chunk(&mut self, pos: ChunkPos, chunk: &Chunk) -> io::Result<()> {
+        self.write_data(pos, &chunk.data().to_dense())
+    }
+
+    /// Like [`write_chunk`](Self::write_chunk), for voxels copied out of a chunk, e.g. to save
+    /// them off the main thread.
+    pub fn write_data(&mut self, pos: ChunkPos, data: &ChunkData) -> io::Result<()> {
+        let index = self.index(pos)?;
+        let payload = PalettedChunk::encode(data).to_bytes();
+        let len = payload.len() as u64 + 4;
+        let sectors = len.div_ceil(SECTOR) as u32;
+
+        // Chunks that outgrow their sectors move past the last chunk in the file.
+        let old = self.header[index];
+        let entry = if old.sectors >= sectors {
+            old
+        } else {
+            let end = self
+                .header
+                .iter()
+                .map(|entry| entry.offset + entry.sectors)
+                .max()
+                .unwrap_or_default();
+            Entry {
+                offset: end.max(HEADER_SECTORS),
+                sectors,
+            }
+        };
+
+        let start = entry.offset as u64 * SECTOR;
+        self.file.seek(SeekFrom::Start(start))?;
+        self.file.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.file.write_all(&payload)?;
+        let end = start + entry.sectors as u64 * SECTOR;
+        if self.file.metadata()?.len() < end {
+            self.file.set_len(end)?;
+        }
+
+        self.file.seek(SeekFrom::Start(index as u64 * ENTRY))?;
+        self.file.write_all(&entry.offset.to_le_bytes())?;
+        self.file.write_all(&entry.sectors.to_le_bytes())?;
+        self.file.flush()?;
+        self.header[index] = entry;
+
+        Ok(())
+    }
+
+    /// Header index of `pos`, if it lies in this region.
+    fn index(&self, pos: ChunkPos) -> io::Result<usize> {
+        if region_of(pos) != self.region {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("chunk {:?} is outside region {}", pos.0, self.region),
+            ));
+        }
+
+        let local = pos.0.rem_euclid(IVec3::splat(REGION_CHUNKS));
+        Ok(((local.z * REGION_CHUNKS + local.y) * REGION_CHUNKS + local.x) as usize)
+    }
+}
+
+/// Region coordinate of the region holding the chunk at `pos`.
+#[inline]
+pub fn region_of(pos: ChunkPos) -> IVec3 {
+    pos.0.div_euclid(IVec3::splat(REGION_CHUNKS))
+}
+
+/// Path of the file of `region` in `dir`, e.g. `r.0.-1.2.region`.
+pub fn region_path(dir: impl AsRef<Path>, region: IVec3) -> PathBuf {
+    dir.as_ref()
+        .join(format!("r.{}.{}.{}.region", region.x, region.y, region.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::Voxel;
+
+    /// A region file at a fresh path in the temp dir, removed when dropped.
+    struct TempRegion(PathBuf);
+
+    impl TempRegion {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("voxel-{}-{name}", std::process::id()));
+            let _ = std::fs::remove_file(&path);
+            Self(path)
+        }
+
+        fn open(&self) -> RegionFile {
+            RegionFile::open(&self.0, IVec3::ZERO).unwrap()
+        }
+
+        fn len(&self) -> u64 {
+            std::fs::metadata(&self.0).unwrap().len()
+        }
+    }
+
+    impl Drop for TempRegion {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// A chunk cycling through `distinct` different voxels.
+    fn mixed(distinct: usize) -> ChunkData {
+        let mut data = ChunkData::cubic(16);
+        data.voxels_mut()
+            .iter_mut()
+            .enumerate()
+            .for_each(|(i, voxel)| *voxel = Voxel::new((i % distinct) as u16));
+        data
+    }
+
+    #[test]
+    fn every_palette_width_survives_the_file() {
+        let temp = TempRegion::new("widths.region");
+        // One palette of each width, from 1 bit to 16.
+        let chunks = [(0, 1), (1, 3), (2, 16), (3, 17), (4, 300)]
+            .map(|(x, distinct)| (ChunkPos::new(x, 1, 2), mixed(distinct)));
+        let bits = chunks
+            .each_ref()
+            .map(|(_, data)| PalettedChunk::encode(data).bits());
+        assert_eq!(bits, [1, 2, 4, 8, 16]);
+
+        let mut region = temp.open();
+        for (pos, data) in &chunks {
+            region.write_data(*pos, data).unwrap();
+        }
+        drop(region);
+
+        let mut region = temp.open();
+        for (pos, data) in &chunks {
+            let read = region.read_data(*pos).unwrap().unwrap();
+            assert_eq!(read.voxels(), data.voxels(), "{pos:?}");
+        }
+        assert!(region.read_data(ChunkPos::new(0, 0, 0)).unwrap().is_none());
+    }
+
+    #[test]
+    fn rewritten_slots_keep_their_neighbors() {
+        let temp = TempRegion::new("rewrites.region");
+        let (first, second) = (ChunkPos::new(0, 0, 0), ChunkPos::new(1, 0, 0));
+        let (small, large) = (mixed(2), mixed(300));
+
+        let mut region = temp.open();
+        region.write_data(first, &small).unwrap();
+        region.write_data(second, &small).unwrap();
+        let len = temp.len();
+
+        // Outgrowing its sectors moves the chunk to the end, past its neighbor.
+        region.write_data(first, &large).unwrap();
+        assert!(temp.len() > len);
+        let len = temp.len();
+        // Shrinking back stays in place.
+        region.write_data(first, &mixed(3)).unwrap();
+        assert_eq!(temp.len(), len);
+        drop(region);
+
+        let mut region = temp.open();
+        let read = |region: &mut RegionFile, pos| region.read_data(pos).unwrap().unwrap();
+        assert_eq!(read(&mut region, first).voxels(), mixed(3).voxels());
+        assert_eq!(read(&mut region, second).voxels(), small.voxels());
+
+        let outside = region.write_data(ChunkPos::new(REGION_CHUNKS, 0, 0), &small);
+        assert_eq!(outside.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
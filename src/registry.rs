@@ -0,0 +1,480 @@
+use crate::{
+    face::{Face, UvTransform},
+    tick::{self, TickHandler, VoxelTick, GRASS_SPREAD_DELAY},
+    voxel::{Orientation, Voxel, VoxelId},
+};
+use bevy::{ecs::system::Resource, log::warn, math::IVec3};
+use std::time::Duration;
+
+/// How a voxel type is blended when rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transparency {
+    /// Never rendered, e.g. air.
+    Invisible,
+    Opaque,
+    /// Alpha-tested against a threshold, e.g. leaves.
+    Cutout,
+    /// Alpha-blended, e.g. water and glass.
+    Translucent,
+}
+
+/// The part of its cell a voxel fills, turned by the voxel's [`Orientation`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VoxelShape {
+    #[default]
+    Cube,
+    /// The half of the cell against the face opposite [`Orientation::up`], so an upright slab
+    /// sits on the floor and a [`NegY`](Orientation::NegY) one hangs from the ceiling.
+    Slab,
+    /// A bottom slab plus the upper quarter on the side the stairs climb towards, which is
+    /// [`Orientation::up`] for sideways orientations and `+z` otherwise. Stairs always stand
+    /// upright.
+    Stairs,
+}
+
+impl VoxelShape {
+    /// Which eighths of the cell the shape fills when turned to `orientation`, one bit per
+    /// half-voxel subcell, bit `x + 2y + 4z` for the subcell at `(x, y, z)` in `0..2`.
+    pub fn mask(self, orientation: Orientation) -> u8 {
+        let up = orientation.up().offset();
+        let climbs = if up.y == 0 { up } else { IVec3::Z };
+
+        (0..8).fold(0, |mask, i| {
+            // Direction from the cell's center to the subcell's.
+            let dir = IVec3::new(i & 1, (i >> 1) & 1, (i >> 2) & 1) * 2 - 1;
+            let filled = match self {
+                Self::Cube => true,
+                Self::Slab => dir.dot(up) < 0,
+                Self::Stairs => dir.y < 0 || dir.dot(climbs) > 0,
+            };
+            mask | (filled as u8) << i
+        })
+    }
+}
+
+/// Texture layers used by each side of a voxel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceTextures {
+    pub top: u32,
+    pub bottom: u32,
+    pub side: u32,
+}
+
+impl FaceTextures {
+    #[inline]
+    pub const fn all(index: u32) -> Self {
+        Self {
+            top: index,
+            bottom: index,
+            side: index,
+        }
+    }
+
+    #[inline]
+    pub const fn column(ends: u32, side: u32) -> Self {
+        Self {
+            top: ends,
+            bottom: ends,
+            side,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VoxelType {
+    pub name: String,
+    pub textures: FaceTextures,
+    /// Alpha-masked layers drawn over `textures`, e.g. ore speckles over stone.
+    ///
+    /// Overlays are rendered as a second, slightly inflated cube rather than blended in a
+    /// shader, so any [`Transparency`] of the base voxel works with them.
+    pub overlay: Option<FaceTextures>,
+    pub transparency: Transparency,
+    /// Emissive strength, `0.0` for voxels that don't glow.
+    pub emission: f32,
+    pub liquid: bool,
+    /// Whether faces are also drawn from behind, e.g. for leaves seen from inside a canopy. The
+    /// mesher duplicates them facing the other way, so no material has to stop culling.
+    pub double_sided: bool,
+    /// How the texture of each face is turned, indexed by [`Face::index`] of the voxel's own
+    /// faces, e.g. to line up a tile drawn sideways in the atlas.
+    pub uv_transforms: [UvTransform; 6],
+    /// Whether the voxel's [`Orientation`](crate::voxel::Orientation) turns its textures, e.g.
+    /// a log's rings onto the faces its axis runs through. Other voxel types always mesh upright.
+    pub directional: bool,
+    /// Meshed shape of the voxel. Other systems, e.g. collision and lighting, treat every
+    /// shape as a full cube.
+    pub shape: VoxelShape,
+    /// Whether the voxel blocks movement, independently of how it renders.
+    pub collidable: bool,
+    /// Behavior run by [`VoxelTickQueue`](crate::tick::VoxelTickQueue) ticks.
+    pub tick: Option<VoxelTick>,
+}
+
+impl VoxelType {
+    #[inline]
+    pub fn new(name: impl Into<String>, textures: FaceTextures) -> Self {
+        Self {
+            name: name.into(),
+            textures,
+            overlay: None,
+            transparency: Transparency::Opaque,
+            emission: 0.0,
+            liquid: false,
+            double_sided: false,
+            uv_transforms: [UvTransform::IDENTITY; 6],
+            directional: false,
+            shape: VoxelShape::Cube,
+            collidable: true,
+            tick: None,
+        }
+    }
+
+    #[inline]
+    pub fn with_overlay(mut self, overlay: FaceTextures) -> Self {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    #[inline]
+    pub fn with_transparency(mut self, transparency: Transparency) -> Self {
+        self.transparency = transparency;
+        self
+    }
+
+    #[inline]
+    pub fn with_emission(mut self, emission: f32) -> Self {
+        self.emission = emission;
+        self
+    }
+
+    #[inline]
+    pub fn liquid(mut self) -> Self {
+        self.liquid = true;
+        self
+    }
+
+    #[inline]
+    pub fn double_sided(mut self) -> Self {
+        self.double_sided = true;
+        self
+    }
+
+    #[inline]
+    pub fn with_uv_transform(mut self, face: Face, transform: UvTransform) -> Self {
+        self.uv_transforms[face.index()] = transform;
+        self
+    }
+
+    #[inline]
+    pub fn directional(mut self) -> Self {
+        self.directional = true;
+        self
+    }
+
+    #[inline]
+    pub fn with_shape(mut self, shape: VoxelShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    #[inline]
+    pub fn with_collidable(mut self, collidable: bool) -> Self {
+        self.collidable = collidable;
+        self
+    }
+
+    /// Runs `handler` `delay` after any neighboring voxel changes.
+    #[inline]
+    pub fn with_tick(mut self, delay: Duration, handler: TickHandler) -> Self {
+        self.tick = Some(VoxelTick { delay, handler });
+        self
+    }
+}
+
+/// Maps voxel ids to their [`VoxelType`].
+///
+/// Id 0 is always air. [`VoxelRegistry::default`] provides the built-in block set matching
+/// `assets/array_texture.png`; insert a registry before the app starts to replace it.
+#[derive(Debug, Clone, Resource)]
+pub struct VoxelRegistry {
+    types: Vec<VoxelType>,
+    /// `collidable` of every type, indexed by id, for the hot collision loop.
+    collidable: Vec<bool>,
+}
+
+impl VoxelRegistry {
+    pub const AIR: &'static str = "air";
+
+    /// Creates a registry containing only air.
+    pub fn new() -> Self {
+        Self {
+            types: vec![VoxelType::new(Self::AIR, FaceTextures::all(0))
+                .with_transparency(Transparency::Invisible)
+                .with_collidable(false)],
+            collidable: vec![false],
+        }
+    }
+
+    /// Registers a voxel type, returning its id.
+    ///
+    /// Panics if the registry is full.
+    pub fn register(&mut self, voxel_type: VoxelType) -> VoxelId {
+        let id = VoxelId::try_from(self.types.len()).expect("voxel registry is full");
+        self.collidable.push(voxel_type.collidable);
+        self.types.push(voxel_type);
+
+        id
+    }
+
+    #[inline]
+    pub fn get(&self, id: VoxelId) -> Option<&VoxelType> {
+        self.types.get(id as usize)
+    }
+
+    /// Whether voxels of type `id` block movement. Unknown ids don't.
+    #[inline]
+    pub fn is_collidable(&self, id: VoxelId) -> bool {
+        self.collidable.get(id as usize).copied().unwrap_or(false)
+    }
+
+    /// Whether the block target stops at voxels of type `id`, collidable or not, so e.g. tall
+    /// grass can be broken. Unknown ids, invisible voxels and liquids aren't targeted.
+    pub fn is_targetable(&self, id: VoxelId) -> bool {
+        self.get(id).is_some_and(|voxel_type| {
+            voxel_type.transparency != Transparency::Invisible && !voxel_type.liquid
+        })
+    }
+
+    /// Looks up the id registered under `name`.
+    pub fn id(&self, name: &str) -> Option<VoxelId> {
+        self.types
+            .iter()
+            .position(|voxel_type| voxel_type.name == name)
+            .map(|i| i as VoxelId)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (VoxelId, &VoxelType)> {
+        self.types
+            .iter()
+            .enumerate()
+            .map(|(i, voxel_type)| (i as VoxelId, voxel_type))
+    }
+
+    /// Name and id of every registered type, for persisting alongside saved voxel data.
+    pub fn name_table(&self) -> Vec<(String, VoxelId)> {
+        self.iter()
+            .map(|(id, voxel_type)| (voxel_type.name.clone(), id))
+            .collect()
+    }
+
+    /// Builds a table translating ids saved under an older registry layout into this one by
+    /// matching type names.
+    ///
+    /// `saved` is the [`VoxelRegistry::name_table`] written with the data. Names that are no
+    /// longer registered map to air unless [`RemapTable::with_fallback`] says otherwise, and are
+    /// logged as a warning.
+    pub fn remap_table(&self, saved: &[(String, VoxelId)]) -> RemapTable {
+        let len = saved
+            .iter()
+            .map(|(_, id)| *id as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut ids = vec![None; len];
+        let mut unknown = Vec::new();
+        saved.iter().for_each(|(name, old)| match self.id(name) {
+            Some(new) => ids[*old as usize] = Some(new),
+            None => unknown.push(name.clone()),
+        });
+
+        if !unknown.is_empty() {
+            warn!(
+                "saved voxel types missing from the registry: {}",
+                unknown.join(", ")
+            );
+        }
+
+        RemapTable {
+            ids,
+            fallback: 0,
+            unknown,
+        }
+    }
+}
+
+/// Translates saved voxel ids into the current registry's ids.
+///
+/// Built with [`VoxelRegistry::remap_table`].
+#[derive(Debug, Clone)]
+pub struct RemapTable {
+    /// New id for each saved id, `None` where the saved name is unknown.
+    ids: Vec<Option<VoxelId>>,
+    fallback: VoxelId,
+    unknown: Vec<String>,
+}
+
+impl RemapTable {
+    /// Sets the id used for saved ids whose type is no longer registered.
+    #[inline]
+    pub fn with_fallback(mut self, fallback: VoxelId) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    #[inline]
+    pub fn get(&self, saved: VoxelId) -> VoxelId {
+        self.ids
+            .get(saved as usize)
+            .copied()
+            .flatten()
+            .unwrap_or(self.fallback)
+    }
+
+    /// Names from the saved table that aren't registered anymore.
+    #[inline]
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+
+    /// Whether every saved id maps to itself, in which case data can be used as is.
+    pub fn is_identity(&self) -> bool {
+        self.ids
+            .iter()
+            .enumerate()
+            .all(|(i, id)| *id == Some(i as VoxelId))
+    }
+
+    /// Rewrites saved voxel data in place.
+    pub fn apply(&self, voxels: &mut [Voxel]) {
+        if self.is_identity() {
+            return;
+        }
+
+        voxels
+            .iter_mut()
+            .for_each(|voxel| voxel.id = self.get(voxel.id));
+    }
+}
+
+impl Default for VoxelRegistry {
+    fn default() -> Self {
+        use Transparency::*;
+
+        let mut registry = Self::new();
+        [
+            VoxelType::new("stone", FaceTextures::all(0)),
+            VoxelType::new("dirt", FaceTextures::all(1))
+                .with_tick(GRASS_SPREAD_DELAY, TickHandler::new(tick::spread_grass)),
+            VoxelType::new(
+                "grass",
+                FaceTextures {
+                    top: 2,
+                    bottom: 1,
+                    side: 3,
+                },
+            ),
+            VoxelType::new("sand", FaceTextures::all(4)),
+            VoxelType::new("water", FaceTextures::all(5))
+                .with_transparency(Translucent)
+                .with_collidable(false)
+                .liquid(),
+            VoxelType::new("glass", FaceTextures::all(6)).with_transparency(Translucent),
+            VoxelType::new("glowstone", FaceTextures::all(7)).with_emission(4.0),
+            VoxelType::new("log", FaceTextures::column(9, 8)).directional(),
+            VoxelType::new("leaves", FaceTextures::all(10))
+                .with_transparency(Cutout)
+                .double_sided(),
+            VoxelType::new("iron_ore", FaceTextures::all(0)).with_overlay(FaceTextures::all(11)),
+            VoxelType::new("barrier", FaceTextures::all(0)).with_transparency(Invisible),
+            VoxelType::new("stone_slab", FaceTextures::all(0)).with_shape(VoxelShape::Slab),
+            VoxelType::new("stone_stairs", FaceTextures::all(0)).with_shape(VoxelShape::Stairs),
+        ]
+        .into_iter()
+        .for_each(|voxel_type| {
+            registry.register(voxel_type);
+        });
+
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `registry`'s types registered in reverse, as if its definitions had been reordered.
+    fn reversed(registry: &VoxelRegistry) -> VoxelRegistry {
+        let types: Vec<_> = registry.iter().skip(1).map(|(_, t)| t.clone()).collect();
+        let mut reversed = VoxelRegistry::new();
+        types.into_iter().rev().for_each(|voxel_type| {
+            reversed.register(voxel_type);
+        });
+        reversed
+    }
+
+    fn names(registry: &VoxelRegistry, voxels: &[Voxel]) -> Vec<String> {
+        voxels
+            .iter()
+            .map(|voxel| registry.get(voxel.id).unwrap().name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn remap_keeps_names_across_reordering() {
+        let saved = VoxelRegistry::default();
+        let current = reversed(&saved);
+        let mut voxels: Vec<Voxel> = saved.iter().map(|(id, _)| Voxel::new(id)).collect();
+        let before = names(&saved, &voxels);
+
+        let remap = current.remap_table(&saved.name_table());
+        assert!(!remap.is_identity());
+        remap.apply(&mut voxels);
+        assert_eq!(names(&current, &voxels), before);
+        assert!(remap.unknown().is_empty());
+    }
+
+    #[test]
+    fn remap_of_same_registry_is_identity() {
+        let registry = VoxelRegistry::default();
+        assert!(registry.remap_table(&registry.name_table()).is_identity());
+    }
+
+    #[test]
+    fn unknown_names_map_to_fallback() {
+        let saved = VoxelRegistry::default();
+        let mut current = VoxelRegistry::new();
+        let stone = current.register(saved.get(saved.id("stone").unwrap()).unwrap().clone());
+
+        let remap = current
+            .remap_table(&saved.name_table())
+            .with_fallback(stone);
+        assert_eq!(remap.get(saved.id("stone").unwrap()), stone);
+        assert_eq!(remap.get(saved.id("dirt").unwrap()), stone);
+        assert_eq!(remap.get(0), 0);
+        assert!(remap.unknown().contains(&"dirt".to_owned()));
+    }
+
+    #[test]
+    fn shape_masks_fill_the_expected_subcells() {
+        // Subcells with y = 0, then with y = 1 and z = 1.
+        let (bottom, upper_back) = (0b0011_0011, 0b1100_0000);
+        assert_eq!(VoxelShape::Cube.mask(Orientation::NegX), 0xff);
+        assert_eq!(VoxelShape::Slab.mask(Orientation::PosY), bottom);
+        assert_eq!(VoxelShape::Slab.mask(Orientation::NegY), !bottom);
+        assert_eq!(VoxelShape::Slab.mask(Orientation::PosX), 0b0101_0101);
+        assert_eq!(
+            VoxelShape::Stairs.mask(Orientation::PosY),
+            bottom | upper_back
+        );
+        // Sideways stairs climb towards their up, and still stand on the floor.
+        assert_eq!(
+            VoxelShape::Stairs.mask(Orientation::NegZ),
+            bottom | 0b0000_1100
+        );
+        for orientation in Orientation::ALL {
+            assert_eq!(VoxelShape::Slab.mask(orientation).count_ones(), 4);
+            assert_eq!(VoxelShape::Stairs.mask(orientation).count_ones(), 6);
+        }
+    }
+}
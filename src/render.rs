@@ -0,0 +1,125 @@
+use bevy::{
+    ecs::system::Resource,
+    log::warn,
+    render::settings::{Backends, WgpuFeatures, WgpuSettings},
+    tasks::block_on,
+};
+use std::env;
+
+/// Environment variable holding a comma separated list of backends to render with, e.g.
+/// `vulkan,dx12`.
+pub const BACKENDS_VAR: &str = "VOXEL_BACKENDS";
+
+/// Environment variable picking the [`RenderMode`], `mesh` or `raymarch`.
+pub const RENDER_MODE_VAR: &str = "VOXEL_RENDER_MODE";
+
+/// How chunks are drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Resource)]
+pub enum RenderMode {
+    /// Chunks are meshed on the CPU by the active [`ChunkMesher`](crate::mesh::ChunkMesher).
+    #[default]
+    Mesh,
+    /// Chunks are uploaded as 3D textures and raymarched in the fragment shader, see
+    /// [`RaymarchPlugin`](crate::raymarch::RaymarchPlugin). Nothing is meshed.
+    Raymarch,
+}
+
+/// Renderer setup decided before the app starts.
+#[derive(Debug, Clone)]
+pub struct RenderConfig {
+    pub backends: Backends,
+    /// Whether to retry with every backend, including GL and software adapters, when none of
+    /// `backends` has an adapter.
+    pub fallback: bool,
+    pub mode: RenderMode,
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            backends: Backends::all(),
+            fallback: true,
+            mode: RenderMode::Mesh,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// The default config, with backends overridden by [`BACKENDS_VAR`] and the mode by
+    /// [`RENDER_MODE_VAR`] if they are set.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        if let Ok(list) = env::var(BACKENDS_VAR) {
+            config.backends = parse_backends(&list);
+        }
+        if let Ok(mode) = env::var(RENDER_MODE_VAR) {
+            match mode.trim().to_lowercase().as_str() {
+                "mesh" => config.mode = RenderMode::Mesh,
+                "raymarch" => config.mode = RenderMode::Raymarch,
+                _ => warn!("unknown render mode {mode:?}, meshing chunks"),
+            }
+        }
+
+        config
+    }
+
+    /// Picks the backends to render with, or `None` if no adapter is available at all.
+    pub fn select_backends(&self) -> Option<Backends> {
+        if has_adapter(self.backends) {
+            return Some(self.backends);
+        }
+        warn!("no graphics adapter found for backends {:?}", self.backends);
+
+        if self.fallback && self.backends != Backends::all() && has_adapter(Backends::all()) {
+            warn!("falling back to any available backend");
+            return Some(Backends::all());
+        }
+
+        None
+    }
+
+    /// Settings rendering with `backends`, requesting [`WgpuFeatures::POLYGON_MODE_LINE`] for
+    /// [`ChunkWireframes`](crate::material::ChunkWireframes) if the adapter supports it.
+    pub fn wgpu_settings(&self, backends: Backends) -> WgpuSettings {
+        let supported =
+            request_adapter(backends).map_or(WgpuFeatures::empty(), |adapter| adapter.features());
+        let features = supported & WgpuFeatures::POLYGON_MODE_LINE;
+        if features.is_empty() {
+            warn!("adapter can't draw lines, wireframes are disabled");
+        }
+
+        WgpuSettings {
+            backends: Some(backends),
+            features,
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses a comma separated list of backend names, ignoring unknown ones.
+pub fn parse_backends(list: &str) -> Backends {
+    list.split(',')
+        .map(|name| match name.trim().to_lowercase().as_str() {
+            "vulkan" | "vk" => Backends::VULKAN,
+            "dx12" | "d3d12" => Backends::DX12,
+            "metal" | "mtl" => Backends::METAL,
+            "gl" | "gles" | "opengl" => Backends::GL,
+            "webgpu" => Backends::BROWSER_WEBGPU,
+            "all" => Backends::all(),
+            _ => Backends::empty(),
+        })
+        .fold(Backends::empty(), |backends, backend| backends | backend)
+}
+
+fn has_adapter(backends: Backends) -> bool {
+    request_adapter(backends).is_some()
+}
+
+fn request_adapter(backends: Backends) -> Option<wgpu::Adapter> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+
+    block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+}
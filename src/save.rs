@@ -0,0 +1,431 @@
+use crate::{
+    chunk::{Chunk, ChunkData, ChunkModified},
+    region::{self, RegionFile},
+    registry::{RemapTable, VoxelRegistry},
+    schedule::{self, VoxelSet},
+    voxel::VoxelId,
+    world::ChunkPos,
+};
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        component::{Component, ComponentId},
+        entity::Entity,
+        event::EventReader,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Local, Query, Res, ResMut, Resource},
+        world::DeferredWorld,
+    },
+    log::warn,
+    math::IVec3,
+    tasks::{block_on, futures_lite::future, IoTaskPool, Task},
+    time::Time,
+    utils::HashMap,
+};
+use std::{
+    fs, io, mem,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// File in the save directory holding the [`VoxelRegistry::name_table`] the region files were
+/// written with.
+pub const NAME_TABLE_FILE: &str = "voxels.ron";
+
+/// How often edited chunks are written to region files, and where, which is also where the
+/// world is loaded from at startup while enabled. See [`AutosavePlugin`].
+#[derive(Debug, Clone, Resource)]
+pub struct AutosaveConfig {
+    pub interval: Duration,
+    /// Directory the region files are kept in, see [`region::region_path`].
+    pub path: PathBuf,
+    pub enabled: bool,
+    /// Id loaded in place of saved voxel types that are no longer registered.
+    pub unknown_voxel: VoxelId,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            path: PathBuf::from("saves/world"),
+            enabled: true,
+            unknown_voxel: 0,
+        }
+    }
+}
+
+/// Which edits of a chunk are on disk. Every [`ChunkModified`] after the one a chunk is created
+/// or loaded with counts as an edit.
+#[derive(Debug, Default, Clone, Copy, Component)]
+pub struct SaveState {
+    revision: u64,
+    saved: u64,
+    /// Revision a save task is writing, if any.
+    in_flight: Option<u64>,
+}
+
+impl SaveState {
+    /// Whether the chunk was edited since it was last written.
+    #[inline]
+    pub fn is_unsaved(&self) -> bool {
+        self.revision > self.saved
+    }
+}
+
+/// A chunk's voxels copied out for saving.
+struct Snapshot {
+    entity: Entity,
+    pos: ChunkPos,
+    revision: u64,
+    data: ChunkData,
+}
+
+/// Snapshots waiting for the next save task, including those of unloaded chunks.
+#[derive(Default, Resource)]
+struct SaveQueue(Vec<Snapshot>);
+
+/// The save task writing the last batch of snapshots. Only one runs at a time, so no two write
+/// the same region file at once.
+#[derive(Default, Resource)]
+struct SaveTask(Option<Task<Vec<SaveResult>>>);
+
+struct SaveResult {
+    entity: Entity,
+    revision: u64,
+    result: io::Result<()>,
+}
+
+/// Counts the edits of every chunk a [`ChunkModified`] reports, adding a [`SaveState`] to chunks
+/// reporting for the first time.
+pub fn track_unsaved_chunks(
+    mut commands: Commands,
+    mut events: EventReader<ChunkModified>,
+    mut states: Query<&mut SaveState>,
+) {
+    for event in events.read() {
+        if let Ok(mut state) = states.get_mut(event.chunk) {
+            state.revision += 1;
+        } else if let Some(mut entity) = commands.get_entity(event.chunk) {
+            entity.insert(SaveState::default());
+        }
+    }
+}
+
+/// Every [`AutosaveConfig::interval`], queues a snapshot of each unsaved chunk that isn't being
+/// saved at its current revision already.
+fn autosave(
+    time: Res<Time>,
+    config: Res<AutosaveConfig>,
+    mut elapsed: Local<Duration>,
+    mut queue: ResMut<SaveQueue>,
+    mut chunks: Query<(Entity, &Chunk, &mut SaveState)>,
+) {
+    *elapsed += time.delta();
+    if !config.enabled || *elapsed < config.interval {
+        return;
+    }
+    *elapsed = Duration::ZERO;
+
+    for (entity, chunk, mut state) in &mut chunks {
+        if !state.is_unsaved() || state.in_flight == Some(state.revision) {
+            continue;
+        }
+
+        state.in_flight = Some(state.revision);
+        queue.0.push(Snapshot {
+            entity,
+            pos: chunk.chunk_pos(),
+            revision: state.revision,
+            data: chunk.data().to_dense(),
+        });
+    }
+}
+
+/// Collects the finished save task, marking the revisions it wrote as saved, and hands queued
+/// snapshots to the [`IoTaskPool`] once no task is running. Chunks edited while their save was
+/// in flight stay unsaved and go out with the next autosave.
+fn flush_saves(
+    config: Res<AutosaveConfig>,
+    registry: Res<VoxelRegistry>,
+    mut queue: ResMut<SaveQueue>,
+    mut task: ResMut<SaveTask>,
+    mut states: Query<&mut SaveState>,
+) {
+    if let Some(running) = task.0.as_mut() {
+        let Some(results) = block_on(future::poll_once(running)) else {
+            return;
+        };
+        task.0 = None;
+
+        for SaveResult {
+            entity,
+            revision,
+            result,
+        } in results
+        {
+            if let Err(err) = &result {
+                warn!("failed to save chunk: {err}");
+            }
+            let Ok(mut state) = states.get_mut(entity) else {
+                continue;
+            };
+            if state.in_flight == Some(revision) {
+                state.in_flight = None;
+            }
+            if result.is_ok() {
+                state.saved = state.saved.max(revision);
+            }
+        }
+    }
+
+    if queue.0.is_empty() {
+        return;
+    }
+    let snapshots = mem::take(&mut queue.0);
+    let (dir, names) = (config.path.clone(), registry.name_table());
+    task.0 = Some(IoTaskPool::get().spawn(async move { write_snapshots(dir, &names, snapshots) }));
+}
+
+/// Writes `snapshots` into the region files in `dir`, opening each file once, along with the
+/// name table their ids refer to.
+fn write_snapshots(
+    dir: PathBuf,
+    names: &[(String, VoxelId)],
+    snapshots: Vec<Snapshot>,
+) -> Vec<SaveResult> {
+    let created = fs::create_dir_all(&dir).and_then(|_| write_name_table(&dir, names));
+    let mut regions: HashMap<IVec3, io::Result<RegionFile>> = HashMap::default();
+
+    snapshots
+        .into_iter()
+        .map(|snapshot| {
+            let region = region::region_of(snapshot.pos);
+            let file = regions.entry(region).or_insert_with(|| {
+                let path = region::region_path(&dir, region);
+                created
+                    .as_ref()
+                    .map_err(copy_error)
+                    .and_then(|_| RegionFile::open(path, region))
+            });
+            let result = match file {
+                Ok(file) => file.write_data(snapshot.pos, &snapshot.data),
+                Err(err) => Err(copy_error(err)),
+            };
+
+            SaveResult {
+                entity: snapshot.entity,
+                revision: snapshot.revision,
+                result,
+            }
+        })
+        .collect()
+}
+
+#[inline]
+fn copy_error(err: &io::Error) -> io::Error {
+    io::Error::new(err.kind(), err.to_string())
+}
+
+/// Writes `names` to the [`NAME_TABLE_FILE`] in `dir`.
+pub fn write_name_table(dir: &Path, names: &[(String, VoxelId)]) -> io::Result<()> {
+    let ron =
+        ron::to_string(names).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    fs::write(dir.join(NAME_TABLE_FILE), ron)
+}
+
+/// Reads the [`NAME_TABLE_FILE`] in `dir`.
+pub fn read_name_table(dir: &Path) -> io::Result<Vec<(String, VoxelId)>> {
+    let ron = fs::read_to_string(dir.join(NAME_TABLE_FILE))?;
+    ron::from_str(&ron).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Chunks saved by [`AutosavePlugin`], read back with their voxel ids translated into the
+/// current registry through the saved [`NAME_TABLE_FILE`].
+#[derive(Debug)]
+pub struct SavedWorld {
+    dir: PathBuf,
+    remap: RemapTable,
+    /// Region files opened so far, `None` for regions nothing was saved in.
+    regions: HashMap<IVec3, Option<RegionFile>>,
+}
+
+impl SavedWorld {
+    /// Opens the save in `dir`, or returns `None` if nothing was saved there. Saved voxel types
+    /// missing from `registry` load as `unknown_voxel`.
+    pub fn open(
+        dir: impl Into<PathBuf>,
+        registry: &VoxelRegistry,
+        unknown_voxel: VoxelId,
+    ) -> io::Result<Option<Self>> {
+        let dir = dir.into();
+        let names = match read_name_table(&dir) {
+            Ok(names) => names,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Some(Self {
+            remap: registry.remap_table(&names).with_fallback(unknown_voxel),
+            dir,
+            regions: HashMap::default(),
+        }))
+    }
+
+    #[inline]
+    pub fn remap(&self) -> &RemapTable {
+        &self.remap
+    }
+
+    /// Reads the chunk saved at `pos`, or `None` if it never was.
+    pub fn read_chunk(&mut self, pos: ChunkPos) -> io::Result<Option<Chunk>> {
+        let region = region::region_of(pos);
+        if !self.regions.contains_key(&region) {
+            // Opening creates missing files, which reading shouldn't.
+            let path = region::region_path(&self.dir, region);
+            let file = if path.exists() {
+                Some(RegionFile::open(path, region)?)
+            } else {
+                None
+            };
+            self.regions.insert(region, file);
+        }
+        let Some(file) = self.regions.get_mut(&region).and_then(Option::as_mut) else {
+            return Ok(None);
+        };
+        let Some(mut data) = file.read_data(pos)? else {
+            return Ok(None);
+        };
+
+        self.remap.apply(data.voxels_mut());
+        Ok(Some(Chunk::from_data(pos.0.as_vec3(), data)))
+    }
+}
+
+/// Queues a snapshot of an unsaved chunk as it is despawned, so unloading never loses edits.
+fn queue_unloaded_chunk(mut world: DeferredWorld, entity: Entity, _component: ComponentId) {
+    if !world
+        .get_resource::<AutosaveConfig>()
+        .is_some_and(|config| config.enabled)
+    {
+        return;
+    }
+    let Some(state) = world.get::<SaveState>(entity).copied() else {
+        return;
+    };
+    let Some(chunk) = world.get::<Chunk>(entity).filter(|_| state.is_unsaved()) else {
+        return;
+    };
+
+    let snapshot = Snapshot {
+        entity,
+        pos: chunk.chunk_pos(),
+        revision: state.revision,
+        data: chunk.data().to_dense(),
+    };
+    if let Some(mut queue) = world.get_resource_mut::<SaveQueue>() {
+        queue.0.push(snapshot);
+    }
+}
+
+/// Writes edited chunks to region files in the background every [`AutosaveConfig::interval`],
+/// and whenever an unsaved chunk is despawned.
+///
+/// Must be added before any [`Chunk`] is spawned, since it hooks into the component's
+/// removal.
+#[derive(Debug, Default)]
+pub struct AutosavePlugin;
+
+impl Plugin for AutosavePlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.world_mut()
+            .register_component_hooks::<Chunk>()
+            .on_remove(queue_unloaded_chunk);
+
+        app.init_resource::<AutosaveConfig>()
+            .init_resource::<VoxelRegistry>()
+            .init_resource::<SaveQueue>()
+            .init_resource::<SaveTask>()
+            .add_systems(
+                Update,
+                (track_unsaved_chunks, autosave, flush_saves)
+                    .chain()
+                    .after(VoxelSet::Generate),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::Voxel;
+    use bevy::ecs::entity::Entity;
+
+    /// An empty directory for the test to save into.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("voxel-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn save(dir: &Path, registry: &VoxelRegistry, pos: ChunkPos, data: ChunkData) {
+        let snapshot = Snapshot {
+            entity: Entity::PLACEHOLDER,
+            pos,
+            revision: 1,
+            data,
+        };
+        let results = write_snapshots(dir.to_owned(), &registry.name_table(), vec![snapshot]);
+        assert!(results.iter().all(|saved| saved.result.is_ok()));
+    }
+
+    #[test]
+    fn saved_chunks_keep_voxel_names_across_registry_reordering() {
+        let dir = temp_dir("reordered-registry");
+        let saved = VoxelRegistry::default();
+        let pos = ChunkPos(IVec3::new(-1, 0, 2));
+        let mut data = ChunkData::cubic(Chunk::SIZE as u32);
+        saved
+            .iter()
+            .for_each(|(id, _)| data.set(id as usize, 0, 0, Voxel::new(id)));
+        save(&dir, &saved, pos, data.clone());
+
+        let types: Vec<_> = saved.iter().skip(1).map(|(_, t)| t.clone()).collect();
+        let mut current = VoxelRegistry::new();
+        types.into_iter().rev().for_each(|voxel_type| {
+            current.register(voxel_type);
+        });
+        let mut world = SavedWorld::open(&dir, &current, 0).unwrap().unwrap();
+        let chunk = world.read_chunk(pos).unwrap().unwrap();
+
+        let name = |registry: &VoxelRegistry, voxel: Option<&Voxel>| {
+            registry.get(voxel.unwrap().id).unwrap().name.clone()
+        };
+        for x in 0..Chunk::SIZE {
+            assert_eq!(
+                name(&current, chunk.get(x, 0, 0)),
+                name(&saved, data.get(x, 0, 0))
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unsaved_chunks_and_regions_read_as_none() {
+        let dir = temp_dir("unsaved");
+        let registry = VoxelRegistry::default();
+        assert!(SavedWorld::open(&dir, &registry, 0).unwrap().is_none());
+
+        save(&dir, &registry, ChunkPos(IVec3::ZERO), ChunkData::cubic(4));
+        let mut world = SavedWorld::open(&dir, &registry, 0).unwrap().unwrap();
+        assert!(world.remap().is_identity());
+        assert!(world.read_chunk(ChunkPos(IVec3::ONE)).unwrap().is_none());
+        assert!(world
+            .read_chunk(ChunkPos(IVec3::splat(100)))
+            .unwrap()
+            .is_none());
+        assert!(!region::region_path(&dir, IVec3::splat(6)).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use bevy::{
+    color::{Color, LinearRgba, Mix},
+    core_pipeline::core_3d::Camera3d,
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    math::Vec3,
+    pbr::{
+        light_consts, AmbientLight, DirectionalLight, DirectionalLightBundle, FogFalloff,
+        FogSettings,
+    },
+    render::camera::ClearColor,
+    time::{Real, Time},
+    transform::components::Transform,
+};
+use std::f32::consts::{PI, TAU};
+
+/// Lowest elevation, in radians, the sunlight is cast from. Keeps shadows from stretching across
+/// the whole world while the sun is at the horizon.
+pub const MIN_SUN_ELEVATION: f32 = 0.15;
+
+/// Sky and lighting applied when the scene is set up.
+#[derive(Debug, Clone, Resource)]
+pub struct SceneConfig {
+    pub clear_color: Color,
+    /// Clear color the sky fades to at night.
+    pub night_clear_color: Color,
+    /// Direction the sunlight travels in. Its horizontal part sets the compass heading of the
+    /// sun's arc.
+    pub sun_direction: Vec3,
+    /// Sun illuminance in lux.
+    pub sun_illuminance: f32,
+    /// Illuminance left once the sun has set.
+    pub night_illuminance: f32,
+    /// Brightness of the [`AmbientLight`] filling in shadows.
+    pub ambient_brightness: f32,
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self {
+            clear_color: Color::BLACK,
+            night_clear_color: Color::srgb(0.01, 0.01, 0.03),
+            sun_direction: Vec3::NEG_ONE.normalize(),
+            sun_illuminance: light_consts::lux::AMBIENT_DAYLIGHT,
+            night_illuminance: light_consts::lux::FULL_MOON_NIGHT,
+            ambient_brightness: AmbientLight::default().brightness,
+        }
+    }
+}
+
+/// Distance fog fading chunks into the sky before the edge of the world, applied to the camera
+/// by [`update_fog`].
+#[derive(Debug, Clone, PartialEq, Resource)]
+pub struct FogConfig {
+    pub enabled: bool,
+    /// Fog color, `None` to follow the [`ClearColor`] through the day so the fog always matches
+    /// the sky.
+    pub color: Option<Color>,
+    /// Distance from the camera, in world units, at which the fog starts.
+    pub start: f32,
+    /// Distance at which the fog hides everything.
+    pub end: f32,
+}
+
+impl Default for FogConfig {
+    fn default() -> Self {
+        // Tuned to the demo world, whose far edge is up to about 70 voxels away.
+        Self {
+            enabled: true,
+            color: None,
+            start: 32.0,
+            end: 72.0,
+        }
+    }
+}
+
+/// Fraction of the day that has passed: `0.25` is sunrise, `0.5` noon and `0.75` sunset.
+#[derive(Debug, Clone, Resource)]
+pub struct TimeOfDay {
+    pub time: f32,
+    /// Real seconds a full day takes.
+    pub day_length: f32,
+    pub paused: bool,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self {
+            time: 0.35,
+            day_length: 600.0,
+            paused: false,
+        }
+    }
+}
+
+impl TimeOfDay {
+    /// Angle of the sun above the horizon, in radians.
+    #[inline]
+    pub fn sun_elevation(&self) -> f32 {
+        (self.time - 0.25) * TAU
+    }
+
+    /// How much of the sun's light reaches the world, fading out around sunset.
+    pub fn daylight(&self) -> f32 {
+        (self.sun_elevation().sin() * 5.0 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+/// Marks the directional light driven by [`TimeOfDay`].
+#[derive(Debug, Component)]
+pub struct Sun;
+
+pub fn setup_scene(mut commands: Commands, config: Res<SceneConfig>) {
+    commands.insert_resource(ClearColor(config.clear_color));
+    commands.insert_resource(AmbientLight {
+        brightness: config.ambient_brightness,
+        ..Default::default()
+    });
+
+    commands.spawn((
+        DirectionalLightBundle {
+            directional_light: DirectionalLight {
+                illuminance: config.sun_illuminance,
+                shadows_enabled: true,
+                ..Default::default()
+            },
+            transform: Transform::default().looking_to(config.sun_direction, Vec3::Y),
+            ..Default::default()
+        },
+        Sun,
+    ));
+}
+
+pub fn advance_time_of_day(time: Res<Time<Real>>, mut time_of_day: ResMut<TimeOfDay>) {
+    if time_of_day.paused {
+        return;
+    }
+
+    let step = time.delta_seconds() / time_of_day.day_length;
+    time_of_day.time = (time_of_day.time + step).fract();
+}
+
+/// Swings the [`Sun`] along its arc, dimming it and the sky at night.
+pub fn update_sun(
+    config: Res<SceneConfig>,
+    time_of_day: Res<TimeOfDay>,
+    mut clear_color: ResMut<ClearColor>,
+    mut sun: Query<(&mut Transform, &mut DirectionalLight), With<Sun>>,
+) {
+    let Ok((mut transform, mut light)) = sun.get_single_mut() else {
+        return;
+    };
+
+    let heading = (config.sun_direction * Vec3::new(1.0, 0.0, 1.0))
+        .try_normalize()
+        .unwrap_or(Vec3::NEG_Z);
+    let elevation = time_of_day.sun_elevation();
+    // Below the horizon the light keeps shining from just above it, at night strength, so it
+    // never lights the world from underneath.
+    let cast = match elevation.sin() < MIN_SUN_ELEVATION.sin() {
+        true if elevation.cos() > 0.0 => MIN_SUN_ELEVATION,
+        true => PI - MIN_SUN_ELEVATION,
+        false => elevation,
+    };
+    let toward_sun = -heading * cast.cos() + Vec3::Y * cast.sin();
+    *transform = Transform::default().looking_to(-toward_sun, Vec3::Y);
+
+    let daylight = time_of_day.daylight();
+    light.illuminance =
+        config.night_illuminance + (config.sun_illuminance - config.night_illuminance) * daylight;
+    light.shadows_enabled = daylight > 0.0;
+
+    let night = LinearRgba::from(config.night_clear_color);
+    clear_color.0 = night.mix(&config.clear_color.into(), daylight).into();
+}
+
+/// Keeps the camera's [`FogSettings`] in line with [`FogConfig`], removing them while disabled.
+/// Runs after [`update_sun`] so the fog follows this frame's sky.
+pub fn update_fog(
+    mut commands: Commands,
+    config: Res<FogConfig>,
+    clear_color: Res<ClearColor>,
+    mut cameras: Query<(Entity, Option<&mut FogSettings>), With<Camera3d>>,
+) {
+    for (entity, fog) in &mut cameras {
+        if !config.enabled {
+            if fog.is_some() {
+                commands.entity(entity).remove::<FogSettings>();
+            }
+            continue;
+        }
+
+        let settings = FogSettings {
+            color: config.color.unwrap_or(clear_color.0),
+            falloff: FogFalloff::Linear {
+                start: config.start,
+                end: config.end,
+            },
+            ..Default::default()
+        };
+        match fog {
+            Some(mut fog) => *fog = settings,
+            None => {
+                commands.entity(entity).insert(settings);
+            }
+        }
+    }
+}
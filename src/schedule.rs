@@ -0,0 +1,54 @@
+use bevy::{
+    app::{App, Plugin, Startup, Update},
+    ecs::schedule::{IntoSystemSetConfigs, SystemSet},
+};
+
+/// The stages of the engine's frame, run in declaration order in both [`Startup`] and
+/// [`Update`], so e.g. a chunk is never meshed before its light has been propagated.
+///
+/// Commands queued in one set are applied before the next starts, so a component inserted while
+/// generating or lighting a chunk is already there once it is meshed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+pub enum VoxelSet {
+    /// Camera movement, voxel editing and debug keys.
+    Input,
+    /// Chunks generated, edited and reported by [`ChunkModified`](crate::chunk::ChunkModified).
+    Generate,
+    /// Voxel light and the scene's sun and fog.
+    Light,
+    /// Meshing tasks started for dirty chunks, and the other views of their voxels: colliders,
+    /// instance lists and raymarched volumes.
+    Mesh,
+    /// Finished meshes swapped in.
+    Render,
+}
+
+/// Orders the [`VoxelSet`]s. Added by every plugin putting systems in them, so it is there
+/// whichever of the [`VoxelEnginePlugins`](crate::VoxelEnginePlugins) an app uses.
+#[derive(Debug, Default)]
+pub struct VoxelSchedulePlugin;
+
+impl Plugin for VoxelSchedulePlugin {
+    fn build(&self, app: &mut App) {
+        let sets = || {
+            (
+                VoxelSet::Input,
+                VoxelSet::Generate,
+                VoxelSet::Light,
+                VoxelSet::Mesh,
+                VoxelSet::Render,
+            )
+                .chain()
+        };
+
+        app.configure_sets(Startup, sets())
+            .configure_sets(Update, sets());
+    }
+}
+
+/// Adds [`VoxelSchedulePlugin`] unless another plugin already did.
+pub fn add_voxel_sets(app: &mut App) {
+    if !app.is_plugin_added::<VoxelSchedulePlugin>() {
+        app.add_plugins(VoxelSchedulePlugin);
+    }
+}
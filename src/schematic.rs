@@ -0,0 +1,165 @@
+use crate::{chunk::Chunk, registry::VoxelRegistry, voxel::Voxel, world::VoxelWorld};
+use bevy::{ecs::system::Query, log::warn, math::IVec3};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, fs, io, path::Path};
+
+/// A structure of voxels placed relative to an origin, e.g. a building or tree, stored as RON:
+///
+/// ```ron
+/// (
+///     voxels: [
+///         (pos: (0, 0, 0), voxel: "log"),
+///         (pos: (0, 1, 0), voxel: "leaves"),
+///     ],
+/// )
+/// ```
+///
+/// Voxels are named rather than numbered so schematics survive registry changes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schematic {
+    pub voxels: Vec<Placement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Placement {
+    /// Offset from the stamp origin, in voxels.
+    pub pos: [i32; 3],
+    /// [`VoxelType::name`](crate::registry::VoxelType::name) of the voxel.
+    pub voxel: String,
+}
+
+impl Schematic {
+    /// Writes every placement at `origin` plus its offset, returning how many landed in loaded
+    /// chunks. Each touched chunk reports the placements in its next
+    /// [`ChunkModified`](crate::chunk::ChunkModified).
+    /// Placements of voxel types missing from `registry` are skipped with a warning.
+    pub fn stamp(
+        &self,
+        world: &VoxelWorld,
+        chunks: &mut Query<&mut Chunk>,
+        registry: &VoxelRegistry,
+        origin: IVec3,
+    ) -> usize {
+        self.voxels
+            .iter()
+            .filter(|placement| {
+                let Some(id) = registry.id(&placement.voxel) else {
+                    warn!("schematic places unknown voxel type {:?}", placement.voxel);
+                    return false;
+                };
+                let position = origin + IVec3::from_array(placement.pos);
+                world.set_voxel(chunks, position, Voxel::new(id))
+            })
+            .count()
+    }
+}
+
+#[derive(Debug)]
+pub enum SchematicError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "failed to read schematic: {err}"),
+            Self::Parse(err) => write!(f, "invalid schematic: {err}"),
+        }
+    }
+}
+
+impl Error for SchematicError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for SchematicError {
+    #[inline]
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for SchematicError {
+    #[inline]
+    fn from(err: ron::error::SpannedError) -> Self {
+        Self::Parse(err)
+    }
+}
+
+/// Reads a RON [`Schematic`] from `path`.
+pub fn load_schematic(path: impl AsRef<Path>) -> Result<Schematic, SchematicError> {
+    let source = fs::read_to_string(path)?;
+    Ok(ron::from_str(&source)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::ChunkPos;
+    use bevy::ecs::{entity::Entity, system::SystemState, world::World};
+
+    /// The sample tree stamped into empty chunks from `-1` to `0` on every axis, at the corner
+    /// they share, returning how many voxels landed and the chunks by position.
+    fn stamp_tree(extra: &[Placement]) -> (World, usize, Vec<(IVec3, Entity)>) {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/schematics/tree.ron");
+        let mut schematic = load_schematic(path).unwrap();
+        schematic.voxels.extend_from_slice(extra);
+
+        let mut world = World::new();
+        let mut voxel_world = VoxelWorld::default();
+        let chunks: Vec<_> = (0..8)
+            .map(|i| {
+                let pos = IVec3::new(i & 1, i >> 1 & 1, i >> 2) - IVec3::ONE;
+                let mut chunk = Chunk::new(pos.as_vec3());
+                chunk.take_modified(Entity::PLACEHOLDER);
+                let entity = world.spawn(chunk).id();
+                voxel_world.insert_chunk(ChunkPos(pos), entity);
+                (pos, entity)
+            })
+            .collect();
+
+        let registry = VoxelRegistry::default();
+        let mut state = SystemState::<Query<&mut Chunk>>::new(&mut world);
+        let stamped = schematic.stamp(
+            &voxel_world,
+            &mut state.get_mut(&mut world),
+            &registry,
+            IVec3::ZERO,
+        );
+        (world, stamped, chunks)
+    }
+
+    #[test]
+    fn stamping_across_chunks_modifies_every_touched_chunk() {
+        let (mut world, stamped, chunks) = stamp_tree(&[]);
+        assert_eq!(stamped, 49);
+
+        let registry = VoxelRegistry::default();
+        let (_, origin) = chunks.iter().find(|(pos, _)| *pos == IVec3::ZERO).unwrap();
+        let trunk = world.get::<Chunk>(*origin).unwrap();
+        assert_eq!(trunk.get(0, 4, 0).unwrap().id, registry.id("log").unwrap());
+
+        // The whole tree is above the origin, its canopy reaching into all four upper chunks.
+        for (pos, entity) in chunks {
+            let mut chunk = world.get_mut::<Chunk>(entity).unwrap();
+            let modified = chunk.take_modified(entity).is_some();
+            assert_eq!(modified, pos.y == 0, "chunk {pos}");
+        }
+    }
+
+    #[test]
+    fn unknown_voxels_are_skipped() {
+        let unknown = Placement {
+            pos: [0, 6, 0],
+            voxel: "unobtainium".to_owned(),
+        };
+        let (_, stamped, _) = stamp_tree(&[unknown]);
+        assert_eq!(stamped, 49);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    chunk::Chunk,
+    mesh::ChunkMeshes,
+    schedule::{self, VoxelSet},
+};
+use bevy::{
+    app::{App, Last, Plugin, Update},
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    ecs::{
+        component::Component,
+        entity::Entity,
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Query, ResMut, Resource},
+    },
+    render::mesh::Mesh,
+    utils::HashMap,
+};
+use std::{fmt::Write, mem, time::Duration};
+
+pub const CHUNKS_MESHED: DiagnosticPath = DiagnosticPath::const_new("voxel/chunks_meshed");
+pub const LOADED_CHUNKS: DiagnosticPath = DiagnosticPath::const_new("voxel/loaded_chunks");
+pub const AVERAGE_TRIANGLES: DiagnosticPath =
+    DiagnosticPath::const_new("voxel/average_triangles_per_chunk");
+pub const MAX_TRIANGLES: DiagnosticPath =
+    DiagnosticPath::const_new("voxel/max_triangles_per_chunk");
+pub const MESH_BUILD_TIME: DiagnosticPath = DiagnosticPath::const_new("voxel/mesh_build_time");
+pub const REMESH_BACKLOG: DiagnosticPath = DiagnosticPath::const_new("voxel/remesh_backlog");
+pub const WORLD_TRIANGLES: DiagnosticPath = DiagnosticPath::const_new("voxel/world_triangles");
+pub const WORLD_VERTEX_BYTES: DiagnosticPath =
+    DiagnosticPath::const_new("voxel/world_vertex_bytes");
+
+/// Size and cost of a chunk's current mesh, every pass combined, kept on the chunk entity.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Component)]
+pub struct ChunkMeshStats {
+    pub vertices: usize,
+    pub indices: usize,
+    /// Triangle pairs, one per face or merged quad.
+    pub quads: usize,
+    /// Size of the vertex buffers, which packed meshes shrink by leaving normals, UVs and colors
+    /// to the shader.
+    pub vertex_bytes: usize,
+    pub build_time: Duration,
+}
+
+impl ChunkMeshStats {
+    /// Counts the vertices and indices of every pass of `meshes`.
+    pub fn new(meshes: &ChunkMeshes, build_time: Duration) -> Self {
+        let indices = |mesh: &Mesh| mesh.indices().map_or(0, |indices| indices.len());
+        let indices = indices(&meshes.opaque) + indices(&meshes.translucent);
+        let bytes = |mesh: &Mesh| mesh.count_vertices() * mesh.get_vertex_size() as usize;
+
+        Self {
+            vertices: meshes.opaque.count_vertices() + meshes.translucent.count_vertices(),
+            indices,
+            quads: indices / 6,
+            vertex_bytes: bytes(&meshes.opaque) + bytes(&meshes.translucent),
+            build_time,
+        }
+    }
+
+    #[inline]
+    pub fn triangles(&self) -> usize {
+        self.indices / 3
+    }
+}
+
+/// Running totals of chunk meshing cost.
+#[derive(Debug, Default, Clone, Resource)]
+pub struct MeshStats {
+    pub chunks_meshed: u64,
+    pub total_triangles: u64,
+    pub max_triangles: u64,
+    /// Chunk whose mesh had [`max_triangles`](Self::max_triangles).
+    pub worst_chunk: Option<Entity>,
+    pub total_build_time: Duration,
+    /// Triangles of every chunk's current mesh, as of the last update.
+    pub world_triangles: u64,
+    /// Vertex buffer bytes of every chunk's current mesh, as of the last update.
+    pub world_vertex_bytes: u64,
+    /// Chunks loaded as of the last update.
+    pub loaded_chunks: usize,
+    /// Dirty chunks left waiting by the [`MeshingBudget`](crate::mesh::MeshingBudget) this frame.
+    pub remesh_backlog: usize,
+    /// Totals per [`ChunkMesher::name`](crate::mesh::ChunkMesher::name).
+    pub per_mesher: HashMap<String, MesherStats>,
+}
+
+/// Running totals of the chunks meshed by one [`ChunkMesher`](crate::mesh::ChunkMesher).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MesherStats {
+    pub chunks: u64,
+    pub vertices: u64,
+    pub build_time: Duration,
+}
+
+impl MeshStats {
+    /// Records the mesh `mesher` built for the chunk on `chunk`.
+    pub fn record(&mut self, chunk: Entity, mesher: &str, stats: &ChunkMeshStats) {
+        let triangles = stats.triangles() as u64;
+        self.chunks_meshed += 1;
+        self.total_triangles += triangles;
+        if triangles > self.max_triangles || self.worst_chunk.is_none() {
+            self.max_triangles = triangles;
+            self.worst_chunk = Some(chunk);
+        }
+        self.total_build_time += stats.build_time;
+
+        let totals = self.per_mesher.entry_ref(mesher).or_default();
+        totals.chunks += 1;
+        totals.vertices += stats.vertices as u64;
+        totals.build_time += stats.build_time;
+    }
+
+    /// Mean time to build one chunk's mesh.
+    pub fn average_build_time(&self) -> Duration {
+        match u32::try_from(self.chunks_meshed) {
+            Ok(0) => Duration::ZERO,
+            Ok(chunks) => self.total_build_time / chunks,
+            Err(_) => self.total_build_time.div_f64(self.chunks_meshed as f64),
+        }
+    }
+
+    /// A few lines summing up meshing so far, for logging.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} chunks meshed, {} triangles and {:.1} KiB of vertices in the world\n\
+             average {:.1} triangles and {:.3} ms per chunk, worst {} triangles ({:?})",
+            self.chunks_meshed,
+            self.world_triangles,
+            self.world_vertex_bytes as f64 / 1024.0,
+            self.average_triangles(),
+            self.average_build_time().as_secs_f64() * 1000.0,
+            self.max_triangles,
+            self.worst_chunk,
+        )
+    }
+
+    /// A table of the average vertices and build time per chunk of every mesher that has meshed
+    /// a chunk, sorted by name.
+    pub fn mesher_comparison(&self) -> String {
+        let mut names: Vec<_> = self.per_mesher.keys().collect();
+        names.sort();
+
+        let mut table = format!(
+            "{:<12} {:>8} {:>14} {:>12}",
+            "mesher", "chunks", "vertices/chunk", "ms/chunk"
+        );
+        for name in names {
+            let totals = self.per_mesher[name];
+            let chunks = totals.chunks.max(1) as f64;
+            let _ = write!(
+                table,
+                "\n{:<12} {:>8} {:>14.0} {:>12.3}",
+                name,
+                totals.chunks,
+                totals.vertices as f64 / chunks,
+                totals.build_time.as_secs_f64() * 1000.0 / chunks,
+            );
+        }
+
+        table
+    }
+
+    pub fn average_triangles(&self) -> f64 {
+        if self.chunks_meshed == 0 {
+            0.0
+        } else {
+            self.total_triangles as f64 / self.chunks_meshed as f64
+        }
+    }
+}
+
+/// Time the chunk pipeline spent in each stage of a frame, e.g. generating, lighting, queueing
+/// meshing tasks and swapping in their meshes, recorded by the systems running the stages.
+/// Meshing tasks record the time they took on worker threads once they finish.
+///
+/// [`MeshStatsPlugin`] rolls the stages over at the end of every frame.
+#[derive(Debug, Default, Clone, Resource)]
+pub struct FrameTimings {
+    current: Vec<(&'static str, Duration)>,
+    last: Vec<(&'static str, Duration)>,
+}
+
+impl FrameTimings {
+    /// Adds `elapsed` to the time `stage` took this frame.
+    pub fn record(&mut self, stage: &'static str, elapsed: Duration) {
+        match self.current.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, total)) => *total += elapsed,
+            None => self.current.push((stage, elapsed)),
+        }
+    }
+
+    /// Time of every stage that ran last frame, in the order they first ran.
+    #[inline]
+    pub fn last_frame(&self) -> &[(&'static str, Duration)] {
+        &self.last
+    }
+}
+
+/// Ends the frame's [`FrameTimings`], keeping them for [`FrameTimings::last_frame`].
+pub fn roll_frame_timings(mut timings: ResMut<FrameTimings>) {
+    let timings = &mut *timings;
+    timings.last = mem::take(&mut timings.current);
+}
+
+/// Adds [`MeshStats`] and publishes it as diagnostics, so e.g. `LogDiagnosticsPlugin` can
+/// print it, and keeps [`FrameTimings`].
+#[derive(Debug, Default)]
+pub struct MeshStatsPlugin;
+
+impl Plugin for MeshStatsPlugin {
+    fn build(&self, app: &mut App) {
+        schedule::add_voxel_sets(app);
+        app.init_resource::<MeshStats>()
+            .init_resource::<FrameTimings>()
+            .register_diagnostic(Diagnostic::new(CHUNKS_MESHED))
+            .register_diagnostic(Diagnostic::new(LOADED_CHUNKS))
+            .register_diagnostic(Diagnostic::new(AVERAGE_TRIANGLES))
+            .register_diagnostic(Diagnostic::new(MAX_TRIANGLES))
+            .register_diagnostic(Diagnostic::new(MESH_BUILD_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(REMESH_BACKLOG))
+            .register_diagnostic(Diagnostic::new(WORLD_TRIANGLES))
+            .register_diagnostic(Diagnostic::new(WORLD_VERTEX_BYTES))
+            .add_systems(Update, update_mesh_stats.after(VoxelSet::Render))
+            .add_systems(Last, roll_frame_timings);
+    }
+}
+
+/// Updates the loaded chunk count and the world's triangles and vertex bytes, and publishes the
+/// stats as diagnostics.
+pub fn update_mesh_stats(
+    mut stats: ResMut<MeshStats>,
+    mut diagnostics: Diagnostics,
+    chunks: Query<Option<&ChunkMeshStats>, With<Chunk>>,
+) {
+    stats.loaded_chunks = chunks.iter().count();
+    stats.world_triangles = chunks
+        .iter()
+        .flatten()
+        .map(|chunk| chunk.triangles() as u64)
+        .sum();
+    stats.world_vertex_bytes = chunks
+        .iter()
+        .flatten()
+        .map(|chunk| chunk.vertex_bytes as u64)
+        .sum();
+
+    diagnostics.add_measurement(&CHUNKS_MESHED, || stats.chunks_meshed as f64);
+    diagnostics.add_measurement(&LOADED_CHUNKS, || stats.loaded_chunks as f64);
+    diagnostics.add_measurement(&AVERAGE_TRIANGLES, || stats.average_triangles());
+    diagnostics.add_measurement(&MAX_TRIANGLES, || stats.max_triangles as f64);
+    diagnostics.add_measurement(&MESH_BUILD_TIME, || {
+        stats.total_build_time.as_secs_f64() * 1000.0
+    });
+    diagnostics.add_measurement(&REMESH_BACKLOG, || stats.remesh_backlog as f64);
+    diagnostics.add_measurement(&WORLD_TRIANGLES, || stats.world_triangles as f64);
+    diagnostics.add_measurement(&WORLD_VERTEX_BYTES, || stats.world_vertex_bytes as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        atlas::AtlasLayout,
+        face::Face,
+        mesh::{self, MeshBuffers},
+        registry::FaceTextures,
+    };
+    use bevy::{
+        math::{Rect, Vec3},
+        render::render_resource::VertexFormat,
+    };
+
+    #[test]
+    fn chunk_stats_match_the_mesh_attributes() {
+        let mut packed = MeshBuffers::default();
+        for face in [Face::PosY, Face::NegX] {
+            mesh::push_face(&mut packed, Vec3::ZERO, face, Rect::new(0.0, 0.0, 1.0, 1.0));
+        }
+        packed.tiles.resize(packed.positions.len(), 0);
+        let meshes = ChunkMeshes {
+            opaque: mesh::generate_cube(FaceTextures::all(0), &AtlasLayout::default()),
+            translucent: packed.build_packed(),
+        };
+        let stats = ChunkMeshStats::new(&meshes, Duration::from_millis(2));
+
+        let [opaque, translucent] = [&meshes.opaque, &meshes.translucent];
+        let attribute_bytes = |mesh: &Mesh| -> usize {
+            mesh.attributes()
+                .map(|(_, values)| values.len() * VertexFormat::from(values).size() as usize)
+                .sum()
+        };
+        assert_eq!(stats.vertices, 24 + 8);
+        assert_eq!(
+            stats.vertices,
+            opaque.count_vertices() + translucent.count_vertices()
+        );
+        assert_eq!(stats.indices, 36 + 12);
+        assert_eq!(stats.quads, 6 + 2);
+        assert_eq!(stats.triangles(), 12 + 4);
+        assert_eq!(
+            stats.vertex_bytes,
+            attribute_bytes(opaque) + attribute_bytes(translucent)
+        );
+    }
+
+    #[test]
+    fn mesh_stats_keep_the_worst_chunk_and_per_mesher_totals() {
+        let chunk = |index| Entity::from_raw(index);
+        let stats = |quads: usize| ChunkMeshStats {
+            vertices: quads * 4,
+            indices: quads * 6,
+            quads,
+            vertex_bytes: 0,
+            build_time: Duration::from_millis(quads as u64),
+        };
+        let mut totals = MeshStats::default();
+        totals.record(chunk(0), "culled", &stats(3));
+        totals.record(chunk(1), "greedy", &stats(5));
+        totals.record(chunk(2), "culled", &stats(1));
+
+        assert_eq!(totals.chunks_meshed, 3);
+        assert_eq!(totals.total_triangles, 18);
+        assert_eq!(
+            (totals.max_triangles, totals.worst_chunk),
+            (10, Some(chunk(1)))
+        );
+        assert_eq!(totals.average_build_time(), Duration::from_millis(3));
+        let culled = totals.per_mesher["culled"];
+        assert_eq!((culled.chunks, culled.vertices), (2, 16));
+    }
+}
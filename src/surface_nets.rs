@@ -0,0 +1,204 @@
+use crate::{
+    chunk::VoxelStorage,
+    face::Face,
+    mesh::{self, ChunkMeshBuffers, ChunkMesher, ChunkNeighborhood, MeshBuffers, MeshContext},
+    registry::{Transparency, VoxelRegistry},
+};
+use bevy::math::{IVec3, Vec3, Vec4};
+
+/// Meshes chunks as a smooth surface with naive surface nets, for non-blocky terrain.
+///
+/// Voxel occupancy is the scalar field, sampled at voxel centers: opaque and cutout voxels are
+/// solid, everything else, including liquids and translucent voxels, is left out of the mesh.
+/// Every cell between eight samples the surface passes through gets one vertex, at the average
+/// of its crossing edges' midpoints, with a normal from the differences of its corners. Each
+/// edge between a solid and an empty sample in the chunk then gets a quad joining the four cells
+/// around it, textured with the solid voxel's face in that direction.
+///
+/// Cells on the chunk border read the neighbors' voxels, including diagonal ones, so surfaces
+/// continue across chunks without gaps. Packed vertices, as used by the texture array material,
+/// can only hold axis aligned normals, so the surface shades smoothly under the atlas material
+/// only.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SurfaceNets;
+
+impl ChunkMesher for SurfaceNets {
+    #[inline]
+    fn name(&self) -> &str {
+        "surface nets"
+    }
+
+    fn mesh(&self, chunks: ChunkNeighborhood, context: &MeshContext, out: &mut ChunkMeshBuffers) {
+        build_surface_nets_mesh(chunks, context, &mut out.opaque);
+    }
+}
+
+/// Appends the smooth surface of `chunks.center` to `builder`, see [`SurfaceNets`].
+pub fn build_surface_nets_mesh(
+    chunks: ChunkNeighborhood,
+    context: &MeshContext,
+    builder: &mut MeshBuffers,
+) {
+    let dims = chunks.center.dims().as_ivec3();
+    // Samples reach one voxel past the chunk on every side, for the cells around border edges.
+    let samples = Grid::new(dims + 2, |pos| {
+        is_solid(context.registry, chunks, pos - IVec3::ONE)
+    });
+    let solid = |pos: IVec3| samples.get(pos + IVec3::ONE);
+    let cells = Grid::new(dims + 1, |cell| cell_vertex(cell - IVec3::ONE, solid));
+    let vertex = |cell: IVec3| cells.get(cell + IVec3::ONE);
+
+    for z in 0..dims.z {
+        for y in 0..dims.y {
+            for x in 0..dims.x {
+                let pos = IVec3::new(x, y, z);
+                for axis in 0..3 {
+                    let next = pos + unit(axis);
+                    let (inside, outside) = (solid(pos), solid(next));
+                    if inside == outside {
+                        continue;
+                    }
+
+                    let (b, c) = (unit((axis + 1) % 3), unit((axis + 2) % 3));
+                    let corners = [pos, pos - b, pos - b - c, pos - c].map(vertex);
+                    let Some(mut corners) = corners.into_iter().collect::<Option<Vec<_>>>() else {
+                        continue;
+                    };
+                    // The quad faces from the solid sample towards the empty one.
+                    if outside {
+                        corners.reverse();
+                    }
+
+                    let (voxel_pos, normal) = if inside {
+                        (pos, unit(axis))
+                    } else {
+                        (next, -unit(axis))
+                    };
+                    let Some((voxel, voxel_type)) = chunks
+                        .get(voxel_pos)
+                        .and_then(|voxel| Some((*voxel, context.registry.get(voxel.id)?)))
+                    else {
+                        continue;
+                    };
+                    let face = Face::from_offset(normal).unwrap_or(Face::PosY);
+                    let texture = mesh::voxel_face_texture(voxel_type, voxel, face);
+
+                    let base = builder.positions.len() as u32;
+                    let transform = mesh::voxel_uv_transform(voxel_type, voxel, face);
+                    let tangent = face.transformed_tangent(transform);
+                    let uvs = face.transformed_uvs(context.uvs.rect(texture), transform);
+                    for (corner, uv) in corners.iter().zip(uvs) {
+                        builder.positions.push(corner.position.to_array());
+                        builder.normals.push(corner.normal.to_array());
+                        builder
+                            .tangents
+                            .push(smooth_tangent(tangent, corner.normal).to_array());
+                        builder.uvs.push(uv.to_array());
+                    }
+                    builder.tiles.extend([texture; 4]);
+                    builder.colors.extend([[1.0; 4]; 4]);
+                    builder.ao.extend([3; 4]);
+                    builder.indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+                }
+            }
+        }
+    }
+}
+
+/// Whether the voxel at `pos` contributes to the surface. Unloaded neighbors count as empty, so
+/// the world's edge is closed off.
+fn is_solid(registry: &VoxelRegistry, chunks: ChunkNeighborhood, pos: IVec3) -> bool {
+    chunks
+        .get(pos)
+        .and_then(|voxel| registry.get(voxel.id))
+        .is_some_and(|voxel_type| {
+            matches!(
+                voxel_type.transparency,
+                Transparency::Opaque | Transparency::Cutout
+            ) && !voxel_type.liquid
+        })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CellVertex {
+    position: Vec3,
+    normal: Vec3,
+}
+
+/// The vertex of the cell spanning the samples `cell..=cell + 1`, if the surface passes through
+/// it.
+fn cell_vertex(cell: IVec3, solid: impl Fn(IVec3) -> bool) -> Option<CellVertex> {
+    let corners: [bool; 8] = std::array::from_fn(|i| solid(cell + corner(i)));
+    if corners.iter().all(|c| *c == corners[0]) {
+        return None;
+    }
+
+    let (mut sum, mut crossings) = (Vec3::ZERO, 0);
+    let mut gradient = Vec3::ZERO;
+    for (i, inside) in corners.iter().enumerate() {
+        for axis in 0..3 {
+            let bit = 1 << axis;
+            if i & bit != 0 {
+                continue;
+            }
+
+            let other = corners[i | bit];
+            if *inside != other {
+                sum += corner(i).as_vec3() + unit(axis).as_vec3() * 0.5;
+                crossings += 1;
+            }
+            gradient[axis] += other as u32 as f32 - *inside as u32 as f32;
+        }
+    }
+
+    Some(CellVertex {
+        position: cell.as_vec3() + sum / crossings as f32,
+        // Occupancy rises into the solid, so the surface faces down the gradient.
+        normal: (-gradient).try_normalize().unwrap_or(Vec3::Y),
+    })
+}
+
+/// The face's `tangent` made orthogonal to a smooth `normal`, keeping its handedness.
+fn smooth_tangent(tangent: Vec4, normal: Vec3) -> Vec4 {
+    let flat = tangent.truncate();
+    (flat - normal * normal.dot(flat))
+        .try_normalize()
+        .unwrap_or_else(|| normal.any_orthonormal_vector())
+        .extend(tangent.w)
+}
+
+/// Offset of corner `i` of a cell, one bit per axis.
+#[inline]
+fn corner(i: usize) -> IVec3 {
+    IVec3::new(i as i32 & 1, (i as i32 >> 1) & 1, (i as i32 >> 2) & 1)
+}
+
+#[inline]
+fn unit(axis: usize) -> IVec3 {
+    let mut unit = IVec3::ZERO;
+    unit[axis] = 1;
+    unit
+}
+
+/// Values computed once per coordinate of a box starting at the origin.
+struct Grid<T> {
+    values: Vec<T>,
+    dims: IVec3,
+}
+
+impl<T: Copy> Grid<T> {
+    fn new(dims: IVec3, value: impl Fn(IVec3) -> T) -> Self {
+        let values = (0..dims.z)
+            .flat_map(|z| (0..dims.y).flat_map(move |y| (0..dims.x).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| value(IVec3::new(x, y, z)))
+            .collect();
+
+        Self { values, dims }
+    }
+
+    #[inline]
+    fn get(&self, pos: IVec3) -> T {
+        let index = (pos.z * self.dims.y + pos.y) * self.dims.x + pos.x;
+        self.values[index as usize]
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{
+    chunk::Chunk,
+    registry::{Transparency, VoxelRegistry},
+    voxel::Voxel,
+    world::VoxelWorld,
+};
+use bevy::{
+    ecs::system::{Query, Res, ResMut, Resource},
+    math::IVec3,
+    time::Time,
+    utils::HashMap,
+};
+use std::{fmt, sync::Arc, time::Duration};
+
+/// Dirt waits this long before turning into grass.
+pub const GRASS_SPREAD_DELAY: Duration = Duration::from_secs(3);
+
+/// Callback invoked when a scheduled tick for a voxel of some type comes due.
+#[derive(Clone)]
+pub struct TickHandler(Arc<dyn Fn(&mut TickContext) + Send + Sync>);
+
+impl TickHandler {
+    #[inline]
+    pub fn new(handler: impl Fn(&mut TickContext) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(handler))
+    }
+}
+
+impl fmt::Debug for TickHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TickHandler(..)")
+    }
+}
+
+/// Tick behavior of a [`VoxelType`](crate::registry::VoxelType).
+#[derive(Debug, Clone)]
+pub struct VoxelTick {
+    /// How long after a neighboring voxel changes the tick runs.
+    pub delay: Duration,
+    pub handler: TickHandler,
+}
+
+/// World positions waiting for a tick, keyed to the [`Time<Fixed>`](bevy::time::Fixed) elapsed
+/// time they are due at.
+///
+/// Ticks whose voxel is no longer loaded when they come due are dropped; nothing about the
+/// queue is persisted, so loading a chunk doesn't resume ticks scheduled before it unloaded.
+#[derive(Debug, Default, Resource)]
+pub struct VoxelTickQueue {
+    due: HashMap<IVec3, Duration>,
+    now: Duration,
+}
+
+impl VoxelTickQueue {
+    /// Schedules a tick for `pos`, keeping the earlier time if one is already scheduled.
+    pub fn schedule_tick(&mut self, pos: IVec3, after: Duration) {
+        let due = self.now + after;
+        self.due
+            .entry(pos)
+            .and_modify(|scheduled| *scheduled = (*scheduled).min(due))
+            .or_insert(due);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.due.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.due.is_empty()
+    }
+
+    /// Removes and returns every position due by `now`, in a stable order.
+    fn drain_due(&mut self, now: Duration) -> Vec<IVec3> {
+        self.now = now;
+
+        let mut due: Vec<_> = self
+            .due
+            .iter()
+            .filter(|(_, due)| **due <= now)
+            .map(|(pos, _)| *pos)
+            .collect();
+        due.sort_unstable_by_key(|pos| (pos.x, pos.y, pos.z));
+        due.iter().for_each(|pos| {
+            self.due.remove(pos);
+        });
+
+        due
+    }
+}
+
+/// World access handed to a [`TickHandler`].
+pub struct TickContext<'a, 'w, 's> {
+    /// Position of the ticking voxel.
+    pub position: IVec3,
+    /// The ticking voxel as it was when the tick came due.
+    pub voxel: Voxel,
+    pub registry: &'a VoxelRegistry,
+    world: &'a VoxelWorld,
+    chunks: &'a mut Query<'w, 's, &'static mut Chunk>,
+    queue: &'a mut VoxelTickQueue,
+}
+
+impl TickContext<'_, '_, '_> {
+    /// Reads a voxel anywhere in the loaded world.
+    pub fn get(&self, position: IVec3) -> Option<Voxel> {
+        self.world.voxel(&self.chunks.to_readonly(), position)
+    }
+
+    /// Writes a voxel anywhere in the loaded world, scheduling ticks for its neighbors.
+    pub fn set(&mut self, position: IVec3, voxel: Voxel) {
+        self.world.set_voxel(self.chunks, position, voxel);
+
+        let (world, chunks) = (self.world, self.chunks.to_readonly());
+        notify_neighbors(self.queue, self.registry, position, |neighbor| {
+            world.voxel(&chunks, neighbor)
+        });
+    }
+
+    #[inline]
+    pub fn schedule_tick(&mut self, position: IVec3, after: Duration) {
+        self.queue.schedule_tick(position, after);
+    }
+}
+
+/// Schedules ticks for the 26 voxels around `position` whose type has tick behavior.
+pub fn notify_neighbors(
+    queue: &mut VoxelTickQueue,
+    registry: &VoxelRegistry,
+    position: IVec3,
+    voxel_at: impl Fn(IVec3) -> Option<Voxel>,
+) {
+    neighbors(position).for_each(|neighbor| {
+        let tick = voxel_at(neighbor).and_then(|voxel| registry.get(voxel.id)?.tick.as_ref());
+
+        if let Some(tick) = tick {
+            queue.schedule_tick(neighbor, tick.delay);
+        }
+    });
+}
+
+/// Runs on [`FixedUpdate`](bevy::app::FixedUpdate), dispatching due ticks to their voxel type's
+/// handler.
+pub fn run_ticks(
+    time: Res<Time>,
+    registry: Res<VoxelRegistry>,
+    world: Res<VoxelWorld>,
+    mut queue: ResMut<VoxelTickQueue>,
+    mut chunks: Query<&'static mut Chunk>,
+) {
+    for position in queue.drain_due(time.elapsed()) {
+        let Some(voxel) = world.voxel(&chunks.to_readonly(), position) else {
+            continue;
+        };
+        let Some(tick) = registry.get(voxel.id).and_then(|t| t.tick.clone()) else {
+            continue;
+        };
+
+        let mut context = TickContext {
+            position,
+            voxel,
+            registry: &registry,
+            world: &world,
+            chunks: &mut chunks,
+            queue: &mut queue,
+        };
+        (tick.handler.0)(&mut context);
+    }
+}
+
+/// Dirt with air above it turns into grass when grass is within one voxel.
+pub fn spread_grass(context: &mut TickContext) {
+    let Some(grass) = context.registry.id("grass") else {
+        return;
+    };
+
+    let covered = context
+        .get(context.position + IVec3::Y)
+        .and_then(|above| context.registry.get(above.id))
+        .is_some_and(|above| above.transparency != Transparency::Invisible);
+    let near_grass = neighbors(context.position)
+        .any(|neighbor| context.get(neighbor).is_some_and(|voxel| voxel.id == grass));
+
+    if !covered && near_grass {
+        context.set(context.position, Voxel::new(grass));
+    }
+}
+
+fn neighbors(position: IVec3) -> impl Iterator<Item = IVec3> {
+    (-1..=1)
+        .flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| IVec3::new(x, y, z))))
+        .filter(|offset| *offset != IVec3::ZERO)
+        .map(move |offset| position + offset)
+}
@@ -1,9 +1,150 @@
-#[repr(transparent)]
-#[derive(Debug, Clone, Copy, PartialEq)]
+use crate::face::{Face, UvTransform};
+use bevy::math::IVec3;
+
+/// Integer type identifying a voxel's [`VoxelType`](crate::registry::VoxelType).
+pub type VoxelId = u16;
+
+/// A voxel, identified by its type. Defaults to [`Voxel::AIR`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Voxel {
-    pub id: u8,
+    pub id: VoxelId,
+    orientation: Orientation,
 }
 
 impl Voxel {
     pub const SIZE: f32 = 1.0;
+    /// The empty voxel, id `0` in every [`VoxelRegistry`](crate::registry::VoxelRegistry).
+    pub const AIR: Self = Self::new(0);
+
+    /// An upright voxel of type `id`.
+    #[inline]
+    pub const fn new(id: VoxelId) -> Self {
+        Self {
+            id,
+            orientation: Orientation::PosY,
+        }
+    }
+
+    /// Same as [`new`](Self::new), for call sites that read better naming the id.
+    #[inline]
+    pub const fn with_id(id: VoxelId) -> Self {
+        Self::new(id)
+    }
+
+    /// The same voxel turned to `orientation`.
+    #[inline]
+    pub const fn with_orientation(self, orientation: Orientation) -> Self {
+        Self {
+            orientation,
+            ..self
+        }
+    }
+
+    /// Which way the voxel is turned. Only meshed for
+    /// [`directional`](crate::registry::VoxelType::directional) voxel types.
+    #[inline]
+    pub const fn orientation(self) -> Orientation {
+        self.orientation
+    }
+}
+
+impl From<VoxelId> for Voxel {
+    #[inline]
+    fn from(id: VoxelId) -> Self {
+        Self::new(id)
+    }
+}
+
+/// Which face of the world a voxel's top faces, e.g. the axis a log lies along. Each orientation
+/// turns the voxel the shortest way from upright, a quarter turn for the sides and a half turn
+/// about x for [`NegY`](Self::NegY).
+#[repr(u8)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// Upright.
+    #[default]
+    PosY,
+    NegY,
+    PosX,
+    NegX,
+    PosZ,
+    NegZ,
+}
+
+impl Orientation {
+    /// Every orientation, indexed by its discriminant.
+    pub const ALL: [Self; 6] = [
+        Self::PosY,
+        Self::NegY,
+        Self::PosX,
+        Self::NegX,
+        Self::PosZ,
+        Self::NegZ,
+    ];
+
+    /// The face the voxel's top faces.
+    #[inline]
+    pub const fn up(self) -> Face {
+        match self {
+            Self::PosY => Face::PosY,
+            Self::NegY => Face::NegY,
+            Self::PosX => Face::PosX,
+            Self::NegX => Face::NegX,
+            Self::PosZ => Face::PosZ,
+            Self::NegZ => Face::NegZ,
+        }
+    }
+
+    /// The orientation turning the other way, back to upright.
+    #[inline]
+    pub const fn inverse(self) -> Self {
+        match self {
+            Self::PosX => Self::NegX,
+            Self::NegX => Self::PosX,
+            Self::PosZ => Self::NegZ,
+            Self::NegZ => Self::PosZ,
+            upright_or_flipped => upright_or_flipped,
+        }
+    }
+
+    /// Turns a direction in the voxel's own frame into the world's.
+    #[inline]
+    pub fn rotate(self, v: IVec3) -> IVec3 {
+        match self {
+            Self::PosY => v,
+            Self::NegY => IVec3::new(v.x, -v.y, -v.z),
+            Self::PosX => IVec3::new(v.y, -v.x, v.z),
+            Self::NegX => IVec3::new(-v.y, v.x, v.z),
+            Self::PosZ => IVec3::new(v.x, -v.z, v.y),
+            Self::NegZ => IVec3::new(v.x, v.z, -v.y),
+        }
+    }
+
+    /// The world face the voxel's own `face` ends up on.
+    #[inline]
+    pub fn rotate_face(self, face: Face) -> Face {
+        Face::from_offset(self.rotate(face.offset())).expect("rotations map faces onto faces")
+    }
+
+    /// The voxel's own face that ends up on the world's `face`, e.g. to pick its texture.
+    #[inline]
+    pub fn local_face(self, face: Face) -> Face {
+        self.inverse().rotate_face(face)
+    }
+
+    /// The [`UvTransform`] turning the texture of the world's `face` along with the voxel, so
+    /// e.g. the bark of a log lying on its side runs along its length rather than upright.
+    pub fn uv_transform(self, face: Face) -> UvTransform {
+        let axes = |face: Face, transform| {
+            face.texture_axes(transform)
+                .map(|axis| axis.round().as_ivec3())
+        };
+        let turned =
+            axes(self.local_face(face), UvTransform::IDENTITY).map(|axis| self.rotate(axis));
+
+        UvTransform::ALL
+            .into_iter()
+            .find(|transform| axes(face, *transform) == turned)
+            .unwrap_or_default()
+    }
 }
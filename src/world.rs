@@ -0,0 +1,518 @@
+use crate::{
+    chunk::{Chunk, ChunkModified},
+    face::Face,
+    voxel::Voxel,
+};
+use bevy::{
+    ecs::{
+        entity::Entity,
+        event::EventReader,
+        observer::Trigger,
+        system::{Commands, Query, Res, ResMut, Resource},
+        world::OnRemove,
+    },
+    math::{swizzles::Vec3Swizzles, IVec2, IVec3, UVec3, Vec3},
+    utils::HashMap,
+};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    ops::{Add, Sub},
+};
+
+/// Coordinate of a chunk, counted in chunks rather than voxels, so the two can't be mixed up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkPos(pub IVec3);
+
+impl ChunkPos {
+    #[inline]
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self(IVec3::new(x, y, z))
+    }
+
+    /// The chunk across `face`.
+    #[inline]
+    pub fn offset(self, face: Face) -> Self {
+        self + face.offset()
+    }
+
+    /// The face-adjacent chunks, indexed by [`Face::index`].
+    #[inline]
+    pub fn neighbors(self) -> [Self; 6] {
+        Face::ALL.map(|face| self.offset(face))
+    }
+
+    /// World position of the chunk's `(0, 0, 0)` voxel for chunks of `chunk_size` voxels.
+    #[inline]
+    pub fn to_world(self, chunk_size: UVec3) -> Vec3 {
+        (self.0 * chunk_size.as_ivec3()).as_vec3()
+    }
+}
+
+/// Formats as `(x, y, z)`, e.g. for the [`Name`](bevy::core::Name) of a chunk's entities.
+impl fmt::Display for ChunkPos {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let IVec3 { x, y, z } = self.0;
+        write!(f, "({x}, {y}, {z})")
+    }
+}
+
+impl From<IVec3> for ChunkPos {
+    #[inline]
+    fn from(pos: IVec3) -> Self {
+        Self(pos)
+    }
+}
+
+impl Add<IVec3> for ChunkPos {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, offset: IVec3) -> Self {
+        Self(self.0 + offset)
+    }
+}
+
+impl Sub<IVec3> for ChunkPos {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, offset: IVec3) -> Self {
+        Self(self.0 - offset)
+    }
+}
+
+/// Offset between two chunks, in chunks.
+impl Sub for ChunkPos {
+    type Output = IVec3;
+
+    #[inline]
+    fn sub(self, other: Self) -> IVec3 {
+        self.0 - other.0
+    }
+}
+
+/// Index of loaded chunk entities by chunk coordinate.
+///
+/// Every chunk in the world is assumed to share `chunk_dims`. Chunks despawned without going
+/// through [`unload_chunk`](Self::unload_chunk) are dropped from the index by
+/// [`forget_despawned_chunk`], so it never holds a dead entity.
+#[derive(Debug, Resource)]
+pub struct VoxelWorld {
+    chunks: HashMap<ChunkPos, Entity>,
+    /// The chunk y coordinates loaded in each `(x, z)` column, kept in step with `chunks`.
+    columns: HashMap<IVec2, BTreeSet<i32>>,
+    chunk_dims: UVec3,
+}
+
+impl Default for VoxelWorld {
+    fn default() -> Self {
+        Self::new(UVec3::splat(Chunk::SIZE as u32))
+    }
+}
+
+impl VoxelWorld {
+    #[inline]
+    pub fn new(chunk_dims: UVec3) -> Self {
+        Self {
+            chunks: HashMap::default(),
+            columns: HashMap::default(),
+            chunk_dims,
+        }
+    }
+
+    #[inline]
+    pub fn chunk_dims(&self) -> UVec3 {
+        self.chunk_dims
+    }
+
+    /// Records `entity` as the chunk at `pos`, returning the entity previously there.
+    #[inline]
+    pub fn insert_chunk(&mut self, pos: ChunkPos, entity: Entity) -> Option<Entity> {
+        self.columns.entry(pos.0.xz()).or_default().insert(pos.0.y);
+        self.chunks.insert(pos, entity)
+    }
+
+    pub fn remove_chunk(&mut self, pos: ChunkPos) -> Option<Entity> {
+        let entity = self.chunks.remove(&pos)?;
+        let column = pos.0.xz();
+        if let Some(ys) = self.columns.get_mut(&column) {
+            ys.remove(&pos.0.y);
+            if ys.is_empty() {
+                self.columns.remove(&column);
+            }
+        }
+
+        Some(entity)
+    }
+
+    /// Removes the chunk at `pos` from the index and despawns it; its
+    /// [`ChunkChild`](crate::chunk::ChunkChild) entities follow once
+    /// [`despawn_chunk_children`](crate::chunk::despawn_chunk_children) runs.
+    pub fn unload_chunk(&mut self, commands: &mut Commands, pos: ChunkPos) -> bool {
+        let Some(entity) = self.remove_chunk(pos) else {
+            return false;
+        };
+        commands.entity(entity).despawn();
+
+        true
+    }
+
+    #[inline]
+    pub fn chunk_at(&self, pos: ChunkPos) -> Option<Entity> {
+        self.chunks.get(&pos).copied()
+    }
+
+    /// The chunk holding the voxel at `world_pos`, a point in world space like a transform's
+    /// translation. Voxels are centered on whole coordinates scaled by [`Voxel::SIZE`].
+    #[inline]
+    pub fn chunk_containing(&self, world_pos: Vec3) -> Option<Entity> {
+        let position = (world_pos / Voxel::SIZE).round().as_ivec3();
+        self.chunk_at(self.split(position).0)
+    }
+
+    /// Splits a world voxel coordinate into its chunk coordinate and the voxel's local
+    /// coordinate inside that chunk.
+    #[inline]
+    pub fn split(&self, position: IVec3) -> (ChunkPos, UVec3) {
+        let dims = self.chunk_dims.as_ivec3();
+
+        (
+            ChunkPos(position.div_euclid(dims)),
+            position.rem_euclid(dims).as_uvec3(),
+        )
+    }
+
+    pub fn voxel(&self, chunks: &Query<&Chunk>, position: IVec3) -> Option<Voxel> {
+        let (pos, local) = self.split(position);
+        let chunk = chunks.get(self.chunk_at(pos)?).ok()?;
+
+        chunk
+            .get(local.x as usize, local.y as usize, local.z as usize)
+            .copied()
+    }
+
+    /// World y of the topmost non-air voxel of the column at world `(x, z)`, searching its loaded
+    /// chunks from the top down. `None` if every loaded voxel of the column is air.
+    pub fn surface_height(&self, chunks: &Query<&Chunk>, world_xz: IVec2) -> Option<i32> {
+        let position = IVec3::new(world_xz.x, 0, world_xz.y);
+        let (column, local) = self.split(position);
+        let ys = self.columns.get(&column.0.xz())?;
+
+        ys.iter().rev().find_map(|&y| {
+            let entity = self.chunk_at(ChunkPos(column.0.with_y(y)))?;
+            let height = chunks
+                .get(entity)
+                .ok()?
+                .surface_height(local.x as usize, local.z as usize)?;
+            Some(y * self.chunk_dims.y as i32 + height as i32)
+        })
+    }
+
+    /// Writes a voxel, to be reported by its chunk's next [`ChunkModified`]. Returns `false` if
+    /// the voxel's chunk isn't loaded.
+    ///
+    /// [`remesh_modified_chunks`] then remeshes the chunk along with every neighbor whose mesh
+    /// borders the voxel, since their faces and ambient occlusion sample it: one for a voxel on a
+    /// chunk face, three on an edge, seven on a corner.
+    pub fn set_voxel(&self, chunks: &mut Query<&mut Chunk>, position: IVec3, voxel: Voxel) -> bool {
+        let (pos, local) = self.split(position);
+        let Some(mut chunk) = self.chunk_at(pos).and_then(|e| chunks.get_mut(e).ok()) else {
+            return false;
+        };
+        chunk.set(local.x as usize, local.y as usize, local.z as usize, voxel);
+
+        true
+    }
+
+    /// Marks every chunk whose mesh shows the voxel at `position` urgent, so an edit there is
+    /// remeshed ahead of other dirty chunks.
+    pub fn mark_urgent(&self, chunks: &mut Query<&mut Chunk>, position: IVec3) {
+        let (pos, local) = self.split(position);
+        let offsets = std::iter::once(IVec3::ZERO).chain(self.border_neighbors(local, local));
+        offsets.for_each(|offset| {
+            if let Some(mut neighbor) = self
+                .chunk_at(pos + offset)
+                .and_then(|e| chunks.get_mut(e).ok())
+            {
+                neighbor.mark_urgent();
+            }
+        });
+    }
+
+    /// Offsets of the chunks whose meshes can see a voxel of the local region `min..=max`,
+    /// including the edge and corner neighbors ambient occlusion samples across, up to seven for
+    /// a corner voxel.
+    fn border_neighbors(&self, min: UVec3, max: UVec3) -> impl Iterator<Item = IVec3> {
+        let last = self.chunk_dims - UVec3::ONE;
+        let touches = move |offset: IVec3| {
+            (0..3).all(|axis| match offset[axis] {
+                -1 => min[axis] == 0,
+                1 => max[axis] == last[axis],
+                _ => true,
+            })
+        };
+
+        (-1..=1)
+            .flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| IVec3::new(x, y, z))))
+            .filter(move |offset| *offset != IVec3::ZERO && touches(*offset))
+    }
+}
+
+/// Drops a chunk from the [`VoxelWorld`] as it is despawned, unless another entity was already
+/// recorded at its position.
+pub fn forget_despawned_chunk(
+    trigger: Trigger<OnRemove, Chunk>,
+    chunks: Query<&Chunk>,
+    mut world: ResMut<VoxelWorld>,
+) {
+    let entity = trigger.entity();
+    let Ok(chunk) = chunks.get(entity) else {
+        return;
+    };
+
+    let pos = chunk.chunk_pos();
+    if world.chunk_at(pos) == Some(entity) {
+        world.remove_chunk(pos);
+    }
+}
+
+/// Marks every chunk a [`ChunkModified`] reports dirty, along with each neighbor, diagonal ones
+/// included, whose border the change touched, so they are remeshed.
+pub fn remesh_modified_chunks(
+    world: Res<VoxelWorld>,
+    mut events: EventReader<ChunkModified>,
+    mut chunks: Query<&mut Chunk>,
+) {
+    for event in events.read() {
+        if let Ok(mut chunk) = chunks.get_mut(event.chunk) {
+            chunk.mark_dirty();
+        }
+
+        let (min, max) = event
+            .region
+            .unwrap_or((UVec3::ZERO, world.chunk_dims - UVec3::ONE));
+        world.border_neighbors(min, max).for_each(|offset| {
+            if let Some(mut neighbor) = world
+                .chunk_at(event.coord + offset)
+                .and_then(|e| chunks.get_mut(e).ok())
+            {
+                neighbor.mark_dirty();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{
+        app::App,
+        ecs::{
+            event::Events,
+            system::{IntoSystem, System, SystemState},
+            world::World,
+        },
+    };
+
+    /// A world holding `chunks` at their positions, and the state of a query reading them.
+    fn world_of(
+        chunks: impl IntoIterator<Item = (ChunkPos, Chunk)>,
+    ) -> (
+        World,
+        VoxelWorld,
+        SystemState<Query<'static, 'static, &'static Chunk>>,
+    ) {
+        let mut world = World::new();
+        let mut voxels = VoxelWorld::default();
+        for (pos, chunk) in chunks {
+            voxels.insert_chunk(pos, world.spawn(chunk).id());
+        }
+        let state = SystemState::new(&mut world);
+        (world, voxels, state)
+    }
+
+    #[test]
+    fn chunks_round_trip_through_the_index() {
+        let mut voxels = VoxelWorld::default();
+        let [first, second] = [1, 2].map(Entity::from_raw);
+        let pos = ChunkPos::new(-3, 0, 7);
+
+        assert_eq!(voxels.insert_chunk(pos, first), None);
+        assert_eq!(voxels.chunk_at(pos), Some(first));
+        assert_eq!(voxels.chunk_at(ChunkPos::new(3, 0, 7)), None);
+        assert_eq!(voxels.insert_chunk(pos, second), Some(first));
+        assert_eq!(voxels.chunk_at(pos), Some(second));
+
+        assert_eq!(voxels.remove_chunk(pos), Some(second));
+        assert_eq!(voxels.chunk_at(pos), None);
+        assert_eq!(voxels.remove_chunk(pos), None);
+    }
+
+    #[test]
+    fn negative_positions_round_down_into_their_chunk() {
+        let mut voxels = VoxelWorld::default();
+        let size = Chunk::SIZE as i32;
+        assert_eq!(
+            voxels.split(IVec3::new(-1, -size, -size - 1)),
+            (ChunkPos::new(-1, -1, -2), UVec3::new(15, 0, 15))
+        );
+        assert_eq!(
+            voxels.split(IVec3::new(0, size - 1, -size)),
+            (ChunkPos::new(0, 0, -1), UVec3::new(0, 15, 0))
+        );
+
+        let entity = Entity::from_raw(1);
+        voxels.insert_chunk(ChunkPos::new(-1, -1, -2), entity);
+        // Voxels are centered on whole coordinates, so -0.5 is already in the voxel at -1.
+        let chunk_containing = |x, y, z| voxels.chunk_containing(Vec3::new(x, y, z));
+        assert_eq!(chunk_containing(-0.5, -16.0, -17.0), Some(entity));
+        assert_eq!(chunk_containing(-16.4, -1.0, -32.4), Some(entity));
+        assert_eq!(chunk_containing(-0.4, -16.0, -17.0), None);
+        assert_eq!(chunk_containing(-16.6, -16.0, -17.0), None);
+    }
+
+    #[test]
+    fn despawned_chunks_leave_the_index() {
+        let mut app = App::new();
+        app.init_resource::<VoxelWorld>()
+            .observe(forget_despawned_chunk);
+        let pos = ChunkPos::new(2, -1, 3);
+        let mut spawn_chunk = || {
+            let mut chunk = Chunk::new(pos.0.as_vec3());
+            chunk.set(0, 0, 0, Voxel::new(1));
+            app.world_mut().spawn(chunk).id()
+        };
+        let [stale, current] = [spawn_chunk(), spawn_chunk()];
+        let above = ChunkPos::new(2, 0, 3);
+        let other = app.world_mut().spawn(Chunk::new(above.0.as_vec3())).id();
+        let mut voxels = app.world_mut().resource_mut::<VoxelWorld>();
+        voxels.insert_chunk(pos, current);
+        voxels.insert_chunk(above, other);
+
+        // A chunk no longer indexed leaves the one that replaced it.
+        app.world_mut().despawn(stale);
+        assert_eq!(
+            app.world().resource::<VoxelWorld>().chunk_at(pos),
+            Some(current)
+        );
+
+        let column = IVec2::new(2 * Chunk::SIZE as i32, 3 * Chunk::SIZE as i32);
+        let mut state = SystemState::<Query<&Chunk>>::new(app.world_mut());
+        let height = |app: &App, state: &mut SystemState<Query<&Chunk>>| {
+            let world = app.world().resource::<VoxelWorld>();
+            world.surface_height(&state.get(app.world()), column)
+        };
+        assert_eq!(height(&app, &mut state), Some(-(Chunk::SIZE as i32)));
+
+        app.world_mut().despawn(current);
+        app.world_mut().despawn(other);
+        let world = app.world().resource::<VoxelWorld>();
+        assert_eq!(world.chunk_at(pos), None);
+        assert_eq!(world.chunk_at(above), None);
+        assert!(world.columns.is_empty());
+        assert_eq!(height(&app, &mut state), None);
+    }
+
+    #[test]
+    fn surface_height_searches_the_loaded_chunks_of_a_column() {
+        let size = Chunk::SIZE;
+        let mut bottom = Chunk::new(Vec3::ZERO);
+        (0..size).for_each(|y| bottom.set(1, y, 1, Voxel::new(1)));
+        bottom.set(2, 0, 2, Voxel::new(1));
+        let mut top = Chunk::new(Vec3::ZERO);
+        top.set(2, 3, 2, Voxel::new(1));
+        let (world, voxels, mut state) = world_of([
+            (ChunkPos::new(0, -1, 0), bottom),
+            (ChunkPos::new(0, 0, 0), Chunk::new(Vec3::ZERO)),
+            (ChunkPos::new(0, 1, 0), top),
+        ]);
+        let chunks = state.get(&world);
+
+        let size = size as i32;
+        assert_eq!(voxels.surface_height(&chunks, IVec2::new(1, 1)), Some(-1));
+        assert_eq!(
+            voxels.surface_height(&chunks, IVec2::new(2, 2)),
+            Some(size + 3)
+        );
+        assert_eq!(voxels.surface_height(&chunks, IVec2::new(3, 3)), None);
+        assert_eq!(
+            voxels.surface_height(&chunks, IVec2::new(size + 1, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn surface_height_follows_chunks_in_and_out_of_a_column() {
+        let solid = |y| {
+            let mut chunk = Chunk::new(Vec3::ZERO);
+            chunk.set(0, y, 0, Voxel::new(1));
+            chunk
+        };
+        let (mut world, mut voxels, mut state) = world_of([
+            (ChunkPos::new(0, -2, 0), solid(5)),
+            (ChunkPos::new(0, 3, 0), solid(1)),
+        ]);
+        let size = Chunk::SIZE as i32;
+        let height = |world: &World, voxels: &VoxelWorld, state: &mut SystemState<_>| {
+            voxels.surface_height(&state.get(world), IVec2::ZERO)
+        };
+        assert_eq!(height(&world, &voxels, &mut state), Some(3 * size + 1));
+
+        // Replacing a chunk keeps the column, and the rest of it stays searchable.
+        let replaced = world.spawn(solid(7)).id();
+        voxels.insert_chunk(ChunkPos::new(0, 3, 0), replaced);
+        assert_eq!(height(&world, &voxels, &mut state), Some(3 * size + 7));
+        voxels.remove_chunk(ChunkPos::new(0, 3, 0));
+        assert_eq!(height(&world, &voxels, &mut state), Some(-2 * size + 5));
+
+        voxels.remove_chunk(ChunkPos::new(0, -2, 0));
+        assert_eq!(voxels.remove_chunk(ChunkPos::new(0, -2, 0)), None);
+        assert_eq!(height(&world, &voxels, &mut state), None);
+        assert!(voxels.columns.is_empty());
+    }
+
+    #[test]
+    fn border_edits_dirty_the_neighbors_that_see_them() {
+        let mut world = World::new();
+        world.init_resource::<Events<ChunkModified>>();
+        let mut voxels = VoxelWorld::default();
+        let mut center = Entity::PLACEHOLDER;
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let entity = world.spawn(Chunk::new(Vec3::ZERO)).id();
+                    voxels.insert_chunk(ChunkPos::new(x, y, z), entity);
+                    if (x, y, z) == (0, 0, 0) {
+                        center = entity;
+                    }
+                }
+            }
+        }
+        world.insert_resource(voxels);
+        let mut remesh = IntoSystem::into_system(remesh_modified_chunks);
+        remesh.initialize(&mut world);
+
+        let last = Chunk::SIZE as u32 - 1;
+        for (voxel, neighbors) in [
+            (UVec3::splat(5), 0),
+            (UVec3::new(last, 5, 5), 1),
+            (UVec3::new(last, 0, 5), 3),
+            (UVec3::new(last, 0, last), 7),
+        ] {
+            let mut chunks = world.query::<&mut Chunk>();
+            chunks
+                .iter_mut(&mut world)
+                .for_each(|mut chunk| chunk.mark_clean());
+            world.send_event(ChunkModified {
+                chunk: center,
+                coord: ChunkPos::default(),
+                region: Some((voxel, voxel)),
+            });
+            remesh.run((), &mut world);
+
+            let dirty = chunks.iter(&world).filter(|chunk| chunk.is_dirty()).count();
+            assert_eq!(dirty, 1 + neighbors, "edit at {voxel}");
+        }
+    }
+}